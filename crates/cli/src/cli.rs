@@ -1,6 +1,21 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use opencode_core::ask;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use opencode_core::config::Config;
+use futures::stream::{BoxStream, StreamExt};
+use opencode_core::error::Result as CoreResult;
+use opencode_core::provider::{CompletionResponse, Message, ModelInfo, Role, StreamChunk};
+use opencode_core::{ask_with_persona_response, ask_with_persona_stream, get_service_container, tokenize};
+use opencode_core::container::{ContainerLimits, ContainerManager};
+use opencode_core::git::GitCheckpointManager;
+use opencode_core::personas::{self, Persona};
+use opencode_core::service::{HealthProbe, ProviderHealth, HEALTH_CHECK_TIMEOUT};
+use opencode_core::supervisor::{AgentStatus, AgentSupervisor};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tracing::{info, error};
 
 #[derive(Parser, Debug, Clone)]
@@ -16,27 +31,84 @@ pub struct Cli {
     /// Configuration file path
     #[arg(short, long)]
     pub config: Option<String>,
+    
+    /// Log the container commands agents would run instead of running them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
 }
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
-    /// Agent management commands
+    /// Agent management commands. Agents only live as long as the process
+    /// that spawned them, so all but the checkpoint commands run in the REPL.
     #[command(subcommand)]
     Agent(AgentCommands),
     
     /// Ask a question directly
     Ask {
         /// The question to ask
+        #[arg(allow_hyphen_values = true)]
         question: String,
+
+        /// The rest of the question when it isn't quoted
+        #[arg(hide = true)]
+        words: Vec<String>,
         
         /// Persona to use for the response
         #[arg(short, long, default_value = "default")]
         persona: String,
+        
+        /// Print the response as JSON with the model and token usage
+        #[arg(long, conflicts_with = "stream")]
+        json: bool,
+
+        /// Print the response as it is generated
+        #[arg(short, long)]
+        stream: bool,
+    },
+    
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
     },
     
+    /// Check that the container runtime, git and the config are usable
+    Doctor,
+    
+    /// Check that every configured provider is reachable
+    Health {
+        /// Probe with a one-token completion instead of listing models
+        #[arg(long)]
+        completion: bool,
+    },
+    
+    /// List the models a provider offers
+    Models {
+        /// Provider to ask; defaults to the default provider
+        #[arg(short, long)]
+        provider: Option<String>,
+    },
+    
+    /// Persona management commands
+    #[command(subcommand)]
+    Persona(PersonaCommands),
+    
     /// Start interactive REPL mode
     Repl,
     
+    /// Estimate how many tokens a file costs as a prompt
+    Tokens {
+        /// File whose content is counted as a user message
+        #[arg(long)]
+        file: PathBuf,
+        
+        /// Model whose tokenizer is used; defaults to the configured model
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+    
     /// Show version information
     Version,
 }
@@ -54,6 +126,14 @@ pub enum AgentCommands {
         /// Agent persona
         #[arg(short, long, default_value = "rusty")]
         persona: String,
+        
+        /// Number of agents to spawn; more than one are numbered `<id>-1`, `<id>-2`, ...
+        #[arg(short = 'n', long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        count: u32,
+
+        /// Label to tag the agent with, as `key=value`; repeatable
+        #[arg(short, long = "label", value_name = "KEY=VALUE", value_parser = parse_label)]
+        labels: Vec<(String, String)>,
     },
     
     /// Stop an agent
@@ -67,41 +147,469 @@ pub enum AgentCommands {
         /// Agent identifier
         id: String,
     },
+    
+    /// Show an agent's recent output
+    Logs {
+        /// Agent identifier
+        id: String,
+    },
+    
+    /// Queue a task for a running agent
+    Send {
+        /// Agent identifier
+        id: String,
+
+        /// Task to send; the remaining words are joined with spaces
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        task: Vec<String>,
+    },
+    
+    /// Restart an agent on its existing branch
+    Restart {
+        /// Agent identifier
+        id: String,
+    },
+    
+    /// Save a checkpoint of an agent's branch
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Checkpoint {
+        /// Agent identifier
+        #[arg(required = true)]
+        id: Option<String>,
+        
+        /// Checkpoint commit message
+        #[arg(short, long, required = true)]
+        message: Option<String>,
+        
+        #[command(subcommand)]
+        action: Option<CheckpointCommands>,
+    },
+    
+    /// List an agent's checkpoints
+    Checkpoints {
+        /// Agent identifier
+        id: String,
+    },
+    
+    /// Create a new agent branch from a checkpoint
+    Restore {
+        /// Checkpoint tag, e.g. cp/alice/<uuid>
+        tag: String,
+        
+        /// Identifier of the new agent
+        #[arg(long = "as")]
+        new_id: String,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CheckpointCommands {
+    /// Show what a checkpoint changed
+    Show {
+        /// Checkpoint tag, e.g. cp/alice/<uuid>
+        tag: String,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum PersonaCommands {
+    /// List configured personas
+    Ls,
+    
+    /// Show a persona's full system prompt
+    Show {
+        /// Persona name
+        name: String,
+    },
+    
+    /// Add a persona to personas.yml
+    Add {
+        /// Persona name
+        name: String,
+        
+        /// System prompt text
+        #[arg(long, conflicts_with = "prompt_file", required_unless_present = "prompt_file")]
+        prompt: Option<String>,
+        
+        /// Read the system prompt from a file
+        #[arg(long)]
+        prompt_file: Option<PathBuf>,
+    },
+}
+
+impl AgentCommands {
+    /// Whether the command works on agents running in this process, as
+    /// opposed to checkpoints, which are kept in git
+    fn manages_running_agents(&self) -> bool {
+        !matches!(
+            self,
+            AgentCommands::Checkpoint { .. } | AgentCommands::Checkpoints { .. } | AgentCommands::Restore { .. }
+        )
+    }
+}
+
+static SUPERVISOR: OnceLock<Arc<AgentSupervisor>> = OnceLock::new();
+
+/// The supervisor owning every agent started by this process, so they can be
+/// shut down together when the CLI exits
+pub fn supervisor() -> Arc<AgentSupervisor> {
+    init_supervisor(false)
+}
+
+/// Create the process-wide supervisor. With `dry_run`, agents log the
+/// container commands they would run instead of starting containers. Has no
+/// effect once the supervisor exists.
+pub fn init_supervisor(dry_run: bool) -> Arc<AgentSupervisor> {
+    SUPERVISOR
+        .get_or_init(|| {
+            let supervisor = AgentSupervisor::new()
+                .with_container_manager(
+                    ContainerManager::new()
+                        .with_dry_run(dry_run)
+                        .with_limits(configured_container_limits()),
+                )
+                .with_tee_logs(true);
+            let supervisor = match configured_max_agents() {
+                Some(max_agents) => supervisor.with_max_agents(max_agents),
+                None => supervisor,
+            };
+            Arc::new(match configured_agent_timeout() {
+                Some(timeout) => supervisor.with_agent_timeout(timeout),
+                None => supervisor,
+            })
+        })
+        .clone()
+}
+
+/// Container limits from the loaded config, or none if the core isn't initialized
+fn configured_container_limits() -> ContainerLimits {
+    get_service_container()
+        .map(|container| container.config().container_limits.clone())
+        .unwrap_or_default()
+}
+
+/// Agent timeout from the loaded config, or none if the core isn't initialized
+fn configured_agent_timeout() -> Option<Duration> {
+    get_service_container()
+        .ok()
+        .and_then(|container| container.config().agent_timeout_seconds)
+        .map(|seconds| Duration::from_secs(seconds.into()))
+}
+
+/// Agent limit from the loaded config, or the default limit if the core isn't initialized
+fn configured_max_agents() -> Option<usize> {
+    get_service_container()
+        .map(|container| container.config().agent_limit())
+        .unwrap_or_else(|_| Config::default().agent_limit())
 }
 
 pub async fn execute_command(command: Commands) -> Result<()> {
     match command {
         Commands::Agent(agent_cmd) => execute_agent_command(agent_cmd).await,
-        Commands::Ask { question, persona } => execute_ask_command(&question, &persona).await,
-        Commands::Repl => {
-            // This should not happen in practice since None case goes to REPL
-            // But we handle it for completeness
-            crate::repl::start().await
-        },
+        Commands::Ask { question, words, persona, json, stream } => {
+            execute_ask_command(&full_question(&question, &words), &persona, json, stream).await
+        }
+        Commands::Completions { shell } => {
+            print!("{}", completions_script(shell));
+            Ok(())
+        }
+        // `main` runs doctor itself with the `--config` path, before the config is loaded
+        Commands::Doctor => execute_doctor_command(None).await,
+        Commands::Health { completion } => execute_health_command(completion).await,
+        Commands::Models { provider } => {
+            println!("{}", models_command_output(provider.as_deref()).await?);
+            Ok(())
+        }
+        Commands::Persona(persona_cmd) => execute_persona_command(persona_cmd),
+        Commands::Repl => crate::repl::start().await,
+        Commands::Tokens { file, model } => {
+            println!("{}", tokens_command_output(&file, model.as_deref())?);
+            Ok(())
+        }
         Commands::Version => {
             execute_version_command().await
         },
     }
 }
 
-async fn execute_agent_command(_command: AgentCommands) -> Result<()> {
-    println!("Agent commands are not yet implemented");
+/// Render the models offered by `provider`, or the default provider; shared with the REPL
+pub async fn models_command_output(provider: Option<&str>) -> Result<String> {
+    let container = get_service_container()?;
+    let (name, models) = match provider {
+        Some(name) => (name.to_string(), container.list_models(name).await?),
+        None => {
+            let provider = container.get_default_provider()?;
+            (provider.name().to_string(), provider.list_models().await?)
+        }
+    };
+    Ok(format_models(&name, &models))
+}
+
+fn format_models(provider: &str, models: &[ModelInfo]) -> String {
+    if models.is_empty() {
+        return format!("Provider '{}' doesn't list its models", provider);
+    }
+
+    let width = models.iter().map(|m| m.id.len()).max().unwrap_or(0);
+    models
+        .iter()
+        .map(|model| {
+            let window = match model.context_window {
+                Some(tokens) => format!("{} tokens", tokens),
+                None => "context window unknown".to_string(),
+            };
+            format!("{:<width$}  {}", model.id, window, width = width)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Completion script for `shell`, generated from the `Cli` definition
+pub fn completions_script(shell: Shell) -> String {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+async fn execute_agent_command(command: AgentCommands) -> Result<()> {
+    if command.manages_running_agents() {
+        anyhow::bail!(
+            "Agents only live as long as the process that spawned them; run `opencode repl` and use `agent` commands there"
+        );
+    }
+    println!("{}", agent_command_output(&supervisor(), command).await?);
     Ok(())
 }
 
-async fn execute_ask_command(question: &str, persona: &str) -> Result<()> {
-    info!("Asking question with persona '{}'", persona);
-    
-    // For now, just use regular ask - persona support will be added later
-    let prompt = if persona != "default" {
-        format!("Acting as {}: {}", persona, question)
-    } else {
-        question.to_string()
+/// Run an agent command against the agents of `supervisor`, returning what
+/// it prints
+pub async fn agent_command_output(supervisor: &AgentSupervisor, command: AgentCommands) -> Result<String> {
+    match command {
+        AgentCommands::Checkpoint { .. }
+        | AgentCommands::Checkpoints { .. }
+        | AgentCommands::Restore { .. } => checkpoint_command_output(&GitCheckpointManager::new("."), command),
+        AgentCommands::Spawn { id, persona, count, labels } => {
+            let personas = personas::load_personas()?;
+            let labels = labels.into_iter().collect();
+            spawn_command_output(supervisor, &personas, &id, &persona, count, labels).await
+        }
+        AgentCommands::Status { id } => status_command_output(supervisor, &id).await,
+        AgentCommands::Send { id, task } => send_command_output(supervisor, &id, &task.join(" ")).await,
+        AgentCommands::Logs { id } => logs_command_output(supervisor, &id).await,
+        AgentCommands::Ls => Ok(ls_command_output(supervisor).await),
+        AgentCommands::Stop { id } => {
+            supervisor.stop(&id).await?;
+            Ok(format!("Stopped {}", id))
+        }
+        AgentCommands::Restart { id } => restart_command_output(supervisor, &id).await,
+    }
+}
+
+/// Parse a `key=value` label argument
+fn parse_label(arg: &str) -> std::result::Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("invalid label '{}', expected key=value", arg)),
+    }
+}
+
+/// Spawn `count` agents after checking that `persona` exists
+async fn spawn_command_output(
+    supervisor: &AgentSupervisor,
+    personas: &HashMap<String, Persona>,
+    id: &str,
+    persona: &str,
+    count: u32,
+    labels: HashMap<String, String>,
+) -> Result<String> {
+    find_persona(personas, persona)?;
+    let ids = supervisor.spawn_pool_with_labels(id, persona, count as usize, labels).await?;
+    Ok(format!("Spawned {} with persona '{}'", ids.join(", "), persona))
+}
+
+/// Describe the status of agent `id`
+async fn status_command_output(supervisor: &AgentSupervisor, id: &str) -> Result<String> {
+    Ok(match supervisor.get_status(id).await? {
+        AgentStatus::Error(message) => format!("{}: Error ({})", id, message),
+        status => format!("{}: {:?}", id, status),
+    })
+}
+
+/// Queue `task` for agent `id`
+async fn send_command_output(supervisor: &AgentSupervisor, id: &str, task: &str) -> Result<String> {
+    supervisor.send_task(id, task).await?;
+    Ok(format!("Sent task to {}", id))
+}
+
+/// One line per agent with its status and persona, sorted by id
+async fn ls_command_output(supervisor: &AgentSupervisor) -> String {
+    let mut agents = supervisor.list().await;
+    if agents.is_empty() {
+        return "No agents running".to_string();
+    }
+    agents.sort_by(|a, b| a.id.cmp(&b.id));
+    agents
+        .iter()
+        .map(|agent| format!("{:<16} {:<10} {}", agent.id, format!("{:?}", agent.status), agent.persona))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Restart agent `id` on its existing branch
+async fn restart_command_output(supervisor: &AgentSupervisor, id: &str) -> Result<String> {
+    supervisor.restart(id).await?;
+    Ok(format!("Restarted {}", id))
+}
+
+/// The recent output of agent `id`, oldest line first
+async fn logs_command_output(supervisor: &AgentSupervisor, id: &str) -> Result<String> {
+    let lines = supervisor.get_logs(id).await?;
+    if lines.is_empty() {
+        return Ok(format!("No output from {} yet", id));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Render the output of a checkpoint command run against `manager`
+fn checkpoint_command_output(manager: &GitCheckpointManager, command: AgentCommands) -> Result<String> {
+    match command {
+        AgentCommands::Checkpoint { action: Some(CheckpointCommands::Show { tag }), .. } => {
+            let diff = manager.diff_checkpoint(&tag)?;
+            if diff.is_empty() {
+                return Ok(format!("Checkpoint {} has no changes", tag));
+            }
+            Ok(diff.trim_end().to_string())
+        }
+        AgentCommands::Checkpoint { id: Some(id), message: Some(message), action: None } => {
+            let tag = manager.save_checkpoint(&format!("agent/{}", id), &message)?;
+            Ok(format!("Created checkpoint {}", tag))
+        }
+        AgentCommands::Checkpoints { id } => {
+            let checkpoints = manager.list_checkpoints(&id)?;
+            if checkpoints.is_empty() {
+                return Ok(format!("No checkpoints found for agent '{}'", id));
+            }
+            Ok(checkpoints.join("\n"))
+        }
+        AgentCommands::Restore { tag, new_id } => {
+            let branch = manager.restore_checkpoint(&tag, &new_id)?;
+            Ok(format!("Restored {} to branch {}", tag, branch))
+        }
+        other => anyhow::bail!("{:?} is not a checkpoint command", other),
+    }
+}
+
+fn execute_persona_command(command: PersonaCommands) -> Result<()> {
+    println!("{}", persona_command_output(command)?);
+    Ok(())
+}
+
+/// Render the output of a persona command; shared with the REPL
+pub fn persona_command_output(command: PersonaCommands) -> Result<String> {
+    match command {
+        PersonaCommands::Ls => Ok(format_persona_list(&personas::load_personas()?)),
+        PersonaCommands::Show { name } => format_persona(&personas::load_personas()?, &name),
+        PersonaCommands::Add { name, prompt, prompt_file } => {
+            let system_prompt = match (prompt, prompt_file) {
+                (Some(prompt), _) => prompt,
+                (None, Some(path)) => std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read prompt file {}", path.display()))?,
+                (None, None) => anyhow::bail!("Either --prompt or --prompt-file is required"),
+            };
+
+            let path = personas::add_persona(Persona { name: name.clone(), system_prompt, ..Default::default() })?;
+            Ok(format!("Added persona '{}' to {}", name, path.display()))
+        }
+    }
+}
+
+/// Render the output of the tokens command; shared with the REPL
+pub fn tokens_command_output(file: &Path, model: Option<&str>) -> Result<String> {
+    let content = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let messages = [Message {
+        role: Role::User,
+        content,
+    }];
+
+    let container = get_service_container().ok();
+    let model = match (model, container) {
+        (Some(model), _) => model.to_string(),
+        (None, Some(container)) => container.config().openai.default_model.clone(),
+        (None, None) => Config::default().openai.default_model,
+    };
+    let count = match container {
+        Some(container) => container.count_tokens(&model, &messages),
+        None => tokenize::count_message_tokens(tokenize::tokenizer_for(&model), &messages),
     };
+
+    Ok(format!("{} tokens ({})", count, model))
+}
+
+fn format_persona_list(personas: &HashMap<String, Persona>) -> String {
+    if personas.is_empty() {
+        return no_personas_hint();
+    }
+
+    let mut personas: Vec<&Persona> = personas.values().collect();
+    personas.sort_by(|a, b| a.name.cmp(&b.name));
+
+    personas
+        .iter()
+        .map(|p| format!("{:<16} {}", p.name, p.system_prompt.lines().next().unwrap_or("")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_persona(personas: &HashMap<String, Persona>, name: &str) -> Result<String> {
+    let persona = find_persona(personas, name)?;
+    Ok(format!("{}\n\n{}", persona.name, persona.system_prompt))
+}
+
+fn find_persona<'a>(personas: &'a HashMap<String, Persona>, name: &str) -> Result<&'a Persona> {
+    if personas.is_empty() {
+        anyhow::bail!("Persona '{}' not found. {}", name, no_personas_hint());
+    }
+
+    personas
+        .get(name)
+        .with_context(|| format!("Persona '{}' not found. Run `opencode persona ls` to see available personas", name))
+}
+
+fn no_personas_hint() -> String {
+    match personas::get_config_path_no_create() {
+        Ok(dir) => format!(
+            "No personas configured. Add them to {}",
+            dir.join("personas.yml").display()
+        ),
+        Err(_) => "No personas configured. Add them to personas.yml in the opencode config directory".to_string(),
+    }
+}
+
+/// `question` followed by the unquoted words after it
+pub fn full_question(question: &str, words: &[String]) -> String {
+    std::iter::once(question)
+        .chain(words.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn execute_ask_command(question: &str, persona: &str, json: bool, stream: bool) -> Result<()> {
+    info!("Asking question with persona '{}'", persona);
+
+    if stream {
+        let chunks = ask_with_persona_stream(question, persona).await?;
+        return write_stream(chunks, &mut std::io::stdout()).await;
+    }
     
-    match ask(&prompt).await {
+    match ask_with_persona_response(question, persona).await {
+        Ok(response) if json => {
+            println!("{}", ask_json_output(&response)?);
+        }
         Ok(response) => {
-            println!("{}", response);
+            println!("{}", response.content);
         }
         Err(e) => {
             error!("Failed to get response: {}", e);
@@ -112,6 +620,138 @@ async fn execute_ask_command(question: &str, persona: &str) -> Result<()> {
     Ok(())
 }
 
+/// Write each chunk of `chunks` to `out` as it arrives, then a newline. A
+/// failed stream still ends the partial output with a newline before its
+/// error is returned.
+async fn write_stream(mut chunks: BoxStream<'static, CoreResult<StreamChunk>>, out: &mut impl Write) -> Result<()> {
+    while let Some(chunk) = chunks.next().await {
+        match chunk {
+            Ok(chunk) => {
+                out.write_all(chunk.delta.as_bytes())?;
+                out.flush()?;
+            }
+            Err(e) => {
+                writeln!(out)?;
+                return Err(anyhow::Error::new(e).context("Response stream failed"));
+            }
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// The `ask --json` rendering of a response
+fn ask_json_output(response: &CompletionResponse) -> Result<String> {
+    let output = serde_json::json!({
+        "content": response.content,
+        "model": response.model,
+        "usage": response.usage,
+    });
+    Ok(serde_json::to_string_pretty(&output)?)
+}
+
+/// Outcome of one `doctor` check: what was found, or why it failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub result: std::result::Result<String, String>,
+}
+
+/// Run every environment check agents depend on
+pub async fn doctor_checks(containers: &ContainerManager, config_path: Option<&str>) -> Vec<DoctorCheck> {
+    let container_use = containers
+        .check_cu_exists()
+        .await
+        .map(|_| "cu is installed".to_string())
+        .map_err(|e| e.to_string());
+
+    let git = match std::process::Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Err(format!("git not found: {}", e)),
+    };
+
+    let config = Config::load(config_path)
+        .map(|config| {
+            let source = config_path.unwrap_or("environment");
+            format!("loaded from {} (default model {})", source, config.openai.default_model)
+        })
+        .map_err(|e| match config_path {
+            Some(path) => format!("{}: {}", path, e),
+            None => e.to_string(),
+        });
+
+    vec![
+        DoctorCheck { name: "container-use", result: container_use },
+        DoctorCheck { name: "git", result: git },
+        DoctorCheck { name: "config", result: config },
+    ]
+}
+
+/// One line per check, marked ok or FAIL
+pub fn format_doctor_report(checks: &[DoctorCheck]) -> String {
+    checks
+        .iter()
+        .map(|check| match &check.result {
+            Ok(detail) => format!("[ok]   {}: {}", check.name, detail),
+            Err(reason) => format!("[FAIL] {}: {}", check.name, reason),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Print the doctor report, failing if any check failed
+pub async fn execute_doctor_command(config_path: Option<&str>) -> Result<()> {
+    let checks = doctor_checks(&ContainerManager::new(), config_path).await;
+    println!("{}", format_doctor_report(&checks));
+
+    let failed = checks.iter().filter(|check| check.result.is_err()).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} checks failed", failed, checks.len());
+    }
+    Ok(())
+}
+
+/// Ping every provider in the service container
+pub async fn provider_health(completion: bool) -> Result<HashMap<String, ProviderHealth>> {
+    let probe = if completion { HealthProbe::Completion } else { HealthProbe::ListModels };
+    Ok(get_service_container()?.health_check_with(probe, HEALTH_CHECK_TIMEOUT).await)
+}
+
+/// One line per provider, sorted by name and marked ok or FAIL
+pub fn format_health_report(health: &HashMap<String, ProviderHealth>) -> String {
+    if health.is_empty() {
+        return "No providers configured".to_string();
+    }
+
+    let mut names: Vec<&String> = health.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let provider = &health[name];
+            let millis = provider.latency.as_millis();
+            match &provider.error {
+                None => format!("[ok]   {}: reachable in {} ms", name, millis),
+                Some(error) => format!("[FAIL] {}: {} (after {} ms)", name, error, millis),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Print the provider health report, failing if any provider is unreachable
+async fn execute_health_command(completion: bool) -> Result<()> {
+    let health = provider_health(completion).await?;
+    println!("{}", format_health_report(&health));
+
+    let unreachable = health.values().filter(|provider| !provider.reachable).count();
+    if unreachable > 0 {
+        anyhow::bail!("{} of {} providers unreachable", unreachable, health.len());
+    }
+    Ok(())
+}
+
 async fn execute_version_command() -> Result<()> {
     println!("OpenCode-RS CLI v{}", env!("CARGO_PKG_VERSION"));
     Ok(())
@@ -120,7 +760,7 @@ async fn execute_version_command() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use clap::CommandFactory;
+    use opencode_core::provider::{CompletionRequest, EchoProvider, LLMProvider};
     use pretty_assertions::assert_eq;
     use test_case::test_case;
 
@@ -153,12 +793,157 @@ mod tests {
         let cli = Cli::try_parse_from(["opencode", "agent", "spawn", "test-agent", "--persona", "test-persona"]).unwrap();
         
         match cli.command {
-            Some(Commands::Agent(AgentCommands::Spawn { id, persona })) => {
+            Some(Commands::Agent(AgentCommands::Spawn { id, persona, count, labels })) => {
                 assert_eq!(id, "test-agent");
                 assert_eq!(persona, "test-persona");
+                assert_eq!(count, 1);
+                assert!(labels.is_empty());
+            }
+            _ => panic!("Expected agent spawn command"),
+        }
+    }
+
+    #[test]
+    fn test_dry_run_flag() {
+        let cli = Cli::try_parse_from(["opencode", "agent", "spawn", "worker", "--dry-run"]).unwrap();
+        assert!(cli.dry_run);
+
+        let cli = Cli::try_parse_from(["opencode", "agent", "spawn", "worker"]).unwrap();
+        assert!(!cli.dry_run);
+    }
+
+    #[test]
+    fn test_agent_spawn_count_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "agent", "spawn", "worker", "-n", "3", "--persona", "rusty"]).unwrap();
+
+        match cli.command {
+            Some(Commands::Agent(AgentCommands::Spawn { id, persona, count, .. })) => {
+                assert_eq!(id, "worker");
+                assert_eq!(persona, "rusty");
+                assert_eq!(count, 3);
             }
             _ => panic!("Expected agent spawn command"),
         }
+        assert!(Cli::try_parse_from(["opencode", "agent", "spawn", "worker", "--count", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_agent_spawn_repeated_labels() {
+        let cli = Cli::try_parse_from([
+            "opencode", "agent", "spawn", "worker",
+            "--label", "project=web",
+            "-l", "owner=sam",
+            "--label", "note=a=b",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Some(Commands::Agent(AgentCommands::Spawn { labels, .. })) => {
+                assert_eq!(
+                    labels,
+                    vec![
+                        ("project".to_string(), "web".to_string()),
+                        ("owner".to_string(), "sam".to_string()),
+                        ("note".to_string(), "a=b".to_string()),
+                    ]
+                );
+            }
+            _ => panic!("Expected agent spawn command"),
+        }
+        assert!(Cli::try_parse_from(["opencode", "agent", "spawn", "worker", "--label", "project"]).is_err());
+        assert!(Cli::try_parse_from(["opencode", "agent", "spawn", "worker", "--label", "=web"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_status_command_output() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("worker", "rusty").await.unwrap();
+        assert_eq!(status_command_output(&supervisor, "worker").await.unwrap(), "worker: Running");
+
+        supervisor.update_agent_status("worker", AgentStatus::Error("container exited".to_string())).await.unwrap();
+        assert_eq!(
+            status_command_output(&supervisor, "worker").await.unwrap(),
+            "worker: Error (container exited)"
+        );
+
+        let err = status_command_output(&supervisor, "ghost").await.unwrap_err();
+        assert!(err.to_string().contains("Agent 'ghost' not found"));
+    }
+
+    #[tokio::test]
+    async fn test_restart_command_output() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("worker", "rusty").await.unwrap();
+        supervisor.stop("worker").await.unwrap();
+
+        assert_eq!(restart_command_output(&supervisor, "worker").await.unwrap(), "Restarted worker");
+        assert_eq!(supervisor.get_status("worker").await.unwrap(), AgentStatus::Running);
+
+        let err = restart_command_output(&supervisor, "ghost").await.unwrap_err();
+        assert!(err.to_string().contains("Agent 'ghost' not found"));
+    }
+
+    #[tokio::test]
+    async fn test_one_shot_agent_commands_point_to_the_repl() {
+        for command in [AgentCommands::Ls, AgentCommands::Logs { id: "worker".to_string() }] {
+            let err = execute_agent_command(command).await.unwrap_err();
+            assert!(err.to_string().contains("run `opencode repl`"), "{}", err);
+        }
+        assert!(!AgentCommands::Checkpoints { id: "worker".to_string() }.manages_running_agents());
+    }
+
+    #[tokio::test]
+    async fn test_ls_command_output() {
+        let supervisor = AgentSupervisor::new();
+        assert_eq!(ls_command_output(&supervisor).await, "No agents running");
+
+        supervisor.spawn("b-worker", "rusty").await.unwrap();
+        supervisor.spawn("a-worker", "architect").await.unwrap();
+        supervisor.stop("b-worker").await.unwrap();
+
+        let output = ls_command_output(&supervisor).await;
+        let lines: Vec<Vec<&str>> = output.lines().map(|line| line.split_whitespace().collect()).collect();
+        assert_eq!(lines, vec![vec!["a-worker", "Running", "architect"], vec!["b-worker", "Stopped", "rusty"]]);
+    }
+
+    #[tokio::test]
+    async fn test_logs_command_output() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("worker", "rusty").await.unwrap();
+        assert_eq!(logs_command_output(&supervisor, "worker").await.unwrap(), "No output from worker yet");
+
+        supervisor.send_task("worker", "hello").await.unwrap();
+        let output = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let output = logs_command_output(&supervisor, "worker").await.unwrap();
+                if output.contains("hello") {
+                    return output;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(output, "hello");
+
+        let err = logs_command_output(&supervisor, "ghost").await.unwrap_err();
+        assert!(err.to_string().contains("Agent 'ghost' not found"));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_validates_persona_before_spawning() {
+        let supervisor = AgentSupervisor::new();
+        let personas = HashMap::from([(
+            "rusty".to_string(),
+            Persona { name: "rusty".to_string(), system_prompt: "You write Rust".to_string(), ..Default::default() },
+        )]);
+
+        let err = spawn_command_output(&supervisor, &personas, "worker", "pythonic", 3, HashMap::new()).await.unwrap_err();
+        assert!(err.to_string().contains("Persona 'pythonic' not found"));
+        assert!(supervisor.list().await.is_empty());
+
+        let output = spawn_command_output(&supervisor, &personas, "worker", "rusty", 2, HashMap::new()).await.unwrap();
+        assert_eq!(output, "Spawned worker-1, worker-2 with persona 'rusty'");
     }
 
     #[test]
@@ -166,14 +951,199 @@ mod tests {
         let cli = Cli::try_parse_from(["opencode", "ask", "What is Rust?", "--persona", "expert"]).unwrap();
         
         match cli.command {
-            Some(Commands::Ask { question, persona }) => {
+            Some(Commands::Ask { question, words, persona, json, stream }) => {
                 assert_eq!(question, "What is Rust?");
+                assert!(words.is_empty());
+                assert_eq!(persona, "expert");
+                assert!(!json);
+                assert!(!stream);
+            }
+            _ => panic!("Expected ask command"),
+        }
+    }
+
+    #[test]
+    fn test_ask_joins_unquoted_words() {
+        let cli = Cli::try_parse_from(["opencode", "ask", "What", "is", "Rust?", "--persona", "expert"]).unwrap();
+
+        match cli.command {
+            Some(Commands::Ask { question, words, persona, .. }) => {
+                assert_eq!(full_question(&question, &words), "What is Rust?");
                 assert_eq!(persona, "expert");
             }
             _ => panic!("Expected ask command"),
         }
     }
 
+    #[test]
+    fn test_ask_json_flag() {
+        let cli = Cli::try_parse_from(["opencode", "ask", "What is Rust?", "--json"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Ask { json: true, .. })));
+    }
+
+    #[test]
+    fn test_ask_stream_flag() {
+        let cli = Cli::try_parse_from(["opencode", "ask", "What is Rust?", "--stream"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Ask { stream: true, json: false, .. })));
+
+        let cli = Cli::try_parse_from(["opencode", "ask", "What is Rust?", "-s"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Ask { stream: true, .. })));
+
+        assert!(Cli::try_parse_from(["opencode", "ask", "What is Rust?", "--stream", "--json"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_stream_prints_echo_provider_chunks() {
+        let provider = EchoProvider::new().with_chunk_size(3);
+        let request = CompletionRequest::builder()
+            .message(Message { role: Role::User, content: "streamed words".to_string() })
+            .build();
+        let mut out = Vec::new();
+
+        write_stream(provider.stream(request).await.unwrap(), &mut out).await.unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "streamed words\n");
+    }
+
+    #[tokio::test]
+    async fn test_write_stream_keeps_partial_output_on_error() {
+        let chunks = futures::stream::iter(vec![
+            Ok(StreamChunk { delta: "partial ".to_string(), finish_reason: None }),
+            Err(opencode_core::error::Error::Provider("connection reset".into())),
+            Ok(StreamChunk { delta: "never printed".to_string(), finish_reason: None }),
+        ])
+        .boxed();
+        let mut out = Vec::new();
+
+        let err = write_stream(chunks, &mut out).await.unwrap_err();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "partial \n");
+        assert!(format!("{:#}", err).contains("connection reset"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_models_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "models", "--provider", "local"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Models { provider: Some(p) }) if p == "local"));
+    }
+
+    #[test]
+    fn test_format_models() {
+        let models = vec![
+            ModelInfo { id: "gpt-4o".to_string(), owned_by: None, context_window: Some(128_000) },
+            ModelInfo { id: "my-finetune".to_string(), owned_by: None, context_window: None },
+        ];
+
+        assert_eq!(
+            format_models("openai", &models),
+            "gpt-4o       128000 tokens\nmy-finetune  context window unknown"
+        );
+        assert_eq!(format_models("local", &[]), "Provider 'local' doesn't list its models");
+    }
+
+    #[test]
+    fn test_health_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "health"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Health { completion: false })));
+        let cli = Cli::try_parse_from(["opencode", "health", "--completion"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Health { completion: true })));
+    }
+
+    #[test]
+    fn test_format_health_report() {
+        let health = HashMap::from([
+            (
+                "openai".to_string(),
+                ProviderHealth { reachable: true, latency: Duration::from_millis(120), error: None },
+            ),
+            (
+                "local".to_string(),
+                ProviderHealth {
+                    reachable: false,
+                    latency: Duration::from_secs(10),
+                    error: Some("No response within 10s".to_string()),
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            format_health_report(&health),
+            "[FAIL] local: No response within 10s (after 10000 ms)\n[ok]   openai: reachable in 120 ms"
+        );
+        assert_eq!(format_health_report(&HashMap::new()), "No providers configured");
+    }
+
+    #[test]
+    fn test_completions_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "completions", "zsh"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Completions { shell: Shell::Zsh })));
+        assert!(Cli::try_parse_from(["opencode", "completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn test_bash_completions_include_subcommands() {
+        let script = completions_script(Shell::Bash);
+        assert!(!script.is_empty());
+        assert!(script.contains("agent"));
+    }
+
+    /// Answers every `cu` invocation with success
+    struct InstalledCu;
+
+    #[async_trait::async_trait]
+    impl opencode_core::container::CommandExecutor for InstalledCu {
+        async fn execute(
+            &self,
+            _program: &str,
+            _args: &[String],
+        ) -> opencode_core::error::Result<opencode_core::container::CommandOutput> {
+            Ok(opencode_core::container::CommandOutput { success: true, ..Default::default() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_doctor_reports_unreadable_config() {
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut config, b"[openai\n").unwrap();
+        let path = config.path().to_str().unwrap();
+
+        let checks = doctor_checks(&ContainerManager::with_executor(Arc::new(InstalledCu)), Some(path)).await;
+
+        let names: Vec<_> = checks.iter().map(|check| check.name).collect();
+        assert_eq!(names, vec!["container-use", "git", "config"]);
+        assert_eq!(checks[0].result, Ok("cu is installed".to_string()));
+        assert!(checks[2].result.as_ref().unwrap_err().starts_with(path));
+
+        let report = format_doctor_report(&checks);
+        assert!(report.contains("[ok]   container-use: cu is installed"));
+        assert!(report.contains(&format!("[FAIL] config: {}", path)));
+    }
+
+    #[test]
+    fn test_ask_json_output() {
+        let response = CompletionResponse {
+            content: "A language".to_string(),
+            model: "gpt-4".to_string(),
+            usage: opencode_core::provider::Usage {
+                prompt_tokens: 3,
+                completion_tokens: 2,
+                total_tokens: 5,
+            },
+            created: Some(1),
+            finish_reason: None,
+        };
+
+        let output: serde_json::Value = serde_json::from_str(&ask_json_output(&response).unwrap()).unwrap();
+        assert_eq!(
+            output,
+            serde_json::json!({
+                "content": "A language",
+                "model": "gpt-4",
+                "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5},
+            })
+        );
+    }
+
     #[test]
     fn test_default_persona() {
         let cli = Cli::try_parse_from(["opencode", "ask", "What is Rust?"]).unwrap();
@@ -218,6 +1188,233 @@ mod tests {
         
         let cli = Cli::try_parse_from(["opencode", "agent", "status", "test"]).unwrap();
         assert!(matches!(cli.command, Some(Commands::Agent(AgentCommands::Status { .. }))));
+
+        let cli = Cli::try_parse_from(["opencode", "agent", "logs", "test"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Agent(AgentCommands::Logs { .. }))));
+
+        let cli = Cli::try_parse_from(["opencode", "agent", "restart", "test"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Agent(AgentCommands::Restart { .. }))));
+    }
+
+    #[test]
+    fn test_agent_send_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "agent", "send", "alice", "fix", "the", "--flaky", "test"]).unwrap();
+        match cli.command {
+            Some(Commands::Agent(AgentCommands::Send { id, task })) => {
+                assert_eq!(id, "alice");
+                assert_eq!(task.join(" "), "fix the --flaky test");
+            }
+            _ => panic!("Expected agent send command"),
+        }
+        assert!(Cli::try_parse_from(["opencode", "agent", "send", "alice"]).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_commands_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "agent", "checkpoint", "alice", "--message", "Initial work"]).unwrap();
+        match cli.command {
+            Some(Commands::Agent(AgentCommands::Checkpoint { id, message, action: None })) => {
+                assert_eq!(id.as_deref(), Some("alice"));
+                assert_eq!(message.as_deref(), Some("Initial work"));
+            }
+            _ => panic!("Expected agent checkpoint command"),
+        }
+        assert!(Cli::try_parse_from(["opencode", "agent", "checkpoint", "alice"]).is_err());
+
+        let cli = Cli::try_parse_from(["opencode", "agent", "checkpoint", "show", "cp/alice/1"]).unwrap();
+        match cli.command {
+            Some(Commands::Agent(AgentCommands::Checkpoint { action: Some(CheckpointCommands::Show { tag }), .. })) => {
+                assert_eq!(tag, "cp/alice/1");
+            }
+            _ => panic!("Expected agent checkpoint show command"),
+        }
+        assert!(Cli::try_parse_from(["opencode", "agent", "checkpoint", "show"]).is_err());
+
+        let cli = Cli::try_parse_from(["opencode", "agent", "checkpoints", "alice"]).unwrap();
+        match cli.command {
+            Some(Commands::Agent(AgentCommands::Checkpoints { id })) => assert_eq!(id, "alice"),
+            _ => panic!("Expected agent checkpoints command"),
+        }
+
+        let cli = Cli::try_parse_from(["opencode", "agent", "restore", "cp/alice/1", "--as", "alice-2"]).unwrap();
+        match cli.command {
+            Some(Commands::Agent(AgentCommands::Restore { tag, new_id })) => {
+                assert_eq!(tag, "cp/alice/1");
+                assert_eq!(new_id, "alice-2");
+            }
+            _ => panic!("Expected agent restore command"),
+        }
+        assert!(Cli::try_parse_from(["opencode", "agent", "restore", "cp/alice/1"]).is_err());
+    }
+
+    /// Answers checkpoint calls with names derived from their arguments
+    struct StubGit;
+
+    impl opencode_core::git::GitOperations for StubGit {
+        fn save_checkpoint(
+            &self,
+            _repo: &std::path::Path,
+            branch: &str,
+            _message: &str,
+            _options: &opencode_core::git::CheckpointOptions,
+        ) -> opencode_core::error::Result<String> {
+            Ok(format!("cp/{}/1", branch))
+        }
+
+        fn list_checkpoints(&self, _repo: &std::path::Path, _agent_id: &str) -> opencode_core::error::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        fn restore_checkpoint(&self, _repo: &std::path::Path, _tag: &str, new_agent_id: &str) -> opencode_core::error::Result<String> {
+            Ok(format!("agent/{}", new_agent_id))
+        }
+
+        fn diff_checkpoint(&self, _repo: &std::path::Path, tag: &str) -> opencode_core::error::Result<String> {
+            match tag {
+                "cp/alice/empty" => Ok(String::new()),
+                _ => Ok(format!("diff --git a/{tag} b/{tag}\n+change\n")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_command_output() {
+        let manager = GitCheckpointManager::with_operations(Arc::new(StubGit), ".");
+
+        let output = checkpoint_command_output(
+            &manager,
+            AgentCommands::Checkpoint {
+                id: Some("alice".to_string()),
+                message: Some("wip".to_string()),
+                action: None,
+            },
+        );
+        assert_eq!(output.unwrap(), "Created checkpoint cp/agent/alice/1");
+
+        let show = |tag: &str| AgentCommands::Checkpoint {
+            id: None,
+            message: None,
+            action: Some(CheckpointCommands::Show { tag: tag.to_string() }),
+        };
+        let output = checkpoint_command_output(&manager, show("cp/alice/1"));
+        assert_eq!(output.unwrap(), "diff --git a/cp/alice/1 b/cp/alice/1\n+change");
+        let output = checkpoint_command_output(&manager, show("cp/alice/empty"));
+        assert_eq!(output.unwrap(), "Checkpoint cp/alice/empty has no changes");
+
+        let output = checkpoint_command_output(&manager, AgentCommands::Checkpoints { id: "alice".to_string() });
+        assert_eq!(output.unwrap(), "No checkpoints found for agent 'alice'");
+
+        let output = checkpoint_command_output(
+            &manager,
+            AgentCommands::Restore { tag: "cp/alice/1".to_string(), new_id: "alice-2".to_string() },
+        );
+        assert_eq!(output.unwrap(), "Restored cp/alice/1 to branch agent/alice-2");
+    }
+
+    #[test]
+    fn test_persona_commands_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "persona", "ls"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Persona(PersonaCommands::Ls))));
+
+        let cli = Cli::try_parse_from(["opencode", "persona", "show", "rusty"]).unwrap();
+        match cli.command {
+            Some(Commands::Persona(PersonaCommands::Show { name })) => assert_eq!(name, "rusty"),
+            _ => panic!("Expected persona show command"),
+        }
+    }
+
+    #[test]
+    fn test_persona_add_parsing() {
+        let cli = Cli::try_parse_from(["opencode", "persona", "add", "reviewer", "--prompt", "Review code"]).unwrap();
+        match cli.command {
+            Some(Commands::Persona(PersonaCommands::Add { name, prompt, prompt_file })) => {
+                assert_eq!(name, "reviewer");
+                assert_eq!(prompt, Some("Review code".to_string()));
+                assert_eq!(prompt_file, None);
+            }
+            _ => panic!("Expected persona add command"),
+        }
+
+        let cli = Cli::try_parse_from(["opencode", "persona", "add", "reviewer", "--prompt-file", "prompt.txt"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Persona(PersonaCommands::Add { prompt_file: Some(_), .. }))
+        ));
+
+        // Exactly one prompt source is required
+        assert!(Cli::try_parse_from(["opencode", "persona", "add", "reviewer"]).is_err());
+        assert!(Cli::try_parse_from([
+            "opencode", "persona", "add", "reviewer", "--prompt", "a", "--prompt-file", "b"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_tokens_command() {
+        let cli = Cli::try_parse_from(["opencode", "tokens", "--file", "main.rs"]).unwrap();
+        match cli.command {
+            Some(Commands::Tokens { file, model }) => {
+                assert_eq!(file, PathBuf::from("main.rs"));
+                assert_eq!(model, None);
+            }
+            _ => panic!("Expected tokens command"),
+        }
+        assert!(Cli::try_parse_from(["opencode", "tokens"]).is_err());
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "fn main() {}\n").unwrap();
+        // 13 characters is 4 tokens, plus the per-message overhead
+        let expected = 4 + tokenize::MESSAGE_OVERHEAD_TOKENS;
+        assert_eq!(
+            tokens_command_output(file.path(), Some("gpt-4")).unwrap(),
+            format!("{} tokens (gpt-4)", expected)
+        );
+
+        assert!(tokens_command_output(Path::new("/nonexistent/file.rs"), None).is_err());
+    }
+
+    fn sample_personas() -> HashMap<String, Persona> {
+        [
+            Persona {
+                name: "rusty".to_string(),
+                system_prompt: "You are a Rust expert.\nPrefer safe code.".to_string(),
+                ..Default::default()
+            },
+            Persona {
+                name: "pythonic".to_string(),
+                system_prompt: "You are a Python expert.".to_string(),
+                ..Default::default()
+            },
+        ]
+        .into_iter()
+        .map(|p| (p.name.clone(), p))
+        .collect()
+    }
+
+    #[test]
+    fn test_format_persona_list() {
+        let output = format_persona_list(&sample_personas());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("pythonic"));
+        assert!(lines[1].starts_with("rusty"));
+        assert!(lines[1].ends_with("You are a Rust expert."));
+    }
+
+    #[test]
+    fn test_format_persona_list_empty() {
+        let output = format_persona_list(&HashMap::new());
+        assert!(output.starts_with("No personas configured"));
+        assert!(output.contains("personas.yml"));
+    }
+
+    #[test]
+    fn test_format_persona() {
+        let output = format_persona(&sample_personas(), "rusty").unwrap();
+        assert_eq!(output, "rusty\n\nYou are a Rust expert.\nPrefer safe code.");
+
+        let err = format_persona(&sample_personas(), "missing").unwrap_err();
+        assert!(err.to_string().contains("Persona 'missing' not found"));
     }
 
     #[tokio::test]
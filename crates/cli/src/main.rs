@@ -1,13 +1,46 @@
 mod cli;
 mod repl;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
+use opencode_core::config::Config;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long agents get to tear down their containers on exit before they are force-aborted
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = cli::Cli::parse();
-    
+    opencode_core::init_tracing();
+    let interactive = matches!(cli.command, None | Some(cli::Commands::Repl));
+
+    // Agents are only spawned from the REPL, so they are stopped when it
+    // exits or the process is signalled; one-shot commands leave them alone
+    let (result, stop_agents) = tokio::select! {
+        result = run(cli) => (result, interactive),
+        _ = shutdown_signal(interactive) => {
+            info!("Received shutdown signal");
+            (Ok(()), true)
+        }
+    };
+
+    if stop_agents {
+        shutdown_agents().await;
+    }
+    result
+}
+
+async fn run(cli: cli::Cli) -> Result<()> {
+    // Doctor reports a broken config instead of failing to start on it
+    if matches!(cli.command, Some(cli::Commands::Doctor)) {
+        return cli::execute_doctor_command(cli.config.as_deref()).await;
+    }
+
+    init_core(cli.config.as_deref())?;
+    cli::init_supervisor(cli.dry_run);
+
     match cli.command {
         Some(cmd) => {
             // Single-shot command mode
@@ -18,4 +51,56 @@ async fn main() -> Result<()> {
             repl::start().await
         }
     }
-}
\ No newline at end of file
+}
+
+/// Load the configuration and initialize the core with it. Without `path`
+/// the global and project config files are layered over the defaults.
+fn init_core(path: Option<&str>) -> Result<()> {
+    let config = match path {
+        Some(path) => Config::load(Some(path)).with_context(|| format!("Failed to load config file {}", path))?,
+        None => Config::load_layered(&Config::default_paths()).context("Failed to load configuration")?,
+    };
+    opencode_core::init(config)?;
+    Ok(())
+}
+
+/// Resolves on SIGTERM, or on SIGINT outside the REPL. The REPL handles
+/// Ctrl-C itself (cancelling a response or exiting its loop).
+async fn shutdown_signal(interactive: bool) {
+    let ctrl_c = async {
+        if interactive || tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Stop every agent this process started so no containers are left running
+async fn shutdown_agents() {
+    let report = cli::supervisor().shutdown_with_timeout(SHUTDOWN_TIMEOUT).await;
+
+    for id in &report.stopped {
+        info!("Stopped agent '{}'", id);
+    }
+    for id in &report.forced {
+        warn!("Force-stopped agent '{}'; its container may still be running", id);
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn opencode(config: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_opencode"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .env_remove("OPENAI_MODEL")
+        .env_remove("OPENAI_API_KEY")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_config_file_sets_default_model() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    std::fs::write(
+        &config,
+        r#"
+[openai]
+default_model = "gpt-4o-mini"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#,
+    )
+    .unwrap();
+    let prompt = dir.path().join("prompt.txt");
+    std::fs::write(&prompt, "hello").unwrap();
+
+    let output = opencode(&config, &["tokens", "--file", prompt.to_str().unwrap()]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(gpt-4o-mini)"));
+}
+
+#[test]
+fn test_invalid_config_file_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("broken.toml");
+    std::fs::write(&config, "[openai\n").unwrap();
+
+    let output = opencode(&config, &["version"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to load config file"), "{}", stderr);
+    assert!(stderr.contains("broken.toml"), "{}", stderr);
+}
@@ -1,13 +1,12 @@
-/// Additional comprehensive tests for 100% coverage
-/// 
-/// This module contains tests specifically designed to cover all untested code paths,
-/// edge cases, and error scenarios across all modules in the opencode_core crate.
+//! Additional comprehensive tests for 100% coverage
+//!
+//! This module contains tests specifically designed to cover all untested code paths,
+//! edge cases, and error scenarios across all modules in the opencode_core crate.
 
 #[cfg(test)]
 mod additional_coverage_tests {
-    use crate::*;
     use crate::config::{Config, OpenAIConfig};
-    use crate::error::Error;
+    use crate::error::{Error, ParseError};
     use crate::provider::*;
     use crate::service::ServiceContainer;
     use std::sync::Arc;
@@ -151,8 +150,11 @@ timeout_seconds = 60
                 api_base: "https://api.openai.com/v1".to_string(),
                 max_retries: 3,
                 timeout_seconds: 30,
+                api_key: None,
+                reconnect_streams: false,
             },
             agent_timeout_seconds: Some(300),
+            ..Default::default()
         };
 
         let temp_file = NamedTempFile::new().unwrap();
@@ -175,8 +177,10 @@ timeout_seconds = 60
         let result = Config::from_env();
         assert!(result.is_err());
         match result {
-            Err(Error::Config(msg)) => assert!(msg.contains("Invalid OPENAI_MAX_RETRIES")),
-            _ => panic!("Expected Config error for invalid max_retries"),
+            Err(Error::Parse { field, source: ParseError::Int(_) }) => {
+                assert_eq!(field.as_deref(), Some("OPENAI_MAX_RETRIES"))
+            }
+            _ => panic!("Expected Parse error for invalid max_retries"),
         }
 
         // Clean up first error
@@ -190,8 +194,10 @@ timeout_seconds = 60
         let result = Config::from_env();
         assert!(result.is_err());
         match result {
-            Err(Error::Config(msg)) => assert!(msg.contains("Invalid OPENAI_TIMEOUT")),
-            _ => panic!("Expected Config error for invalid timeout"),
+            Err(Error::Parse { field, source: ParseError::Int(_) }) => {
+                assert_eq!(field.as_deref(), Some("OPENAI_TIMEOUT"))
+            }
+            _ => panic!("Expected Parse error for invalid timeout"),
         }
 
         // Restore original environment
@@ -246,12 +252,12 @@ timeout_seconds = 60
         let request = CompletionRequest {
             model: "test-model".to_string(),
             messages: vec![Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Test".to_string(),
             }],
             temperature: Some(0.7),
             max_tokens: Some(100),
-            stream: false,
+            ..Default::default()
         };
 
         let result = provider.complete(request).await;
@@ -270,12 +276,13 @@ timeout_seconds = 60
         let request = CompletionRequest {
             model: "test-model".to_string(),
             messages: vec![Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Test".to_string(),
             }],
             temperature: Some(0.7),
             max_tokens: Some(100),
             stream: true,
+            ..Default::default()
         };
 
         let result = failing_provider.stream(request).await;
@@ -338,6 +345,7 @@ timeout_seconds = 60
             temperature: Some(2.0),  // Max temperature
             max_tokens: Some(0),  // Zero max tokens
             stream: true,
+            ..Default::default()
         };
 
         assert_eq!(request.model, "");
@@ -350,17 +358,18 @@ timeout_seconds = 60
     fn test_message_edge_cases() {
         // Test Message with edge cases
         let message = Message {
-            role: "".to_string(),  // Empty role
+            role: Role::User,
             content: "".to_string(),  // Empty content
         };
 
-        assert_eq!(message.role, "");
         assert_eq!(message.content, "");
+        // Empty roles are rejected
+        assert!(Role::try_from("").is_err());
 
         // Test very long content
         let long_content = "a".repeat(10000);
         let message = Message {
-            role: "user".to_string(),
+            role: Role::User,
             content: long_content.clone(),
         };
         assert_eq!(message.content.len(), 10000);
@@ -395,6 +404,8 @@ timeout_seconds = 60
                 completion_tokens: 0,
                 total_tokens: 0,
             },
+            created: None,
+            finish_reason: None,
         };
         assert_eq!(response.content, "");
         assert_eq!(response.model, "");
@@ -425,6 +436,8 @@ timeout_seconds = 60
             api_base: "".to_string(),       // Empty API base
             max_retries: 0,                 // Zero retries
             timeout_seconds: 0,             // Zero timeout
+            api_key: None,
+            reconnect_streams: false,
         };
         assert_eq!(config.default_model, "");
         assert_eq!(config.api_base, "");
@@ -455,14 +468,14 @@ timeout_seconds = 60
             should_fail: false,
         });
         container.register_provider("test2", mock_provider2);
-        assert!(container.list_providers().len() >= 1);
+        assert!(!container.list_providers().is_empty());
     }
 
     #[test]
     fn test_unicode_and_special_characters() {
         // Test with Unicode and special characters
         let message = Message {
-            role: "user".to_string(),
+            role: Role::User,
             content: "Hello 世界! 🚀 Test αβγ δεζ ñáéíóú".to_string(),
         };
         assert!(message.content.contains("世界"));
@@ -475,6 +488,8 @@ timeout_seconds = 60
             api_base: "https://api.example.com/v1/世界".to_string(),
             max_retries: 3,
             timeout_seconds: 30,
+            api_key: None,
+            reconnect_streams: false,
         };
         assert!(config.default_model.contains("🚀"));
         assert!(config.api_base.contains("世界"));
@@ -486,12 +501,12 @@ timeout_seconds = 60
         let request = CompletionRequest {
             model: "a".repeat(1000),  // Very long model name
             messages: vec![Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "x".repeat(100000),  // Very long content
             }],
             temperature: Some(1.9999),  // Close to max temperature
             max_tokens: Some(u32::MAX),  // Maximum tokens
-            stream: false,
+            ..Default::default()
         };
         assert_eq!(request.model.len(), 1000);
         assert_eq!(request.messages[0].content.len(), 100000);
@@ -506,7 +521,7 @@ timeout_seconds = 60
             messages: vec![],
             temperature: Some(0.0),  // Minimum valid temperature
             max_tokens: None,
-            stream: false,
+            ..Default::default()
         };
         assert_eq!(request.temperature, Some(0.0));
 
@@ -515,7 +530,7 @@ timeout_seconds = 60
             messages: vec![],
             temperature: Some(2.0),  // Maximum valid temperature
             max_tokens: None,
-            stream: false,
+            ..Default::default()
         };
         assert_eq!(request.temperature, Some(2.0));
 
@@ -523,11 +538,11 @@ timeout_seconds = 60
         let request = CompletionRequest {
             model: "test".to_string(),
             messages: vec![],
-            temperature: Some(0.7123456789),
+            temperature: Some(0.712_345_66),
             max_tokens: None,
-            stream: false,
+            ..Default::default()
         };
-        assert_eq!(request.temperature, Some(0.7123456789));
+        assert_eq!(request.temperature, Some(0.712_345_66));
     }
 
     #[test]
@@ -539,8 +554,11 @@ timeout_seconds = 60
                 api_base: "https://api.openai.com/v1".to_string(),
                 max_retries: 3,
                 timeout_seconds: 30,
+                api_key: None,
+                reconnect_streams: false,
             },
             agent_timeout_seconds: Some(300),
+            ..Default::default()
         };
 
         let serialized = toml::to_string(&config).unwrap();
@@ -556,20 +574,19 @@ timeout_seconds = 60
     fn test_message_invariants() {
         // Test message invariants
         let message = Message {
-            role: "user".to_string(),
+            role: Role::User,
             content: "test".to_string(),
         };
 
         // Role and content should be preserved exactly
-        assert_eq!(message.role, "user");
+        assert_eq!(message.role, Role::User);
         assert_eq!(message.content, "test");
 
         // Message should handle empty strings
         let empty_message = Message {
-            role: "".to_string(),
+            role: Role::User,
             content: "".to_string(),
         };
-        assert_eq!(empty_message.role.len(), 0);
         assert_eq!(empty_message.content.len(), 0);
     }
 
@@ -581,7 +598,7 @@ timeout_seconds = 60
             messages: vec![],
             temperature: None,
             max_tokens: None,
-            stream: false,
+            ..Default::default()
         };
 
         assert_eq!(request.model, "test-model");
@@ -622,7 +639,7 @@ timeout_seconds = 60
             messages: vec![],
             temperature: None,
             max_tokens: None,
-            stream: false,
+            ..Default::default()
         };
 
         let response = mock.complete(request).await.unwrap();
@@ -0,0 +1,53 @@
+use crate::error::Result;
+use crate::supervisor::Agent;
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+/// What an agent does with the tasks sent to it. The supervisor runs one
+/// behavior per agent and hands it tasks one at a time, in the order they
+/// were sent.
+#[async_trait]
+pub trait AgentBehavior: Send {
+    /// Handle one task, returning its output
+    async fn on_task(&mut self, task: String) -> Result<String>;
+}
+
+/// Answers every task with the task itself
+#[derive(Debug, Clone, Default)]
+pub struct EchoBehavior;
+
+#[async_trait]
+impl AgentBehavior for EchoBehavior {
+    async fn on_task(&mut self, task: String) -> Result<String> {
+        Ok(task)
+    }
+}
+
+type MakeBehavior = dyn Fn(&Agent) -> Box<dyn AgentBehavior> + Send + Sync;
+
+/// Creates the behavior for each agent the supervisor launches
+#[derive(Clone)]
+pub struct BehaviorFactory(Arc<MakeBehavior>);
+
+impl BehaviorFactory {
+    pub fn new(factory: impl Fn(&Agent) -> Box<dyn AgentBehavior> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(factory))
+    }
+
+    pub fn create(&self, agent: &Agent) -> Box<dyn AgentBehavior> {
+        (self.0)(agent)
+    }
+}
+
+impl Default for BehaviorFactory {
+    fn default() -> Self {
+        Self::new(|_| Box::new(EchoBehavior))
+    }
+}
+
+impl fmt::Debug for BehaviorFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BehaviorFactory")
+    }
+}
@@ -0,0 +1,399 @@
+use crate::error::Result;
+use crate::provider::{CompletionRequest, CompletionResponse, Message, ResponseFormat};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+use tracing::debug;
+
+/// Identifies a completion: the provider that answers it plus the request
+/// fields that determine the response (model, messages and sampling options).
+/// `stream` is left out since it only changes how the response is delivered.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+/// The fields behind a `CacheKey`, serialized in a fixed order
+#[derive(Serialize)]
+struct KeyFields<'a> {
+    provider: &'a str,
+    model: &'a str,
+    messages: &'a [Message],
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    stop: Option<&'a [String]>,
+    seed: Option<i64>,
+    top_p: Option<f32>,
+    response_format: Option<&'a ResponseFormat>,
+}
+
+impl CacheKey {
+    pub fn new(provider: &str, request: &CompletionRequest) -> Self {
+        let fields = KeyFields {
+            provider,
+            model: &request.model,
+            messages: &request.messages,
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            stop: request.stop.as_deref(),
+            seed: request.seed,
+            top_p: request.top_p,
+            response_format: request.response_format.as_ref(),
+        };
+        Self(serde_json::to_string(&fields).expect("cache key fields always serialize"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Hex SHA-256 of the key. Unlike `std`'s hashers it's the same across
+    /// builds and Rust releases, so it can name files that outlive the process.
+    pub fn digest(&self) -> String {
+        format!("{:x}", Sha256::digest(self.0.as_bytes()))
+    }
+}
+
+/// Storage for completion responses, keyed by the request that produced them.
+/// Backends decide their own expiry and eviction; a miss is never an error.
+pub trait CacheBackend: Send + Sync {
+    /// The cached response for `key`, if present and not expired
+    fn get(&self, key: &CacheKey) -> Option<CompletionResponse>;
+
+    /// Cache `response` under `key`, replacing any earlier response
+    fn put(&self, key: &CacheKey, response: CompletionResponse);
+
+    /// Drop the cached response for `key`, if any
+    fn invalidate(&self, key: &CacheKey);
+}
+
+/// In-memory LRU cache of completion responses. Entries expire after `ttl`
+/// and the least recently used entry is evicted once `max_entries` is reached.
+#[derive(Debug)]
+pub struct ResponseCache {
+    max_entries: usize,
+    ttl: Duration,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Incremented on every access to order entries by recency
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: CompletionResponse,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+impl ResponseCache {
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
+        Self {
+            max_entries,
+            ttl,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().unwrap().entries.clear();
+    }
+}
+
+impl CacheBackend for ResponseCache {
+    fn get(&self, key: &CacheKey) -> Option<CompletionResponse> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+
+        let entry = state.entries.get_mut(key)?;
+        if entry.inserted_at.elapsed() >= self.ttl {
+            state.entries.remove(key);
+            return None;
+        }
+        entry.last_used = clock;
+        Some(entry.response.clone())
+    }
+
+    /// Evicts the least recently used entry if full
+    fn put(&self, key: &CacheKey, response: CompletionResponse) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+
+        if !state.entries.contains_key(key) && state.entries.len() >= self.max_entries {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+
+        state.entries.insert(
+            key.clone(),
+            CacheEntry {
+                response,
+                inserted_at: Instant::now(),
+                last_used: clock,
+            },
+        );
+    }
+
+    fn invalidate(&self, key: &CacheKey) {
+        self.state.lock().unwrap().entries.remove(key);
+    }
+}
+
+/// Cache that keeps each response as a JSON file named after its key's
+/// `digest`, so entries survive restarts. Entries expire after `ttl`;
+/// unreadable or corrupt files, and files stored under a different key,
+/// count as misses.
+#[derive(Debug)]
+pub struct FileCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileEntry {
+    /// The full `CacheKey`, so a digest collision is a miss rather than
+    /// another request's response
+    key: String,
+    /// Seconds since the Unix epoch
+    stored_at: u64,
+    response: CompletionResponse,
+}
+
+impl FileCache {
+    /// Cache in `dir`, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, ttl })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key.digest()))
+    }
+
+    fn write(&self, path: &Path, entry: &FileEntry) -> std::io::Result<()> {
+        // Write then rename so readers never see a partial file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+impl CacheBackend for FileCache {
+    fn get(&self, key: &CacheKey) -> Option<CompletionResponse> {
+        let path = self.path(key);
+        let bytes = fs::read(&path).ok()?;
+        let entry: FileEntry = match serde_json::from_slice(&bytes) {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("Ignoring corrupt cache file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        if entry.key != key.as_str() {
+            debug!("Ignoring cache file {} stored under another key", path.display());
+            return None;
+        }
+        if unix_now().saturating_sub(entry.stored_at) >= self.ttl.as_secs() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(entry.response)
+    }
+
+    fn put(&self, key: &CacheKey, response: CompletionResponse) {
+        let path = self.path(key);
+        let entry = FileEntry {
+            key: key.as_str().to_string(),
+            stored_at: unix_now(),
+            response,
+        };
+        if let Err(e) = self.write(&path, &entry) {
+            debug!("Failed to write cache file {}: {}", path.display(), e);
+        }
+    }
+
+    fn invalidate(&self, key: &CacheKey) {
+        let _ = fs::remove_file(self.path(key));
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{Message, Role, Usage};
+
+    fn request(content: &str) -> CompletionRequest {
+        CompletionRequest::builder()
+            .model("gpt-4")
+            .message(Message {
+                role: Role::User,
+                content: content.to_string(),
+            })
+            .build()
+    }
+
+    fn key(content: &str) -> CacheKey {
+        CacheKey::new("openai", &request(content))
+    }
+
+    fn response(content: &str) -> CompletionResponse {
+        CompletionResponse {
+            content: content.to_string(),
+            model: "gpt-4".to_string(),
+            usage: Usage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+            },
+            created: None,
+            finish_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_cache_key_ignores_stream_flag() {
+        let streaming = CompletionRequest {
+            stream: true,
+            ..request("hi")
+        };
+        assert_eq!(key("hi"), CacheKey::new("openai", &streaming));
+        assert_ne!(key("hi"), key("bye"));
+
+        let warmer = CompletionRequest {
+            temperature: Some(0.9),
+            ..request("hi")
+        };
+        assert_ne!(key("hi"), CacheKey::new("openai", &warmer));
+    }
+
+    #[test]
+    fn test_cache_key_includes_provider() {
+        assert_ne!(key("hi"), CacheKey::new("anthropic", &request("hi")));
+        assert_ne!(key("hi").digest(), CacheKey::new("anthropic", &request("hi")).digest());
+    }
+
+    #[test]
+    fn test_cache_key_digest_is_stable() {
+        // File names must not change between builds, or persisted entries are lost
+        assert_eq!(
+            key("hi").digest(),
+            "892bc9eaec0f51d9cc67195a1b770d3d02736fa225cd36881199b47957fccd1c"
+        );
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = ResponseCache::new(2, Duration::from_secs(60));
+        cache.put(&key("a"), response("A"));
+        cache.put(&key("b"), response("B"));
+
+        // Touch "a" so "b" becomes the eviction candidate
+        assert!(cache.get(&key("a")).is_some());
+        cache.put(&key("c"), response("C"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("c")).is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_entries_expire() {
+        let cache = ResponseCache::new(10, Duration::from_secs(60));
+        cache.put(&key("a"), response("A"));
+
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert_eq!(cache.get(&key("a")).unwrap().content, "A");
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(cache.get(&key("a")).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_file_cache_survives_being_recreated() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileCache::new(dir.path(), Duration::from_secs(60)).unwrap();
+        cache.put(&key("a"), response("A"));
+        drop(cache);
+
+        let cache = FileCache::new(dir.path(), Duration::from_secs(60)).unwrap();
+        assert_eq!(cache.get(&key("a")).unwrap().content, "A");
+        assert!(cache.get(&key("b")).is_none());
+
+        cache.invalidate(&key("a"));
+        assert!(cache.get(&key("a")).is_none());
+    }
+
+    #[test]
+    fn test_file_cache_misses_on_expired_or_corrupt_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let expired = FileCache::new(dir.path(), Duration::ZERO).unwrap();
+        expired.put(&key("a"), response("A"));
+        assert!(expired.get(&key("a")).is_none());
+
+        let cache = FileCache::new(dir.path(), Duration::from_secs(60)).unwrap();
+        cache.put(&key("b"), response("B"));
+        fs::write(cache.path(&key("b")), "not json").unwrap();
+        assert!(cache.get(&key("b")).is_none());
+    }
+
+    #[test]
+    fn test_file_cache_misses_on_digest_collision() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileCache::new(dir.path(), Duration::from_secs(60)).unwrap();
+        cache.put(&key("a"), response("A"));
+
+        // Pretend "b" hashes to the file holding "a"'s response
+        fs::rename(cache.path(&key("a")), cache.path(&key("b"))).unwrap();
+        assert!(cache.get(&key("b")).is_none());
+    }
+
+    #[test]
+    fn test_invalidate_removes_memory_entry() {
+        let cache = ResponseCache::new(10, Duration::from_secs(60));
+        cache.put(&key("a"), response("A"));
+        cache.invalidate(&key("a"));
+        assert!(cache.is_empty());
+    }
+}
@@ -1,12 +1,22 @@
+use crate::container::ContainerLimits;
 use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
+mod secret;
 #[cfg(test)]
 mod tests;
 
+pub use secret::{Secret, REDACTED};
+
+/// Project-local config file that `Config::default_paths` looks for in the
+/// working directory
+pub const PROJECT_CONFIG_FILE: &str = ".opencode.toml";
+
 /// OpenAI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIConfig {
@@ -14,6 +24,19 @@ pub struct OpenAIConfig {
     pub api_base: String,
     pub max_retries: u32,
     pub timeout_seconds: u32,
+    /// Read from `OPENAI_API_KEY` when set. Only written by `save` when
+    /// present, and then redacted; a redacted key loads as unset.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "secret::deserialize_optional"
+    )]
+    pub api_key: Option<Secret<String>>,
+    /// Re-issue a stream that drops before finishing, up to `max_retries`
+    /// times, asking the model to continue from what it already sent. Off by
+    /// default because the continuation may repeat some tokens.
+    #[serde(default)]
+    pub reconnect_streams: bool,
 }
 
 impl Default for OpenAIConfig {
@@ -23,15 +46,126 @@ impl Default for OpenAIConfig {
             api_base: "https://api.openai.com/v1".to_string(),
             max_retries: 3,
             timeout_seconds: 30,
+            api_key: None,
+            reconnect_streams: false,
         }
     }
 }
 
+/// Kind of backend a provider entry talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderType {
+    OpenAI,
+    Anthropic,
+    Google,
+    /// A local server speaking the OpenAI API (e.g. Ollama, llama.cpp)
+    Local,
+}
+
+impl std::fmt::Display for ProviderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ProviderType::OpenAI => "openai",
+            ProviderType::Anthropic => "anthropic",
+            ProviderType::Google => "google",
+            ProviderType::Local => "local",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Where `cache_enabled` keeps cached responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    /// In-memory LRU cache, lost when the process exits
+    #[default]
+    Memory,
+    /// One JSON file per response under `cache_dir`, kept across runs
+    File,
+}
+
+/// Configuration for an additional named provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub provider_type: ProviderType,
+    /// Redacted when debug-printed or saved, and empty when loaded back
+    #[serde(default)]
+    pub api_key: Secret<String>,
+    pub base_url: Option<String>,
+    /// Extra HTTP headers sent with every request. Kept sorted so saved
+    /// configs serialize the same way every time.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Throttle requests to this provider; unlimited when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+/// Per-provider request budget
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+    pub tokens_per_minute: u32,
+}
+
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub openai: OpenAIConfig,
-    pub agent_timeout_seconds: Option<u64>,
+    /// Stop agents that stay `Running` or `Busy` this long
+    pub agent_timeout_seconds: Option<u32>,
+    /// Most agents that may be active (not `Stopped`) at once; defaults to
+    /// 20, and 0 means unlimited
+    #[serde(default = "default_max_agents")]
+    pub max_agents: usize,
+    /// Name of the provider used when none is requested explicitly
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    /// Providers registered in addition to the `[openai]` section
+    #[serde(default)]
+    pub providers: Vec<ProviderConfig>,
+    /// Providers tried in order by `complete_with_fallback` when the default
+    /// provider is rate limited or failing
+    #[serde(default)]
+    pub fallback_providers: Vec<String>,
+    /// Retry once with the default model when a requested model is unknown
+    #[serde(default)]
+    pub fallback_to_default_model: bool,
+    /// Cache responses to identical non-streaming requests
+    #[serde(default)]
+    pub cache_enabled: bool,
+    #[serde(default = "default_cache_max_entries")]
+    pub cache_max_entries: usize,
+    #[serde(default = "default_cache_ttl_seconds")]
+    pub cache_ttl_seconds: u64,
+    #[serde(default)]
+    pub cache: CacheKind,
+    /// Directory for the `file` cache; defaults to `Config::default_cache_dir`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+    /// Redact likely secrets such as API keys from outgoing messages before
+    /// they reach a provider
+    #[serde(default)]
+    pub scrub_secrets: bool,
+    /// Resource limits for agent containers; unlimited by default
+    #[serde(default)]
+    pub container_limits: ContainerLimits,
+}
+
+fn default_max_agents() -> usize {
+    20
+}
+
+fn default_cache_max_entries() -> usize {
+    100
+}
+
+fn default_cache_ttl_seconds() -> u64 {
+    3600
 }
 
 impl Default for Config {
@@ -39,11 +173,65 @@ impl Default for Config {
         Self {
             openai: OpenAIConfig::default(),
             agent_timeout_seconds: Some(300), // 5 minutes default
+            max_agents: default_max_agents(),
+            default_provider: None,
+            providers: Vec::new(),
+            fallback_providers: Vec::new(),
+            fallback_to_default_model: false,
+            cache_enabled: false,
+            cache_max_entries: default_cache_max_entries(),
+            cache_ttl_seconds: default_cache_ttl_seconds(),
+            cache: CacheKind::default(),
+            cache_dir: None,
+            scrub_secrets: false,
+            container_limits: ContainerLimits::default(),
         }
     }
 }
 
 impl Config {
+    /// Default config that uses OpenAI with `api_key`
+    pub fn openai(api_key: impl Into<Secret<String>>) -> Self {
+        let mut config = Self::default();
+        config.openai.api_key = Some(api_key.into());
+        config.default_provider = Some("openai".to_string());
+        config
+    }
+
+    /// Default config that uses Anthropic with `api_key`, registered as "anthropic"
+    pub fn anthropic(api_key: impl Into<Secret<String>>) -> Self {
+        Self::default_with_provider(ProviderConfig {
+            name: ProviderType::Anthropic.to_string(),
+            provider_type: ProviderType::Anthropic,
+            api_key: api_key.into(),
+            base_url: None,
+            headers: BTreeMap::new(),
+            rate_limit: None,
+        })
+    }
+
+    /// Default config that uses the OpenAI-compatible server at `base_url`,
+    /// registered as "local"
+    pub fn local(base_url: impl Into<String>) -> Self {
+        Self::default_with_provider(ProviderConfig {
+            name: ProviderType::Local.to_string(),
+            provider_type: ProviderType::Local,
+            api_key: Secret::default(),
+            base_url: Some(base_url.into()),
+            headers: BTreeMap::new(),
+            rate_limit: None,
+        })
+    }
+
+    /// Default config with `provider` as its only extra provider and the default
+    pub fn default_with_provider(provider: ProviderConfig) -> Self {
+        Self {
+            default_provider: Some(provider.name.clone()),
+            providers: vec![provider],
+            ..Self::default()
+        }
+    }
+
     /// Load configuration from file and environment variables
     /// Environment variables take precedence over file values
     pub fn load<P: AsRef<Path>>(config_path: Option<P>) -> Result<Self> {
@@ -57,13 +245,84 @@ impl Config {
         let env_config = Self::from_env()?;
         config.merge_env(env_config);
 
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Load several config files in order, each overriding the ones before it,
+    /// then apply environment variables on top.
+    /// Tables are merged key by key, so a layer only needs the fields it
+    /// changes; arrays and plain values are replaced whole. Files that don't
+    /// exist are skipped and fields no layer sets keep their defaults.
+    pub fn load_layered(paths: &[PathBuf]) -> Result<Self> {
+        let mut merged = toml::Value::try_from(Self::default())
+            .map_err(|e| Error::Config(format!("Failed to serialize default config: {}", e)))?;
+        for path in paths.iter().filter(|path| path.exists()) {
+            merge_toml(&mut merged, read_toml(path)?);
+        }
+        let mut config: Config = merged.try_into()?;
+
+        let env_config = Self::from_env()?;
+        config.merge_env(env_config);
+
+        config.validate()?;
         Ok(config)
     }
 
+    /// Config files `load_layered` reads when none are given: the global
+    /// `config.toml` in the user's config directory, then `.opencode.toml` in
+    /// the working directory
+    pub fn default_paths() -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if let Some(dirs) = directories::ProjectDirs::from("dev", "opencode", "opencode") {
+            paths.push(dirs.config_dir().join("config.toml"));
+        }
+        paths.push(PathBuf::from(PROJECT_CONFIG_FILE));
+        paths
+    }
+
+    /// `cache` directory inside the user's config directory, used by the file
+    /// cache when `cache_dir` isn't set
+    pub fn default_cache_dir() -> Option<PathBuf> {
+        directories::ProjectDirs::from("dev", "opencode", "opencode")
+            .map(|dirs| dirs.config_dir().join("cache"))
+    }
+
+    /// The agent limit set by `max_agents`, or `None` when unlimited
+    pub fn agent_limit(&self) -> Option<usize> {
+        Some(self.max_agents).filter(|&max_agents| max_agents > 0)
+    }
+
+    /// Check that configured values are usable, naming the offending field on failure
+    pub fn validate(&self) -> Result<()> {
+        if let Err(e) = url::Url::parse(&self.openai.api_base) {
+            return Err(Error::Config(format!(
+                "openai.api_base '{}' is not a valid URL: {}",
+                self.openai.api_base, e
+            )));
+        }
+
+        if self.openai.default_model.trim().is_empty() {
+            return Err(Error::Config(format!(
+                "openai.default_model must not be empty (got '{}')",
+                self.openai.default_model
+            )));
+        }
+
+        if !(1..=86400).contains(&self.openai.timeout_seconds) {
+            return Err(Error::Config(format!(
+                "openai.timeout_seconds must be between 1 and 86400 (got {})",
+                self.openai.timeout_seconds
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Load configuration from a TOML file
+    /// `${VAR}` references in string values are replaced with the variable's value
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        let config: Config = read_toml(path.as_ref())?.try_into()?;
         Ok(config)
     }
 
@@ -83,13 +342,21 @@ impl Config {
         if let Ok(max_retries) = env::var("OPENAI_MAX_RETRIES") {
             config.openai.max_retries = max_retries
                 .parse()
-                .map_err(|e| Error::Config(format!("Invalid OPENAI_MAX_RETRIES: {}", e)))?;
+                .map_err(|e| Error::parse("OPENAI_MAX_RETRIES", e))?;
         }
 
         if let Ok(timeout) = env::var("OPENAI_TIMEOUT") {
             config.openai.timeout_seconds = timeout
                 .parse()
-                .map_err(|e| Error::Config(format!("Invalid OPENAI_TIMEOUT: {}", e)))?;
+                .map_err(|e| Error::parse("OPENAI_TIMEOUT", e))?;
+        }
+
+        if let Ok(api_key) = env::var("OPENAI_API_KEY") {
+            config.openai.api_key = Some(api_key.into());
+        }
+
+        if let Ok(provider) = env::var("OPENCODE_DEFAULT_PROVIDER") {
+            config.default_provider = Some(provider);
         }
 
         Ok(config)
@@ -111,13 +378,147 @@ impl Config {
         if env::var("OPENAI_TIMEOUT").is_ok() {
             self.openai.timeout_seconds = env_config.openai.timeout_seconds;
         }
+        if env::var("OPENAI_API_KEY").is_ok() {
+            self.openai.api_key = env_config.openai.api_key;
+        }
+        if env::var("OPENCODE_DEFAULT_PROVIDER").is_ok() {
+            self.default_provider = env_config.default_provider;
+        }
+    }
+
+    /// Get a configured provider entry by name
+    pub fn get_provider(&self, name: &str) -> Option<&ProviderConfig> {
+        self.providers.iter().find(|p| p.name == name)
     }
 
     /// Save configuration to a TOML file
+    /// Output is deterministic: fields serialize in declaration order and maps are sorted
+    /// API keys are written redacted; reference them as `${VAR}` instead
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let content = toml::to_string_pretty(self)
             .map_err(|e| Error::Config(format!("Failed to serialize config: {}", e)))?;
         fs::write(path, content)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Keeps a watched config up to date; watching stops when this is dropped
+pub struct ConfigWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl Config {
+    /// Load the config at `path` and keep it up to date as the file changes.
+    ///
+    /// Each change is re-loaded and validated before being swapped in; a reload
+    /// that fails is logged and the previous config is kept.
+    pub fn watch<P: AsRef<Path>>(path: P) -> Result<(Arc<RwLock<Config>>, ConfigWatcher)> {
+        use notify::{RecursiveMode, Watcher};
+
+        let path = fs::canonicalize(path.as_ref())?;
+        let config = Arc::new(RwLock::new(Self::load(Some(&path))?));
+
+        let shared = Arc::clone(&config);
+        let watched = path.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            match res {
+                Ok(event) if affects(&event, &watched) => reload(&watched, &shared),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Config watch error: {}", e),
+            }
+        })
+        .map_err(|e| Error::Config(format!("Failed to watch {}: {}", path.display(), e)))?;
+
+        // Watch the directory rather than the file so editors that save by
+        // renaming a temp file over the original are still picked up
+        let dir = path.parent().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| Error::Config(format!("Failed to watch {}: {}", dir.display(), e)))?;
+
+        Ok((config, ConfigWatcher { _watcher: watcher }))
+    }
+}
+
+fn affects(event: &notify::Event, path: &Path) -> bool {
+    (event.kind.is_modify() || event.kind.is_create())
+        && event.paths.iter().any(|p| p.file_name() == path.file_name())
+}
+
+fn reload(path: &Path, config: &RwLock<Config>) {
+    match Config::load(Some(path)) {
+        Ok(new_config) => {
+            if let Ok(mut current) = config.write() {
+                *current = new_config;
+                tracing::info!("Reloaded config from {}", path.display());
+            }
+        }
+        Err(e) => tracing::warn!("Ignoring invalid config change in {}: {}", path.display(), e),
+    }
+}
+
+/// Parse a TOML file with its `${VAR}` references expanded
+fn read_toml(path: &Path) -> Result<toml::Value> {
+    let content = fs::read_to_string(path)?;
+    let mut value: toml::Value = toml::from_str(&content)?;
+    expand_env_vars(&mut value)?;
+    Ok(value)
+}
+
+/// Deep-merge `layer` over `base`: tables merge key by key, anything else in
+/// `layer` replaces the value in `base`
+fn merge_toml(base: &mut toml::Value, layer: toml::Value) {
+    match (base, layer) {
+        (toml::Value::Table(base), toml::Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Recursively expand `${VAR}` references in every string of a TOML value
+fn expand_env_vars(value: &mut toml::Value) -> Result<()> {
+    match value {
+        toml::Value::String(s) => *s = expand_str(s)?,
+        toml::Value::Array(items) => {
+            for item in items {
+                expand_env_vars(item)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                expand_env_vars(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand_str(input: &str) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let value = env::var(name).map_err(|_| {
+            Error::Config(format!("Environment variable {} referenced in config is not set", name))
+        })?;
+        output.push_str(&rest[..start]);
+        output.push_str(&value);
+        rest = &rest[start + 3 + len..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
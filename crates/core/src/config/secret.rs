@@ -0,0 +1,71 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Printed, and serialized, in place of a secret's value
+pub const REDACTED: &str = "***redacted***";
+
+/// A value such as an API key that must not end up in logs or saved files.
+/// `Debug`, `Display` and `Serialize` all emit [`REDACTED`]; the real value
+/// is only available through `expose`. Reading [`REDACTED`] back, as from a
+/// saved config, gives an unset secret rather than the placeholder.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl Secret<String> {
+    /// The secret value, for the code that actually needs to send it
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret<String> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(deserialize_optional(deserializer)?.unwrap_or_default())
+    }
+}
+
+/// Deserialize an optional secret, treating [`REDACTED`] as absent
+pub fn deserialize_optional<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Secret<String>>, D::Error> {
+    let value = Option::<String>::deserialize(deserializer)?;
+    if value.as_deref() == Some(REDACTED) {
+        tracing::warn!("Ignoring a redacted API key; set it through its environment variable or ${{VAR}}");
+        return Ok(None);
+    }
+    Ok(value.map(Secret))
+}
@@ -21,6 +21,27 @@ fn test_openai_config_defaults() {
     assert_eq!(config.timeout_seconds, 30);
 }
 
+#[test]
+fn test_provider_constructors() {
+    let config = Config::openai("sk-test");
+    assert_eq!(config.default_provider.as_deref(), Some("openai"));
+    assert_eq!(config.openai.api_key.as_ref().unwrap().expose(), "sk-test");
+    assert!(config.providers.is_empty());
+
+    let config = Config::anthropic("sk-ant-test");
+    assert_eq!(config.default_provider.as_deref(), Some("anthropic"));
+    let provider = config.get_provider("anthropic").unwrap();
+    assert_eq!(provider.provider_type, ProviderType::Anthropic);
+    assert_eq!(provider.api_key.expose(), "sk-ant-test");
+
+    let config = Config::local("http://localhost:11434/v1");
+    assert_eq!(config.default_provider.as_deref(), Some("local"));
+    let provider = config.get_provider("local").unwrap();
+    assert_eq!(provider.provider_type, ProviderType::Local);
+    assert_eq!(provider.base_url.as_deref(), Some("http://localhost:11434/v1"));
+    assert!(config.validate().is_ok());
+}
+
 #[test]
 fn test_config_from_toml() {
     let toml_content = r#"
@@ -88,6 +109,47 @@ fn test_config_from_env() {
     env::remove_var("OPENAI_TIMEOUT");
 }
 
+#[test]
+fn test_config_from_env_reads_api_key() {
+    env::set_var("OPENAI_API_KEY", "sk-from-env");
+    let config = Config::from_env().unwrap();
+    env::remove_var("OPENAI_API_KEY");
+
+    assert_eq!(config.openai.api_key.as_ref().map(Secret::expose), Some("sk-from-env"));
+}
+
+#[test]
+fn test_config_api_key_missing_by_default() {
+    let config: Config = toml::from_str(
+        r#"
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#,
+    )
+    .unwrap();
+    assert!(config.openai.api_key.is_none());
+
+    let file = NamedTempFile::new().unwrap();
+    config.save(file.path()).unwrap();
+    assert!(!std::fs::read_to_string(file.path()).unwrap().contains("api_key"));
+}
+
+#[test]
+fn test_saved_openai_api_key_is_redacted() {
+    let mut config = Config::default();
+    config.openai.api_key = Some("sk-very-secret-key".into());
+
+    let file = NamedTempFile::new().unwrap();
+    config.save(file.path()).unwrap();
+
+    let saved = std::fs::read_to_string(file.path()).unwrap();
+    assert!(!saved.contains("sk-very-secret-key"));
+    assert!(saved.contains(&format!("api_key = \"{}\"", REDACTED)));
+}
+
 #[test]
 fn test_config_from_env_partial() {
     // Clean up any existing env vars first
@@ -234,8 +296,11 @@ fn test_config_serialization() {
             api_base: "https://api.openai.com/v1".to_string(),
             max_retries: 3,
             timeout_seconds: 30,
+            api_key: None,
+            reconnect_streams: false,
         },
         agent_timeout_seconds: Some(300),
+        ..Default::default()
     };
 
     let toml_str = toml::to_string(&config).unwrap();
@@ -246,4 +311,546 @@ fn test_config_serialization() {
     let parsed: Config = toml::from_str(&toml_str).unwrap();
     assert_eq!(parsed.openai.default_model, config.openai.default_model);
     assert_eq!(parsed.openai.max_retries, config.openai.max_retries);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_config_with_providers_from_toml() {
+    let toml_content = r#"
+default_provider = "local"
+
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+
+[[providers]]
+name = "local"
+type = "local"
+base_url = "http://localhost:11434/v1"
+
+[[providers]]
+name = "anthropic"
+type = "anthropic"
+api_key = "sk-ant"
+"#;
+
+    let config: Config = toml::from_str(toml_content).unwrap();
+    assert_eq!(config.default_provider, Some("local".to_string()));
+    assert_eq!(config.providers.len(), 2);
+
+    let local = config.get_provider("local").unwrap();
+    assert_eq!(local.provider_type, ProviderType::Local);
+    assert_eq!(local.base_url, Some("http://localhost:11434/v1".to_string()));
+    assert!(local.api_key.expose().is_empty());
+
+    let anthropic = config.get_provider("anthropic").unwrap();
+    assert_eq!(anthropic.provider_type, ProviderType::Anthropic);
+    assert_eq!(anthropic.api_key.expose(), "sk-ant");
+}
+
+#[test]
+fn test_openai_only_config_still_loads() {
+    let toml_content = r#"
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#;
+
+    let config: Config = toml::from_str(toml_content).unwrap();
+    assert!(config.providers.is_empty());
+    assert_eq!(config.default_provider, None);
+    assert_eq!(config.container_limits, ContainerLimits::default());
+}
+
+#[test]
+fn test_config_container_limits_from_toml() {
+    let toml_content = r#"
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+
+[container_limits]
+memory = "1g"
+network = "none"
+"#;
+
+    let config: Config = toml::from_str(toml_content).unwrap();
+    assert_eq!(config.container_limits.memory.as_deref(), Some("1g"));
+    assert_eq!(config.container_limits.cpus, None);
+    assert_eq!(config.container_limits.network, crate::container::NetworkMode::None);
+}
+
+fn write_config(content: &str) -> NamedTempFile {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    write!(temp_file, "{}", content).unwrap();
+    temp_file
+}
+
+#[test]
+fn test_config_expands_env_vars() {
+    env::set_var("OPENCODE_TEST_EXPAND_KEY", "secret-key-123");
+    env::set_var("OPENCODE_TEST_EXPAND_MODEL", "gpt-4o");
+
+    let temp_file = write_config(
+        r#"
+[openai]
+default_model = "${OPENCODE_TEST_EXPAND_MODEL}"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+
+[[providers]]
+name = "openai"
+type = "openai"
+api_key = "${OPENCODE_TEST_EXPAND_KEY}"
+"#,
+    );
+
+    let config = Config::from_file(temp_file.path()).unwrap();
+    assert_eq!(config.openai.default_model, "gpt-4o");
+    assert_eq!(config.providers[0].api_key.expose(), "secret-key-123");
+
+    env::remove_var("OPENCODE_TEST_EXPAND_KEY");
+    env::remove_var("OPENCODE_TEST_EXPAND_MODEL");
+}
+
+#[test]
+fn test_config_expands_nested_env_vars() {
+    env::set_var("OPENCODE_TEST_NESTED_HOST", "localhost");
+    env::set_var("OPENCODE_TEST_NESTED_PORT", "11434");
+
+    let temp_file = write_config(
+        r#"
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+
+[[providers]]
+name = "local"
+type = "local"
+base_url = "http://${OPENCODE_TEST_NESTED_HOST}:${OPENCODE_TEST_NESTED_PORT}/v1"
+"#,
+    );
+
+    let config = Config::from_file(temp_file.path()).unwrap();
+    assert_eq!(
+        config.providers[0].base_url,
+        Some("http://localhost:11434/v1".to_string())
+    );
+
+    env::remove_var("OPENCODE_TEST_NESTED_HOST");
+    env::remove_var("OPENCODE_TEST_NESTED_PORT");
+}
+
+#[test]
+fn test_config_unset_env_var_is_error() {
+    env::remove_var("OPENCODE_TEST_UNSET_KEY");
+
+    let temp_file = write_config(
+        r#"
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+
+[[providers]]
+name = "openai"
+type = "openai"
+api_key = "${OPENCODE_TEST_UNSET_KEY}"
+"#,
+    );
+
+    match Config::load(Some(temp_file.path())) {
+        Err(Error::Config(msg)) => assert!(msg.contains("OPENCODE_TEST_UNSET_KEY")),
+        other => panic!("Expected config error, got {:?}", other),
+    }
+}
+
+fn assert_invalid(config: Config, field: &str, value: &str) {
+    match config.validate() {
+        Err(Error::Config(msg)) => {
+            assert!(msg.contains(field), "message should name {}: {}", field, msg);
+            assert!(msg.contains(value), "message should include {}: {}", value, msg);
+        }
+        other => panic!("Expected config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_validate_default_config() {
+    assert!(Config::default().validate().is_ok());
+}
+
+#[test]
+fn test_validate_rejects_invalid_api_base() {
+    let mut config = Config::default();
+    config.openai.api_base = "not a url".to_string();
+    assert_invalid(config, "openai.api_base", "not a url");
+
+    let mut config = Config::default();
+    config.openai.api_base = String::new();
+    assert_invalid(config, "openai.api_base", "''");
+}
+
+#[test]
+fn test_validate_rejects_empty_default_model() {
+    let mut config = Config::default();
+    config.openai.default_model = "  ".to_string();
+    assert_invalid(config, "openai.default_model", "'  '");
+}
+
+#[test]
+fn test_validate_rejects_timeout_out_of_range() {
+    let mut config = Config::default();
+    config.openai.timeout_seconds = 0;
+    assert_invalid(config, "openai.timeout_seconds", "0");
+
+    let mut config = Config::default();
+    config.openai.timeout_seconds = 86401;
+    assert_invalid(config, "openai.timeout_seconds", "86401");
+
+    let mut config = Config::default();
+    config.openai.timeout_seconds = 86400;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_load_validates_config() {
+    let temp_file = write_config(
+        r#"
+[openai]
+default_model = ""
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#,
+    );
+
+    match Config::load(Some(temp_file.path())) {
+        Err(Error::Config(msg)) => assert!(msg.contains("openai.default_model")),
+        other => panic!("Expected config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_config_save_is_deterministic() {
+    let mut config = Config {
+        default_provider: Some("gateway".to_string()),
+        ..Default::default()
+    };
+
+    // Insert headers out of order; the saved output must not depend on it
+    let mut headers = BTreeMap::new();
+    headers.insert("X-Team".to_string(), "platform".to_string());
+    headers.insert("Authorization-Extra".to_string(), "token".to_string());
+    headers.insert("X-Env".to_string(), "prod".to_string());
+
+    config.providers = vec![
+        ProviderConfig {
+            name: "gateway".to_string(),
+            provider_type: ProviderType::OpenAI,
+            api_key: "sk-gateway".into(),
+            base_url: Some("https://gateway.example.com/v1".to_string()),
+            headers,
+            rate_limit: None,
+        },
+        ProviderConfig {
+            name: "local".to_string(),
+            provider_type: ProviderType::Local,
+            api_key: Default::default(),
+            base_url: Some("http://localhost:11434/v1".to_string()),
+            headers: BTreeMap::new(),
+            rate_limit: None,
+        },
+    ];
+
+    let first = NamedTempFile::new().unwrap();
+    let second = NamedTempFile::new().unwrap();
+    config.save(first.path()).unwrap();
+    config.clone().save(second.path()).unwrap();
+
+    let first_bytes = std::fs::read(first.path()).unwrap();
+    let second_bytes = std::fs::read(second.path()).unwrap();
+    assert_eq!(first_bytes, second_bytes);
+
+    let content = String::from_utf8(first_bytes).unwrap();
+    let auth = content.find("Authorization-Extra").unwrap();
+    let env_header = content.find("X-Env").unwrap();
+    let team = content.find("X-Team").unwrap();
+    assert!(auth < env_header && env_header < team);
+
+    // Saving what was loaded back produces the same bytes
+    let reloaded = Config::from_file(first.path()).unwrap();
+    assert_eq!(reloaded.get_provider("gateway").unwrap().headers.len(), 3);
+    let third = NamedTempFile::new().unwrap();
+    reloaded.save(third.path()).unwrap();
+    assert_eq!(std::fs::read(third.path()).unwrap(), content.into_bytes());
+}
+
+fn wait_for_model(config: &RwLock<Config>, model: &str) -> bool {
+    for _ in 0..50 {
+        if config.read().unwrap().openai.default_model == model {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    false
+}
+
+fn config_with_model(model: &str) -> String {
+    format!(
+        r#"
+[openai]
+default_model = "{}"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#,
+        model
+    )
+}
+
+#[test]
+fn test_watch_reloads_changes_and_keeps_last_valid() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, config_with_model("gpt-4")).unwrap();
+
+    let (config, _watcher) = Config::watch(&path).unwrap();
+    assert_eq!(config.read().unwrap().openai.default_model, "gpt-4");
+
+    std::fs::write(&path, config_with_model("gpt-4o")).unwrap();
+    assert!(wait_for_model(&config, "gpt-4o"));
+
+    // An invalid edit is ignored and the previous config stays in place
+    std::fs::write(&path, config_with_model("")).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert_eq!(config.read().unwrap().openai.default_model, "gpt-4o");
+}
+
+#[test]
+fn test_watch_stops_on_drop() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, config_with_model("gpt-4")).unwrap();
+
+    let (config, watcher) = Config::watch(&path).unwrap();
+    drop(watcher);
+
+    std::fs::write(&path, config_with_model("gpt-4o")).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert_eq!(config.read().unwrap().openai.default_model, "gpt-4");
+}
+
+#[test]
+fn test_watch_missing_file() {
+    assert!(Config::watch("does/not/exist.toml").is_err());
+}
+
+
+#[test]
+fn test_debug_redacts_api_keys() {
+    let config = Config {
+        providers: vec![ProviderConfig {
+            name: "gateway".to_string(),
+            provider_type: ProviderType::OpenAI,
+            api_key: "sk-very-secret-key".into(),
+            base_url: Some("https://gateway.example.com/v1".to_string()),
+            headers: BTreeMap::new(),
+            rate_limit: None,
+        }],
+        ..Default::default()
+    };
+
+    for output in [format!("{:?}", config), format!("{:#?}", config.providers[0])] {
+        assert!(!output.contains("sk-very-secret-key"), "key leaked: {}", output);
+        assert!(output.contains(REDACTED));
+        assert!(output.contains("gateway.example.com"));
+    }
+}
+
+#[test]
+fn test_secret_is_redacted_everywhere() {
+    let secret = Secret::from("sk-very-secret-key");
+
+    assert_eq!(secret.expose(), "sk-very-secret-key");
+    assert_eq!(format!("{}", secret), REDACTED);
+    assert_eq!(format!("{:?}", secret), REDACTED);
+    assert_eq!(serde_json::to_string(&secret).unwrap(), format!("\"{}\"", REDACTED));
+
+    let parsed: Secret<String> = serde_json::from_str("\"sk-from-file\"").unwrap();
+    assert_eq!(parsed.expose(), "sk-from-file");
+}
+
+#[test]
+fn test_saved_config_does_not_contain_api_keys() {
+    let config = Config {
+        providers: vec![ProviderConfig {
+            name: "gateway".to_string(),
+            provider_type: ProviderType::OpenAI,
+            api_key: "sk-very-secret-key".into(),
+            base_url: None,
+            headers: BTreeMap::new(),
+            rate_limit: None,
+        }],
+        ..Default::default()
+    };
+
+    let file = NamedTempFile::new().unwrap();
+    config.save(file.path()).unwrap();
+
+    let saved = std::fs::read_to_string(file.path()).unwrap();
+    assert!(!saved.contains("sk-very-secret-key"));
+    assert!(saved.contains(REDACTED));
+}
+
+#[test]
+fn test_redacted_api_keys_load_as_unset() {
+    let mut config = Config::openai("sk-very-secret-key");
+    config.providers.push(ProviderConfig {
+        name: "gateway".to_string(),
+        provider_type: ProviderType::OpenAI,
+        api_key: "sk-other-secret-key".into(),
+        base_url: None,
+        headers: BTreeMap::new(),
+        rate_limit: None,
+    });
+
+    let file = NamedTempFile::new().unwrap();
+    config.save(file.path()).unwrap();
+    let loaded = Config::from_file(file.path()).unwrap();
+
+    // The placeholder is never mistaken for the key itself
+    assert!(loaded.openai.api_key.is_none());
+    assert_eq!(loaded.providers[0].api_key.expose(), "");
+    assert_eq!(loaded.providers[0].name, "gateway");
+}
+
+#[test]
+fn test_load_layered_project_overrides_global() {
+    let dir = tempfile::tempdir().unwrap();
+    let global = dir.path().join("global.toml");
+    let project = dir.path().join(PROJECT_CONFIG_FILE);
+    std::fs::write(
+        &global,
+        r#"
+agent_timeout_seconds = 600
+cache_enabled = true
+cache_ttl_seconds = 60
+fallback_providers = ["local", "backup"]
+
+[container_limits]
+memory = "2g"
+cpus = "2"
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &project,
+        r#"
+cache_ttl_seconds = 5
+fallback_providers = ["local"]
+
+[container_limits]
+cpus = "4"
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load_layered(&[global, project]).unwrap();
+
+    // Project values win
+    assert_eq!(config.cache_ttl_seconds, 5);
+    assert_eq!(config.container_limits.cpus.as_deref(), Some("4"));
+    assert_eq!(config.fallback_providers, vec!["local"]);
+    // Fields the project leaves out inherit from the global file
+    assert_eq!(config.agent_timeout_seconds, Some(600));
+    assert!(config.cache_enabled);
+    assert_eq!(config.container_limits.memory.as_deref(), Some("2g"));
+    // Fields neither file sets keep their defaults
+    assert_eq!(config.cache_max_entries, 100);
+}
+
+#[test]
+fn test_load_layered_skips_missing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join(PROJECT_CONFIG_FILE);
+    std::fs::write(&project, "cache_max_entries = 7\n").unwrap();
+
+    let config = Config::load_layered(&[dir.path().join("missing.toml"), project]).unwrap();
+    assert_eq!(config.cache_max_entries, 7);
+    assert_eq!(config.agent_timeout_seconds, Some(300));
+}
+
+#[test]
+fn test_load_layered_reports_invalid_layer() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join(PROJECT_CONFIG_FILE);
+    std::fs::write(&project, "[openai]\ntimeout_seconds = 0\n").unwrap();
+
+    let err = Config::load_layered(&[project]).unwrap_err();
+    assert!(err.to_string().contains("openai.timeout_seconds"), "{}", err);
+}
+
+#[test]
+fn test_cache_backend_from_toml() {
+    let config: Config = toml::from_str(
+        r#"
+cache_enabled = true
+cache = "file"
+cache_dir = "/tmp/opencode-cache"
+
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.cache, CacheKind::File);
+    assert_eq!(config.cache_dir, Some(PathBuf::from("/tmp/opencode-cache")));
+
+    assert_eq!(Config::default().cache, CacheKind::Memory);
+    let err = toml::from_str::<Config>("cache = \"sled\"").unwrap_err();
+    assert!(err.to_string().contains("unknown variant"), "{}", err);
+}
+
+#[test]
+fn test_max_agents_defaults_and_zero_is_unlimited() {
+    let config: Config = toml::from_str(
+        r#"
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.max_agents, 20);
+    assert_eq!(config.agent_limit(), Some(20));
+
+    let config: Config = toml::from_str(
+        r#"
+max_agents = 0
+
+[openai]
+default_model = "gpt-4"
+api_base = "https://api.openai.com/v1"
+max_retries = 3
+timeout_seconds = 30
+"#,
+    )
+    .unwrap();
+    config.validate().unwrap();
+    assert_eq!(config.agent_limit(), None);
+}
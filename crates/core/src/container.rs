@@ -0,0 +1,513 @@
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+/// Keeps an agent's container environment alive while its behavior handles tasks
+pub const AGENT_SHELL_COMMAND: &str = "echo 'Agent started. Waiting for tasks...'; sleep 3600";
+
+/// Number of output lines kept per agent
+pub const DEFAULT_LOG_LINES: usize = 1000;
+
+/// Bounded buffer of the most recent output lines of a container. Clones
+/// share the same buffer.
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+    tee: bool,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            tee: false,
+        }
+    }
+
+    /// Also print every captured line to stdout
+    pub fn with_tee(mut self, tee: bool) -> Self {
+        self.tee = tee;
+        self
+    }
+
+    /// Append a line, evicting the oldest once the buffer is full
+    pub fn push(&self, line: &str) {
+        if self.tee {
+            println!("{}", line);
+        }
+
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        if self.capacity > 0 {
+            lines.push_back(line.to_string());
+        }
+    }
+
+    /// The buffered lines, oldest first
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Result of running an external command to completion
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Callback receiving each line a command prints
+pub type LineSink<'a> = dyn Fn(&str) + Send + Sync + 'a;
+
+/// Runs external commands on behalf of the container manager, so tests can
+/// substitute a mock for the `cu` binary
+#[async_trait]
+pub trait CommandExecutor: Send + Sync {
+    async fn execute(&self, program: &str, args: &[String]) -> Result<CommandOutput>;
+
+    /// Like `execute`, but hands each stdout/stderr line to `on_line`. The
+    /// default implementation replays the output once the command finishes.
+    async fn execute_streaming(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: &LineSink<'_>,
+    ) -> Result<CommandOutput> {
+        let output = self.execute(program, args).await?;
+        output.stdout.lines().chain(output.stderr.lines()).for_each(on_line);
+        Ok(output)
+    }
+}
+
+fn spawn_error(program: &str, e: std::io::Error) -> Error {
+    Error::Service(format!(
+        "Failed to execute '{}': {}. Is `container-use` installed and in your PATH?",
+        program, e
+    ))
+}
+
+/// Executes commands as real child processes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemExecutor;
+
+#[async_trait]
+impl CommandExecutor for SystemExecutor {
+    async fn execute(&self, program: &str, args: &[String]) -> Result<CommandOutput> {
+        let output = Command::new(program)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| spawn_error(program, e))?;
+
+        Ok(CommandOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    async fn execute_streaming(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: &LineSink<'_>,
+    ) -> Result<CommandOutput> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Aborting the owning task must not leave the process running
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| spawn_error(program, e))?;
+
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let mut stderr = BufReader::new(child.stderr.take().expect("stderr is piped")).lines();
+        let mut output = CommandOutput::default();
+        let (mut stdout_done, mut stderr_done) = (false, false);
+
+        while !(stdout_done && stderr_done) {
+            tokio::select! {
+                line = stdout.next_line(), if !stdout_done => match line? {
+                    Some(line) => {
+                        on_line(&line);
+                        output.stdout.push_str(&line);
+                        output.stdout.push('\n');
+                    }
+                    None => stdout_done = true,
+                },
+                line = stderr.next_line(), if !stderr_done => match line? {
+                    Some(line) => {
+                        on_line(&line);
+                        output.stderr.push_str(&line);
+                        output.stderr.push('\n');
+                    }
+                    None => stderr_done = true,
+                },
+            }
+        }
+
+        output.success = child.wait().await?.success();
+        Ok(output)
+    }
+}
+
+/// Network access granted to a container environment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkMode {
+    /// The container runtime's default network
+    #[default]
+    Bridge,
+    /// No network access at all
+    None,
+    /// Share the host's network stack
+    Host,
+}
+
+/// Resource limits applied when opening an environment. Unset limits are
+/// left to `cu`'s defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerLimits {
+    /// Memory cap, e.g. "512m" or "2g"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    /// CPU cap in cores, e.g. "0.5"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<String>,
+    #[serde(default)]
+    pub network: NetworkMode,
+}
+
+impl ContainerLimits {
+    /// `cu environment open` flags enforcing these limits
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(memory) = &self.memory {
+            args.extend(["--memory".to_string(), memory.clone()]);
+        }
+        if let Some(cpus) = &self.cpus {
+            args.extend(["--cpus".to_string(), cpus.clone()]);
+        }
+        match self.network {
+            NetworkMode::Bridge => {}
+            NetworkMode::None => args.extend(["--network".to_string(), "none".to_string()]),
+            NetworkMode::Host => args.extend(["--network".to_string(), "host".to_string()]),
+        }
+        args
+    }
+}
+
+/// Manages `container-use` environments. `cu` keys each environment by the
+/// git branch of its worktree, so the branch name doubles as the container id.
+#[derive(Clone)]
+pub struct ContainerManager {
+    executor: Arc<dyn CommandExecutor>,
+    /// Log container commands instead of running them
+    dry_run: bool,
+    limits: ContainerLimits,
+}
+
+impl ContainerManager {
+    pub fn new() -> Self {
+        Self::with_executor(Arc::new(SystemExecutor))
+    }
+
+    pub fn with_executor(executor: Arc<dyn CommandExecutor>) -> Self {
+        Self {
+            executor,
+            dry_run: false,
+            limits: ContainerLimits::default(),
+        }
+    }
+
+    /// Open every environment with `limits`
+    pub fn with_limits(mut self, limits: ContainerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Only log the `cu` command each container run would execute
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// The full `cu` command line that runs `shell_command` in the
+    /// environment for `branch_name`, program first
+    pub fn open_command(&self, branch_name: &str, shell_command: &str) -> Vec<String> {
+        let mut command: Vec<String> = ["cu", "environment", "open", "--branch", branch_name]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        command.extend(self.limits.args());
+        command.extend(["--", "sh", "-c", shell_command].iter().map(|a| a.to_string()));
+        command
+    }
+
+    /// Check that the `cu` command is available
+    pub async fn check_cu_exists(&self) -> Result<()> {
+        let output = self.cu(&["--version"]).await?;
+        if !output.success {
+            return Err(Error::Service(
+                "'cu --version' command failed. Ensure container-use is correctly installed.".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run a shell command inside the environment for `branch_name`, waiting
+    /// for it to finish
+    pub async fn run_in_container(&self, branch_name: &str, shell_command: &str) -> Result<()> {
+        let command = self.open_command(branch_name, shell_command);
+        if self.dry_run {
+            tracing::info!("Dry run, not executing: {}", command.join(" "));
+            return Ok(());
+        }
+
+        let output = self.executor.execute(&command[0], &command[1..]).await?;
+        check_container_output(branch_name, &output)
+    }
+
+    /// Like `run_in_container`, but captures the command's output into `logs`
+    pub async fn run_in_container_with_logs(
+        &self,
+        branch_name: &str,
+        shell_command: &str,
+        logs: &LogBuffer,
+    ) -> Result<()> {
+        let command = self.open_command(branch_name, shell_command);
+        if self.dry_run {
+            tracing::info!("Dry run, not executing: {}", command.join(" "));
+            logs.push(&format!("[dry run] {}", command.join(" ")));
+            return Ok(());
+        }
+
+        let output = self
+            .executor
+            .execute_streaming(&command[0], &command[1..], &|line| logs.push(line))
+            .await?;
+        check_container_output(branch_name, &output)
+    }
+
+    /// Stop and tear down the environment for `branch_name` so no container is left behind
+    pub async fn stop_container(&self, branch_name: &str) -> Result<()> {
+        if self.dry_run {
+            tracing::info!("Dry run, not executing: cu environment stop --branch {}", branch_name);
+            return Ok(());
+        }
+
+        let output = self.cu(&["environment", "stop", "--branch", branch_name]).await?;
+
+        if !output.success {
+            return Err(Error::Service(format!(
+                "Failed to stop container for '{}': {}",
+                branch_name,
+                output.stderr.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn cu(&self, args: &[&str]) -> Result<CommandOutput> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        self.executor.execute("cu", &args).await
+    }
+}
+
+fn check_container_output(branch_name: &str, output: &CommandOutput) -> Result<()> {
+    if !output.success {
+        return Err(Error::Service(format!(
+            "Container command for '{}' failed: {}",
+            branch_name,
+            output.stderr.trim()
+        )));
+    }
+    Ok(())
+}
+
+impl Default for ContainerManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ContainerManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContainerManager")
+            .field("dry_run", &self.dry_run)
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+
+    /// Records every command instead of running it and answers with `stdout`.
+    /// Commands whose `cu` subcommand (e.g. "open" or "stop") is listed in
+    /// `fail_on` report failure.
+    #[derive(Debug, Default)]
+    pub struct MockExecutor {
+        pub calls: Mutex<Vec<Vec<String>>>,
+        pub fail_on: Vec<&'static str>,
+        pub stdout: String,
+    }
+
+    impl MockExecutor {
+        pub fn failing_on(subcommands: &[&'static str]) -> Self {
+            Self {
+                fail_on: subcommands.to_vec(),
+                ..Self::default()
+            }
+        }
+
+        pub fn calls(&self) -> Vec<Vec<String>> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl CommandExecutor for MockExecutor {
+        async fn execute(&self, program: &str, args: &[String]) -> Result<CommandOutput> {
+            let mut call = vec![program.to_string()];
+            call.extend(args.iter().cloned());
+            self.calls.lock().unwrap().push(call);
+
+            let fails = args
+                .get(1)
+                .is_some_and(|sub| self.fail_on.contains(&sub.as_str()));
+            Ok(CommandOutput {
+                success: !fails,
+                stdout: self.stdout.clone(),
+                stderr: if fails { "mock failure".to_string() } else { String::new() },
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_in_container_command() {
+        let executor = Arc::new(MockExecutor::default());
+        let manager = ContainerManager::with_executor(executor.clone());
+
+        manager.run_in_container("agent-1", "ls -l").await.unwrap();
+
+        assert_eq!(
+            executor.calls(),
+            vec![vec!["cu", "environment", "open", "--branch", "agent-1", "--", "sh", "-c", "ls -l"]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_in_container_with_limits() {
+        let executor = Arc::new(MockExecutor::default());
+        let manager = ContainerManager::with_executor(executor.clone()).with_limits(ContainerLimits {
+            memory: Some("512m".to_string()),
+            cpus: Some("0.5".to_string()),
+            network: NetworkMode::None,
+        });
+
+        manager.run_in_container("agent-1", "ls").await.unwrap();
+
+        assert_eq!(
+            executor.calls(),
+            vec![vec![
+                "cu", "environment", "open", "--branch", "agent-1", "--memory", "512m", "--cpus", "0.5",
+                "--network", "none", "--", "sh", "-c", "ls",
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_default_limits_add_no_flags() {
+        assert!(ContainerLimits::default().args().is_empty());
+
+        let host = ContainerLimits {
+            network: NetworkMode::Host,
+            ..ContainerLimits::default()
+        };
+        assert_eq!(host.args(), vec!["--network", "host"]);
+    }
+
+    #[tokio::test]
+    async fn test_stop_container_command() {
+        let executor = Arc::new(MockExecutor::default());
+        let manager = ContainerManager::with_executor(executor.clone());
+
+        manager.stop_container("agent-1").await.unwrap();
+
+        assert_eq!(
+            executor.calls(),
+            vec![vec!["cu", "environment", "stop", "--branch", "agent-1"]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_container_failure() {
+        let manager = ContainerManager::with_executor(Arc::new(MockExecutor::failing_on(&["stop"])));
+
+        let err = manager.stop_container("agent-1").await.unwrap_err();
+        assert!(err.to_string().contains("mock failure"));
+    }
+
+    #[tokio::test]
+    async fn test_run_in_container_captures_logs() {
+        let executor = Arc::new(MockExecutor {
+            stdout: "building\ntesting\ndone\n".to_string(),
+            ..MockExecutor::default()
+        });
+        let manager = ContainerManager::with_executor(executor);
+        let logs = LogBuffer::new(DEFAULT_LOG_LINES);
+
+        manager.run_in_container_with_logs("agent-1", "make", &logs).await.unwrap();
+
+        assert_eq!(logs.lines(), vec!["building", "testing", "done"]);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_dry_run_logs_command_without_executing() {
+        let executor = Arc::new(MockExecutor::default());
+        let manager = ContainerManager::with_executor(executor.clone()).with_dry_run(true);
+        let logs = LogBuffer::new(DEFAULT_LOG_LINES);
+
+        manager.run_in_container("agent-1", "ls -l").await.unwrap();
+        manager.run_in_container_with_logs("agent-2", "make", &logs).await.unwrap();
+
+        manager.stop_container("agent-1").await.unwrap();
+
+        assert!(executor.calls().is_empty());
+        assert!(logs_contain("cu environment open --branch agent-1 -- sh -c ls -l"));
+        assert_eq!(logs.lines(), vec!["[dry run] cu environment open --branch agent-2 -- sh -c make"]);
+    }
+
+    #[test]
+    fn test_log_buffer_keeps_most_recent_lines() {
+        let logs = LogBuffer::new(2);
+        for line in ["one", "two", "three"] {
+            logs.push(line);
+        }
+        assert_eq!(logs.lines(), vec!["two", "three"]);
+
+        // Clones share the same buffer
+        logs.clone().push("four");
+        assert_eq!(logs.lines(), vec!["three", "four"]);
+    }
+}
@@ -0,0 +1,346 @@
+//! Dependency injection container: singletons, factories, named
+//! implementations of a trait and per-scope services, resolved by type
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+
+#[cfg(test)]
+mod tests;
+
+/// A type-erased `Arc` of a service, or a `Factory` of one
+type BoxedService = Box<dyn Any + Send + Sync>;
+
+type Factory<T> = Box<dyn Fn() -> Arc<T> + Send + Sync>;
+
+/// Builds a singleton from dependencies resolved out of the container
+type BuildWithDeps = dyn Fn(&Container) -> Result<BoxedService, DIError> + Send + Sync;
+
+#[derive(Default)]
+pub struct Container {
+    services: HashMap<TypeId, BoxedService>,
+    lazy_singletons: HashMap<TypeId, LazySingleton>,
+    async_singletons: HashMap<TypeId, AsyncSingleton>,
+    /// A `Factory<T>` per type
+    factories: HashMap<TypeId, BoxedService>,
+    /// An `Arc<I>` per trait object type and name
+    interfaces: HashMap<(TypeId, String), BoxedService>,
+    /// Types that only a `Scope` can provide
+    scoped_types: HashSet<TypeId>,
+}
+
+/// A singleton built on first resolve, once its dependencies can be resolved
+struct LazySingleton {
+    init: Box<BuildWithDeps>,
+    instance: OnceLock<BoxedService>,
+}
+
+/// A singleton whose factory is async, run once by the first `resolve_async`
+struct AsyncSingleton {
+    init: Box<dyn Fn() -> Pin<Box<dyn Future<Output = BoxedService> + Send>> + Send + Sync>,
+    instance: tokio::sync::OnceCell<BoxedService>,
+}
+
+thread_local! {
+    /// Types whose singletons are being built on this thread, outermost first.
+    /// Construction is synchronous, so a per-thread stack sees every
+    /// nested resolution.
+    static RESOLVING: std::cell::RefCell<Vec<(TypeId, &'static str)>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Marks `T` as being built until dropped
+struct ResolutionGuard;
+
+impl ResolutionGuard {
+    /// Fails with the cycle if `T` is already being built further up the stack
+    fn enter<T: Any>() -> Result<Self, DIError> {
+        RESOLVING.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if let Some(start) = stack.iter().position(|(id, _)| *id == TypeId::of::<T>()) {
+                let mut cycle: Vec<&str> = stack[start..].iter().map(|(_, name)| *name).collect();
+                cycle.push(std::any::type_name::<T>());
+                return Err(DIError::CircularDependency(cycle.join(" -> ")));
+            }
+            stack.push((TypeId::of::<T>(), std::any::type_name::<T>()));
+            Ok(ResolutionGuard)
+        })
+    }
+}
+
+impl Drop for ResolutionGuard {
+    fn drop(&mut self) {
+        RESOLVING.with(|stack| {
+            stack.borrow_mut().pop();
+        });
+    }
+}
+
+/// Services provided for one unit of work, such as a request, in front of
+/// the container's
+pub struct Scope<'a> {
+    container: &'a Container,
+    scoped_instances: HashMap<TypeId, BoxedService>,
+}
+
+#[derive(Default)]
+pub struct ContainerBuilder {
+    container: Container,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DIError {
+    #[error("Service not found: {0}")]
+    ServiceNotFound(String),
+    
+    /// The types involved, e.g. `A -> B -> A`
+    #[error("Circular dependency detected: {0}")]
+    CircularDependency(String),
+    
+    #[error("Service already registered: {0}")]
+    AlreadyRegistered(String),
+    
+    #[error("Invalid service lifetime")]
+    InvalidLifetime,
+    
+    /// A scoped service was resolved in a scope that didn't `provide` it
+    #[error("Scoped service not provided in this scope: {0}")]
+    ScopedInstanceMissing(String),
+}
+
+impl Container {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Number of registered singletons and factories
+    pub fn service_count(&self) -> usize {
+        self.services.len() + self.lazy_singletons.len() + self.async_singletons.len() + self.factories.len()
+    }
+    
+    /// Register a singleton, created right away by `factory`
+    pub fn register_singleton<T: Any + Send + Sync + 'static>(
+        &mut self,
+        factory: impl Fn() -> Arc<T> + Send + Sync + 'static,
+    ) {
+        let service = factory();
+        self.services.insert(TypeId::of::<T>(), Box::new(service));
+    }
+    
+    /// Register a transient service: every resolve calls `factory` again
+    pub fn register_factory<T: Any + Send + Sync + 'static>(
+        &mut self,
+        factory: impl Fn() -> Arc<T> + Send + Sync + 'static,
+    ) {
+        let factory: Factory<T> = Box::new(factory);
+        self.factories.insert(TypeId::of::<T>(), Box::new(factory));
+    }
+    
+    /// Register the implementation of trait object type `I` called `name`,
+    /// created right away by `factory`
+    pub fn register_interface<I: ?Sized + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+        factory: impl Fn() -> Arc<I> + Send + Sync + 'static,
+    ) {
+        self.interfaces.insert((TypeId::of::<I>(), name.to_string()), Box::new(factory()));
+    }
+    
+    /// Register a singleton built from dependencies resolved out of this
+    /// container. Dependencies are resolved lazily, on the first `resolve`
+    /// of `T`, so they may be registered in any order; a dependency that is
+    /// still missing by then fails that resolve with `ServiceNotFound`.
+    pub fn register_singleton_with_deps<T: Any + Send + Sync + 'static, D>(
+        &mut self,
+        factory: impl Fn(D) -> Arc<T> + Send + Sync + 'static,
+    ) where
+        D: ResolveDependencies,
+    {
+        let init = move |container: &Container| {
+            let deps = D::resolve(container)?;
+            Ok(Box::new(factory(deps)) as BoxedService)
+        };
+        self.lazy_singletons.insert(
+            TypeId::of::<T>(),
+            LazySingleton {
+                init: Box::new(init),
+                instance: OnceLock::new(),
+            },
+        );
+    }
+    
+    /// Declare `T` as scoped: each `Scope` must `provide` its own instance
+    pub fn register_scoped<T: Any + Send + Sync + 'static>(&mut self) {
+        self.scoped_types.insert(TypeId::of::<T>());
+    }
+    
+    /// Register a singleton with an async factory. The factory runs on the
+    /// first `resolve_async`; concurrent resolutions wait for that one run.
+    /// Until then a sync `resolve` can't produce the service.
+    pub fn register_async_singleton<T: Any + Send + Sync + 'static>(
+        &mut self,
+        factory: impl Fn() -> Pin<Box<dyn Future<Output = Arc<T>> + Send>> + Send + Sync + 'static,
+    ) {
+        let init = move || -> Pin<Box<dyn Future<Output = BoxedService> + Send>> {
+            let service = factory();
+            Box::pin(async move { Box::new(service.await) as BoxedService })
+        };
+        self.async_singletons.insert(
+            TypeId::of::<T>(),
+            AsyncSingleton {
+                init: Box::new(init),
+                instance: tokio::sync::OnceCell::new(),
+            },
+        );
+    }
+    
+    /// The registered `T`: a singleton, or a new instance from its factory
+    pub fn resolve<T: Any + Send + Sync + 'static>(&self) -> Result<Arc<T>, DIError> {
+        // Try singletons first
+        if let Some(service) = self.services.get(&TypeId::of::<T>()) {
+            if let Some(arc) = service.downcast_ref::<Arc<T>>() {
+                return Ok(arc.clone());
+            }
+        }
+        
+        // Then singletons with dependencies, building them on first use
+        if let Some(lazy) = self.lazy_singletons.get(&TypeId::of::<T>()) {
+            if lazy.instance.get().is_none() {
+                let _guard = ResolutionGuard::enter::<T>()?;
+                let instance = (lazy.init)(self)?;
+                // Another thread may have won the race; either instance is valid
+                let _ = lazy.instance.set(instance);
+            }
+            if let Some(arc) = lazy.instance.get().and_then(|i| i.downcast_ref::<Arc<T>>()) {
+                return Ok(arc.clone());
+            }
+        }
+        
+        // Async singletons that have already been initialized
+        if let Some(service) = self
+            .async_singletons
+            .get(&TypeId::of::<T>())
+            .and_then(|singleton| singleton.instance.get())
+        {
+            if let Some(arc) = service.downcast_ref::<Arc<T>>() {
+                return Ok(arc.clone());
+            }
+        }
+        
+        // Try factories
+        if let Some(factory) = self.factories.get(&TypeId::of::<T>()) {
+            if let Some(f) = factory.downcast_ref::<Factory<T>>() {
+                return Ok(f());
+            }
+        }
+        
+        Err(DIError::ServiceNotFound(std::any::type_name::<T>().to_string()))
+    }
+    
+    pub fn resolve_interface<I: ?Sized + 'static>(
+        &self,
+        name: &str,
+    ) -> Result<Arc<I>, DIError> {
+        if let Some(service) = self.interfaces.get(&(TypeId::of::<I>(), name.to_string())) {
+            if let Some(arc) = service.downcast_ref::<Arc<I>>() {
+                return Ok(arc.clone());
+            }
+        }
+        
+        Err(DIError::ServiceNotFound(format!("{} ({})", std::any::type_name::<I>(), name)))
+    }
+    
+    /// Like `resolve`, also running an async singleton's factory if needed
+    pub async fn resolve_async<T: Any + Send + Sync + 'static>(&self) -> Result<Arc<T>, DIError> {
+        let Some(singleton) = self.async_singletons.get(&TypeId::of::<T>()) else {
+            return self.resolve::<T>();
+        };
+        
+        let service = singleton.instance.get_or_init(|| (singleton.init)()).await;
+        service
+            .downcast_ref::<Arc<T>>()
+            .cloned()
+            .ok_or_else(|| DIError::ServiceNotFound(std::any::type_name::<T>().to_string()))
+    }
+    
+    pub fn create_scope(&self) -> Scope<'_> {
+        Scope {
+            container: self,
+            scoped_instances: HashMap::new(),
+        }
+    }
+}
+
+impl Scope<'_> {
+    /// Use `instance` for `T` within this scope
+    pub fn provide<T: Any + Send + Sync + 'static>(&mut self, instance: Arc<T>) {
+        self.scoped_instances.insert(TypeId::of::<T>(), Box::new(instance));
+    }
+    
+    /// The instance of `T` provided to this scope, or the container's
+    pub fn resolve<T: Any + Send + Sync + 'static>(&self) -> Result<Arc<T>, DIError> {
+        // Check scoped instances first
+        if let Some(instance) = self.scoped_instances.get(&TypeId::of::<T>()) {
+            if let Some(arc) = instance.downcast_ref::<Arc<T>>() {
+                return Ok(arc.clone());
+            }
+        }
+        
+        // Scoped types only ever live in a scope, so the container can't have one
+        if self.container.scoped_types.contains(&TypeId::of::<T>()) {
+            return Err(DIError::ScopedInstanceMissing(std::any::type_name::<T>().to_string()));
+        }
+        
+        // Fall back to container
+        self.container.resolve::<T>()
+    }
+}
+
+impl ContainerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn register_singleton<T: Any + Send + Sync + 'static>(
+        mut self,
+        factory: impl Fn() -> Arc<T> + Send + Sync + 'static,
+    ) -> Self {
+        self.container.register_singleton(factory);
+        self
+    }
+    
+    pub fn register_factory<T: Any + Send + Sync + 'static>(
+        mut self,
+        factory: impl Fn() -> Arc<T> + Send + Sync + 'static,
+    ) -> Self {
+        self.container.register_factory(factory);
+        self
+    }
+    
+    pub fn build(self) -> Container {
+        self.container
+    }
+}
+
+/// Dependencies of a service, resolved together from the container. Tuples
+/// of up to three `Arc`s implement it.
+pub trait ResolveDependencies: Sized {
+    fn resolve(container: &Container) -> Result<Self, DIError>;
+}
+
+macro_rules! impl_resolve_dependencies {
+    ($($dep:ident),+) => {
+        impl<$($dep: Any + Send + Sync + 'static),+> ResolveDependencies for ($(Arc<$dep>,)+) {
+            fn resolve(container: &Container) -> Result<Self, DIError> {
+                Ok(($(container.resolve::<$dep>()?,)+))
+            }
+        }
+    };
+}
+
+impl_resolve_dependencies!(T1);
+impl_resolve_dependencies!(T1, T2);
+impl_resolve_dependencies!(T1, T2, T3);
+impl_resolve_dependencies!(T1, T2, T3, T4);
@@ -0,0 +1,228 @@
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tracing::debug;
+
+/// In-memory store of embedding vectors keyed by id, searched by cosine
+/// similarity. Every vector in a store has the same number of dimensions,
+/// fixed by the first one added.
+#[derive(Debug, Default)]
+pub struct EmbeddingStore {
+    entries: HashMap<String, Vec<f32>>,
+    dimensions: Option<usize>,
+}
+
+impl EmbeddingStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `vector` under `id`, replacing any vector already stored there
+    pub fn add(&mut self, id: impl Into<String>, vector: Vec<f32>) -> Result<()> {
+        if vector.is_empty() {
+            return Err(Error::InvalidRequest("embedding has no dimensions".into()));
+        }
+        self.check_dimensions(&vector)?;
+
+        self.dimensions = Some(vector.len());
+        self.entries.insert(id.into(), vector);
+        Ok(())
+    }
+
+    /// Remove the vector stored under `id`, returning whether there was one
+    pub fn remove(&mut self, id: &str) -> bool {
+        let removed = self.entries.remove(id).is_some();
+        if self.entries.is_empty() {
+            self.dimensions = None;
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Dimensions of the stored vectors, or `None` while the store is empty
+    pub fn dimensions(&self) -> Option<usize> {
+        self.dimensions
+    }
+
+    /// The `k` entries most similar to `query`, most similar first, with their
+    /// cosine similarity. Ties are broken by id so results are stable.
+    pub fn top_k(&self, query: &[f32], k: usize) -> Result<Vec<(String, f32)>> {
+        self.check_dimensions(query)?;
+
+        let mut scored: Vec<(String, f32)> = self
+            .entries
+            .iter()
+            .map(|(id, vector)| (id.clone(), cosine_similarity(query, vector)))
+            .collect();
+        scored.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then_with(|| a_id.cmp(b_id)));
+        scored.truncate(k);
+        Ok(scored)
+    }
+
+    fn check_dimensions(&self, vector: &[f32]) -> Result<()> {
+        match self.dimensions {
+            Some(dimensions) if dimensions != vector.len() => Err(Error::InvalidRequest(format!(
+                "embedding has {} dimensions, expected {}",
+                vector.len(),
+                dimensions
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Cosine of the angle between `a` and `b`; 0 when either is all zeros
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Text files under `dir`, as `(path, contents)` pairs sorted by path. Hidden
+/// files and directories are skipped, as are binary files, and contents are
+/// cut to at most `max_bytes`.
+pub fn read_text_files(dir: &Path, max_bytes: usize) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() {
+                match read_text_prefix(&path, max_bytes)? {
+                    Some(contents) => files.push((path.display().to_string(), contents)),
+                    None => debug!("Skipping binary file {}", path.display()),
+                }
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Up to `max_bytes` of the file at `path`, or `None` if it isn't UTF-8 text
+fn read_text_prefix(path: &Path, max_bytes: usize) -> Result<Option<String>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.take(max_bytes as u64).read_to_end(&mut bytes)?;
+    if bytes.contains(&0) {
+        return Ok(None);
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(Some(text)),
+        // The cut may land inside a character; keep everything before it
+        Err(e) if e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            Ok(Some(String::from_utf8(bytes).expect("truncated to valid UTF-8")))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> EmbeddingStore {
+        let mut store = EmbeddingStore::new();
+        store.add("east", vec![1.0, 0.0]).unwrap();
+        store.add("north", vec![0.0, 1.0]).unwrap();
+        store.add("north-east", vec![1.0, 1.0]).unwrap();
+        store.add("west", vec![-1.0, 0.0]).unwrap();
+        store
+    }
+
+    #[test]
+    fn test_top_k_ranks_by_cosine_similarity() {
+        let results = store().top_k(&[2.0, 0.5], 3).unwrap();
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["east", "north-east", "north"]);
+        assert!(results[0].1 > results[1].1 && results[1].1 > results[2].1);
+
+        // Magnitude doesn't matter, only direction
+        let results = store().top_k(&[-5.0, 0.0], 1).unwrap();
+        assert_eq!(results, vec![("west".to_string(), 1.0)]);
+    }
+
+    #[test]
+    fn test_top_k_with_k_larger_than_store() {
+        assert_eq!(store().top_k(&[0.0, 1.0], 10).unwrap().len(), 4);
+        assert!(EmbeddingStore::new().top_k(&[0.0, 1.0], 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_dimensions_are_rejected() {
+        let mut store = store();
+
+        let err = store.top_k(&[1.0, 0.0, 0.0], 1).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid request: embedding has 3 dimensions, expected 2");
+        assert!(store.add("up", vec![0.0, 0.0, 1.0]).is_err());
+        assert!(store.add("empty", vec![]).is_err());
+        assert_eq!(store.len(), 4);
+    }
+
+    #[test]
+    fn test_add_replaces_and_remove_resets_dimensions() {
+        let mut store = EmbeddingStore::new();
+        store.add("a", vec![1.0, 0.0]).unwrap();
+        store.add("a", vec![0.0, 1.0]).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.top_k(&[0.0, 1.0], 1).unwrap()[0].1, 1.0);
+
+        assert!(store.remove("a"));
+        assert!(!store.remove("a"));
+        assert_eq!(store.dimensions(), None);
+        store.add("b", vec![1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(store.dimensions(), Some(3));
+    }
+
+    #[test]
+    fn test_read_text_files_skips_binary_and_hidden_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/.cache")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("src/.cache/hidden.txt"), "secret").unwrap();
+        std::fs::write(dir.path().join("README.md"), "héllo world").unwrap();
+        std::fs::write(dir.path().join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+
+        let files = read_text_files(dir.path(), 1024).unwrap();
+        let names: Vec<String> = files
+            .iter()
+            .map(|(path, _)| Path::new(path).strip_prefix(dir.path()).unwrap().display().to_string())
+            .collect();
+        assert_eq!(names, vec!["README.md", "src/main.rs"]);
+
+        // "é" is two bytes; a cut through it drops the whole character
+        let files = read_text_files(dir.path(), 2).unwrap();
+        assert_eq!(files[0].1, "h");
+        assert_eq!(files[1].1, "fn");
+    }
+
+    #[test]
+    fn test_cosine_similarity_of_zero_vector() {
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+}
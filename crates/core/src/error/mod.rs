@@ -0,0 +1,581 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::num::{ParseFloatError, ParseIntError};
+use std::time::{Duration, SystemTime};
+use thiserror::Error as ThisError;
+
+#[cfg(test)]
+mod tests;
+
+/// Custom error type for the application
+#[derive(Debug)]
+pub enum Error {
+    /// Configuration errors
+    Config(String),
+    /// Provider errors (API calls, network, etc.)
+    Provider(String),
+    /// The provider does not know the requested model
+    ModelNotFound(String),
+    /// The provider is throttling requests; worth retrying later
+    RateLimited(String),
+    /// The provider refused the request as malformed or unauthorized
+    InvalidRequest(String),
+    /// Service container errors
+    Service(String),
+    /// IO errors
+    Io(std::io::Error),
+    /// A value couldn't be parsed; `field` names the value being read, when known
+    Parse {
+        field: Option<String>,
+        source: ParseError,
+    },
+    /// Other errors
+    Other(String),
+    /// Several attempts failed, in the order they were made
+    Multiple(Vec<Error>),
+    /// The named operation was cancelled before it finished
+    Cancelled(String),
+}
+
+/// Why a value couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Int(ParseIntError),
+    Float(ParseFloatError),
+    /// Invalid or unexpected JSON, with the decoder's message
+    Json(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Int(err) => err.fmt(f),
+            ParseError::Float(err) => err.fmt(f),
+            ParseError::Json(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Int(err) => Some(err),
+            ParseError::Float(err) => Some(err),
+            ParseError::Json(_) => None,
+        }
+    }
+}
+
+impl From<ParseIntError> for ParseError {
+    fn from(err: ParseIntError) -> Self {
+        ParseError::Int(err)
+    }
+}
+
+impl From<ParseFloatError> for ParseError {
+    fn from(err: ParseFloatError) -> Self {
+        ParseError::Float(err)
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(err: serde_json::Error) -> Self {
+        ParseError::Json(err.to_string())
+    }
+}
+
+impl Error {
+    /// A parse failure while reading `field`
+    pub fn parse(field: &str, source: impl Into<ParseError>) -> Self {
+        Error::Parse {
+            field: Some(field.to_string()),
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(msg) => write!(f, "Configuration error: {}", msg),
+            Error::Provider(msg) => write!(f, "Provider error: {}", msg),
+            Error::ModelNotFound(model) => write!(f, "Model not found: {}", model),
+            Error::RateLimited(msg) => write!(f, "Rate limit exceeded: {}", msg),
+            Error::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
+            Error::Service(msg) => write!(f, "Service error: {}", msg),
+            Error::Io(err) => write!(f, "IO error: {}", err),
+            Error::Parse { field: Some(field), source } => write!(f, "Parse error in {}: {}", field, source),
+            Error::Parse { field: None, source } => write!(f, "Parse error: {}", source),
+            Error::Other(msg) => write!(f, "Error: {}", msg),
+            Error::Multiple(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "Multiple errors occurred: {}", messages.join("; "))
+            }
+            Error::Cancelled(operation) => write!(f, "Cancelled: {}", operation),
+        }
+    }
+}
+
+/// `io::Error` isn't `Clone`, so a cloned `Io` error keeps only its kind and message
+impl Clone for Error {
+    fn clone(&self) -> Self {
+        match self {
+            Error::Config(msg) => Error::Config(msg.clone()),
+            Error::Provider(msg) => Error::Provider(msg.clone()),
+            Error::ModelNotFound(model) => Error::ModelNotFound(model.clone()),
+            Error::RateLimited(msg) => Error::RateLimited(msg.clone()),
+            Error::InvalidRequest(msg) => Error::InvalidRequest(msg.clone()),
+            Error::Service(msg) => Error::Service(msg.clone()),
+            Error::Io(err) => Error::Io(io::Error::new(err.kind(), err.to_string())),
+            Error::Parse { field, source } => Error::Parse {
+                field: field.clone(),
+                source: source.clone(),
+            },
+            Error::Other(msg) => Error::Other(msg.clone()),
+            Error::Multiple(errors) => Error::Multiple(errors.clone()),
+            Error::Cancelled(operation) => Error::Cancelled(operation.clone()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(err: ParseIntError) -> Self {
+        Error::Parse {
+            field: None,
+            source: err.into(),
+        }
+    }
+}
+
+impl From<ParseFloatError> for Error {
+    fn from(err: ParseFloatError) -> Self {
+        Error::Parse {
+            field: None,
+            source: err.into(),
+        }
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Error::Config(format!("TOML parsing error: {}", err))
+    }
+}
+
+impl From<git2::Error> for Error {
+    fn from(err: git2::Error) -> Self {
+        Error::Other(format!("Git error: {}", err.message()))
+    }
+}
+
+impl From<std::env::VarError> for Error {
+    fn from(err: std::env::VarError) -> Self {
+        Error::Config(format!("Environment variable error: {}", err))
+    }
+}
+
+/// Result type alias
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Structured error carrying a category, an optional chain of context
+/// messages, a recovery hint and telemetry
+#[derive(Debug, ThisError)]
+pub enum OpenCodeError {
+    #[error("Provider error: {0}")]
+    Provider(#[from] ProviderError),
+
+    #[error("Configuration error: {0}")]
+    Configuration(#[from] ConfigError),
+
+    #[error("Network error: {0}")]
+    Network(#[from] NetworkError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Parse error: {0}")]
+    Parse(#[from] std::num::ParseIntError),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Multiple errors occurred: {0:?}")]
+    Multiple(Vec<OpenCodeError>),
+
+    /// An error rebuilt by `from_json`, e.g. after crossing the Tauri IPC
+    /// boundary; only its type name, message and category survive
+    #[error("{message}")]
+    Remote {
+        error_type: String,
+        message: String,
+        category: ErrorCategory,
+    },
+
+    /// An error annotated through `with_context`, `with_recovery` or
+    /// `with_telemetry`; displays as the underlying error
+    #[error(transparent)]
+    Enhanced(Box<EnhancedError>),
+}
+
+#[derive(Debug, ThisError)]
+pub enum ProviderError {
+    #[error("API error: {message} (status: {status})")]
+    ApiError { status: u16, message: String },
+
+    #[error("Network error: {0}")]
+    NetworkError(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Rate limit exceeded")]
+    RateLimitExceeded,
+
+    #[error("Authentication failed: {0}")]
+    AuthenticationError(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+#[derive(Debug, ThisError)]
+pub enum ConfigError {
+    #[error("Configuration file not found")]
+    NotFound,
+
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Parse error: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Validation error: {0}")]
+    Validation(String),
+}
+
+#[derive(Debug, ThisError)]
+pub enum NetworkError {
+    #[error("Connection refused")]
+    ConnectionRefused,
+
+    #[error("Operation '{operation}' timed out after {}s", duration.as_secs())]
+    Timeout { operation: String, duration: Duration },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCategory {
+    Transient,
+    Configuration,
+    Internal,
+    External,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryPolicy {
+    None,
+    Exponential {
+        max_attempts: u32,
+        base_delay: Duration,
+        max_delay: Duration,
+    },
+    Fixed {
+        attempts: u32,
+        delay: Duration,
+    },
+}
+
+impl From<Error> for OpenCodeError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Config(msg) => Self::Configuration(ConfigError::Validation(msg)),
+            Error::Provider(msg) => Self::Provider(ProviderError::Other(msg)),
+            Error::ModelNotFound(model) => {
+                Self::Provider(ProviderError::InvalidRequest(format!("Model not found: {}", model)))
+            }
+            Error::RateLimited(_) => Self::Provider(ProviderError::RateLimitExceeded),
+            Error::InvalidRequest(msg) => Self::Provider(ProviderError::InvalidRequest(msg)),
+            Error::Io(err) => Self::Io(err),
+            Error::Parse { source: ParseError::Int(err), .. } => Self::Parse(err),
+            err @ (Error::Parse { .. } | Error::Cancelled(_)) => Self::Internal(err.to_string()),
+            Error::Service(msg) | Error::Other(msg) => Self::Internal(msg),
+            Error::Multiple(errors) => Self::Multiple(errors.into_iter().map(Self::from).collect()),
+        }
+    }
+}
+
+/// Collect every value when all results succeeded; otherwise return all
+/// of the errors, in order, as `OpenCodeError::Multiple`
+pub fn collect_results<T, E>(results: Vec<Result<T, E>>) -> Result<Vec<T>, OpenCodeError>
+where
+    E: Into<OpenCodeError>,
+{
+    let mut values = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(err) => errors.push(err.into()),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(OpenCodeError::Multiple(errors))
+    }
+}
+
+impl RetryPolicy {
+    /// The policy for transient failures: three attempts, backing off from one second
+    pub fn transient() -> Self {
+        Self::Exponential {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// How a caller might recover from an error
+#[derive(Debug, Clone)]
+pub enum Recovery {
+    Retry { after: Duration, max_attempts: u32 },
+    Fallback { alternative: String },
+    Manual(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorTelemetry {
+    pub timestamp: SystemTime,
+    pub request_id: Option<String>,
+    pub user_id: Option<String>,
+    pub additional_data: HashMap<String, String>,
+}
+
+/// An `OpenCodeError` together with the details attached to it
+#[derive(Debug)]
+pub struct EnhancedError {
+    error: OpenCodeError,
+    /// Context messages, oldest first
+    contexts: Vec<String>,
+    recovery: Option<Recovery>,
+    telemetry: Option<ErrorTelemetry>,
+}
+
+impl fmt::Display for EnhancedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for EnhancedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&self.error)
+    }
+}
+
+/// Wire format of `OpenCodeError::to_json`
+#[derive(Serialize, Deserialize)]
+struct SerializedError {
+    #[serde(rename = "type")]
+    error_type: String,
+    message: String,
+    category: ErrorCategory,
+    #[serde(default)]
+    contexts: Vec<String>,
+    recovery: Option<SerializedRecovery>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum SerializedRecovery {
+    Retry { after_ms: u64, max_attempts: u32 },
+    Fallback { alternative: String },
+    Manual { instructions: String },
+}
+
+impl From<&Recovery> for SerializedRecovery {
+    fn from(recovery: &Recovery) -> Self {
+        match recovery {
+            Recovery::Retry { after, max_attempts } => Self::Retry {
+                after_ms: after.as_millis() as u64,
+                max_attempts: *max_attempts,
+            },
+            Recovery::Fallback { alternative } => Self::Fallback {
+                alternative: alternative.clone(),
+            },
+            Recovery::Manual(instructions) => Self::Manual {
+                instructions: instructions.clone(),
+            },
+        }
+    }
+}
+
+impl From<SerializedRecovery> for Recovery {
+    fn from(recovery: SerializedRecovery) -> Self {
+        match recovery {
+            SerializedRecovery::Retry { after_ms, max_attempts } => Self::Retry {
+                after: Duration::from_millis(after_ms),
+                max_attempts,
+            },
+            SerializedRecovery::Fallback { alternative } => Self::Fallback { alternative },
+            SerializedRecovery::Manual { instructions } => Self::Manual(instructions),
+        }
+    }
+}
+
+impl OpenCodeError {
+    /// Attach a context message describing what was being done when the error occurred
+    pub fn with_context(self, context: impl Into<String>) -> Self {
+        let mut enhanced = self.into_enhanced();
+        enhanced.contexts.push(context.into());
+        Self::Enhanced(enhanced)
+    }
+
+    pub fn with_recovery(self, recovery: Recovery) -> Self {
+        let mut enhanced = self.into_enhanced();
+        enhanced.recovery = Some(recovery);
+        Self::Enhanced(enhanced)
+    }
+
+    pub fn with_telemetry(self, telemetry: ErrorTelemetry) -> Self {
+        let mut enhanced = self.into_enhanced();
+        enhanced.telemetry = Some(telemetry);
+        Self::Enhanced(enhanced)
+    }
+
+    fn into_enhanced(self) -> Box<EnhancedError> {
+        match self {
+            Self::Enhanced(enhanced) => enhanced,
+            error => Box::new(EnhancedError {
+                error,
+                contexts: Vec::new(),
+                recovery: None,
+                telemetry: None,
+            }),
+        }
+    }
+
+    /// The underlying error, without any attached details
+    pub fn root(&self) -> &OpenCodeError {
+        match self {
+            Self::Enhanced(enhanced) => &enhanced.error,
+            error => error,
+        }
+    }
+
+    /// Context messages in the order they were added
+    pub fn contexts(&self) -> Vec<String> {
+        match self {
+            Self::Enhanced(enhanced) => enhanced.contexts.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn recovery(&self) -> Option<&Recovery> {
+        match self {
+            Self::Enhanced(enhanced) => enhanced.recovery.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn telemetry(&self) -> Option<&ErrorTelemetry> {
+        match self {
+            Self::Enhanced(enhanced) => enhanced.telemetry.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The context chain, newest first, followed by the error itself,
+    /// e.g. "Initializing application: Loading configuration: IO error: ..."
+    pub fn full_message(&self) -> String {
+        let mut parts: Vec<String> = self.contexts().into_iter().rev().collect();
+        parts.push(self.to_string());
+        parts.join(": ")
+    }
+
+    /// Name of the underlying variant, e.g. "Provider" or "Network"
+    pub fn type_name(&self) -> &str {
+        match self.root() {
+            Self::Provider(_) => "Provider",
+            Self::Configuration(_) => "Configuration",
+            Self::Network(_) => "Network",
+            Self::Io(_) => "Io",
+            Self::Parse(_) => "Parse",
+            Self::Internal(_) => "Internal",
+            Self::Multiple(_) => "Multiple",
+            Self::Remote { error_type, .. } => error_type,
+            Self::Enhanced(enhanced) => enhanced.error.type_name(),
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self.root() {
+            Self::Provider(ProviderError::RateLimitExceeded) => ErrorCategory::Transient,
+            Self::Network(_) => ErrorCategory::Transient,
+            Self::Configuration(_) => ErrorCategory::Configuration,
+            Self::Internal(_) => ErrorCategory::Internal,
+            Self::Remote { category, .. } => *category,
+            _ => ErrorCategory::External,
+        }
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        match self.category() {
+            ErrorCategory::Transient => RetryPolicy::transient(),
+            _ => RetryPolicy::None,
+        }
+    }
+
+    /// Serialize the error for structured logging or IPC: its type, message,
+    /// category, context chain and recovery hint. Telemetry is not included.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&SerializedError {
+            error_type: self.type_name().to_string(),
+            message: self.to_string(),
+            category: self.category(),
+            contexts: self.contexts(),
+            recovery: self.recovery().map(SerializedRecovery::from),
+        })
+        .expect("serializing an error cannot fail")
+    }
+
+    /// Rebuild an error serialized with `to_json`. The result is a `Remote`
+    /// error carrying the original type name, message and category, plus
+    /// the context chain and recovery hint.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let serialized: SerializedError = serde_json::from_str(json)?;
+
+        let mut error = Self::Remote {
+            error_type: serialized.error_type,
+            message: serialized.message,
+            category: serialized.category,
+        };
+        for context in serialized.contexts {
+            error = error.with_context(context);
+        }
+        if let Some(recovery) = serialized.recovery {
+            error = error.with_recovery(recovery.into());
+        }
+        Ok(error)
+    }
+}
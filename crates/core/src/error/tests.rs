@@ -5,7 +5,98 @@
 
 use super::*;
 use std::io;
-use std::sync::Arc;
+
+mod error_tests {
+    use super::*;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn test_error_display() {
+        let err = Error::Config("Invalid API key".to_string());
+        assert_eq!(err.to_string(), "Configuration error: Invalid API key");
+
+        let err = Error::Provider("API rate limit exceeded".to_string());
+        assert_eq!(err.to_string(), "Provider error: API rate limit exceeded");
+
+        let err = Error::ModelNotFound("gpt-5".to_string());
+        assert_eq!(err.to_string(), "Model not found: gpt-5");
+
+        let err = Error::Service("Service not found".to_string());
+        assert_eq!(err.to_string(), "Service error: Service not found");
+
+        let err = Error::Other("Unknown error".to_string());
+        assert_eq!(err.to_string(), "Error: Unknown error");
+
+        let err = Error::Multiple(vec![
+            Error::RateLimited("slow down".to_string()),
+            Error::InvalidRequest("bad key".to_string()),
+        ]);
+        assert_eq!(
+            err.to_string(),
+            "Multiple errors occurred: Rate limit exceeded: slow down; Invalid request: bad key"
+        );
+
+        let err = Error::Cancelled("swarm.scale_up".to_string());
+        assert_eq!(err.to_string(), "Cancelled: swarm.scale_up");
+    }
+
+    #[test]
+    fn test_error_from_io() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "File not found");
+        let err: Error = io_err.into();
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[test]
+    fn test_error_clone_keeps_io_kind_and_message() {
+        let err = Error::Multiple(vec![
+            Error::Io(io::Error::new(io::ErrorKind::NotFound, "File not found")),
+            Error::RateLimited("slow down".to_string()),
+        ]);
+
+        let cloned = err.clone();
+        assert_eq!(cloned.to_string(), err.to_string());
+        match cloned {
+            Error::Multiple(errors) => assert!(matches!(&errors[0], Error::Io(e) if e.kind() == io::ErrorKind::NotFound)),
+            other => panic!("Expected Multiple, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_source() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Access denied");
+        let err = Error::Io(io_err);
+        assert!(StdError::source(&err).is_some());
+
+        let err = Error::Config("Bad config".to_string());
+        assert!(StdError::source(&err).is_none());
+    }
+
+    #[test]
+    fn test_error_from_parse_errors() {
+        let err: Error = "12x".parse::<u32>().unwrap_err().into();
+        assert!(matches!(err, Error::Parse { field: None, source: ParseError::Int(_) }));
+        assert_eq!(err.to_string(), "Parse error: invalid digit found in string");
+        assert!(StdError::source(&err).is_some());
+
+        let err: Error = "warm".parse::<f32>().unwrap_err().into();
+        assert!(matches!(err, Error::Parse { source: ParseError::Float(_), .. }));
+
+        let err = Error::parse("OPENAI_TIMEOUT", "soon".parse::<u64>().unwrap_err());
+        assert_eq!(err.to_string(), "Parse error in OPENAI_TIMEOUT: invalid digit found in string");
+        assert!(matches!(OpenCodeError::from(err), OpenCodeError::Parse(_)));
+    }
+
+    #[test]
+    fn test_error_from_env_var() {
+        let env_err = std::env::VarError::NotPresent;
+        let err: Error = env_err.into();
+        match err {
+            Error::Config(msg) => assert!(msg.contains("Environment variable error")),
+            _ => panic!("Expected Config error"),
+        }
+    }
+}
 
 #[cfg(test)]
 mod error_handling_tests {
@@ -59,6 +150,21 @@ mod error_handling_tests {
         assert!(full_message.contains("File not found"));
     }
 
+    #[test]
+    fn test_full_message_renders_context_newest_first() {
+        let error = OpenCodeError::Internal("boom".to_string())
+            .with_context("Loading configuration")
+            .with_context("Initializing application");
+
+        assert_eq!(
+            error.full_message(),
+            "Initializing application: Loading configuration: Internal error: boom"
+        );
+        // Attached details don't change how the error is classified
+        assert!(matches!(error.root(), OpenCodeError::Internal(_)));
+        assert_eq!(error.category(), ErrorCategory::Internal);
+    }
+
     #[test]
     fn test_error_recovery_suggestions() {
         // GIVEN: Errors with recovery suggestions
@@ -231,6 +337,44 @@ mod error_handling_tests {
         assert_eq!(json["recovery"]["alternative"], "Use gpt-3.5-turbo instead");
     }
 
+    #[test]
+    fn test_error_serialization_uses_variant_name() {
+        let error = OpenCodeError::Network(NetworkError::ConnectionRefused);
+        let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+
+        assert_eq!(json["type"], "Network");
+        assert_eq!(json["category"], "Transient");
+        assert!(json["recovery"].is_null());
+    }
+
+    #[test]
+    fn test_error_json_round_trip() {
+        let error = OpenCodeError::Provider(ProviderError::ApiError {
+            status: 404,
+            message: "Model not found".to_string(),
+        })
+        .with_context("Calling OpenAI API")
+        .with_context("Answering question")
+        .with_recovery(Recovery::Fallback {
+            alternative: "Use gpt-3.5-turbo instead".to_string(),
+        });
+
+        let restored = OpenCodeError::from_json(&error.to_json()).unwrap();
+
+        assert_eq!(restored.type_name(), "Provider");
+        assert_eq!(restored.to_string(), error.to_string());
+        assert_eq!(restored.contexts(), error.contexts());
+        assert_eq!(restored.category(), error.category());
+        match restored.recovery() {
+            Some(Recovery::Fallback { alternative }) => {
+                assert_eq!(alternative, "Use gpt-3.5-turbo instead");
+            }
+            other => panic!("Expected Fallback recovery, got {:?}", other),
+        }
+        // Serializing the rebuilt error gives the same JSON
+        assert_eq!(restored.to_json(), error.to_json());
+    }
+
     #[test]
     fn test_error_aggregation() {
         // GIVEN: Multiple errors that need to be aggregated
@@ -264,6 +408,31 @@ mod error_handling_tests {
         }
     }
 
+    #[test]
+    fn test_collect_results_all_ok() {
+        let results: Vec<Result<u32, OpenCodeError>> = vec![Ok(1), Ok(2), Ok(3)];
+        assert_eq!(collect_results(results).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_collect_results_keeps_every_error_in_order() {
+        let results: Vec<Result<u32, Error>> = vec![
+            Ok(1),
+            Err(Error::RateLimited("slow down".to_string())),
+            Ok(2),
+            Err(Error::Config("bad model".to_string())),
+        ];
+
+        match collect_results(results) {
+            Err(OpenCodeError::Multiple(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(matches!(errors[0], OpenCodeError::Provider(ProviderError::RateLimitExceeded)));
+                assert!(matches!(errors[1], OpenCodeError::Configuration(ConfigError::Validation(_))));
+            }
+            other => panic!("Expected Multiple error, got {:?}", other),
+        }
+    }
+
     #[tokio::test]
     async fn test_async_error_handling() {
         // GIVEN: An async operation that might fail
@@ -303,179 +472,3 @@ mod error_handling_tests {
         assert!(matches!(our_parse_error, OpenCodeError::Parse(_)));
     }
 }
-
-// Type definitions that will be moved to the actual implementation
-use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum OpenCodeError {
-    #[error("Provider error: {0}")]
-    Provider(#[from] ProviderError),
-    
-    #[error("Configuration error: {0}")]
-    Configuration(#[from] ConfigError),
-    
-    #[error("Network error: {0}")]
-    Network(#[from] NetworkError),
-    
-    #[error("IO error: {0}")]
-    Io(#[from] io::Error),
-    
-    #[error("Parse error: {0}")]
-    Parse(#[from] std::num::ParseIntError),
-    
-    #[error("Internal error: {0}")]
-    Internal(String),
-    
-    #[error("Multiple errors occurred: {0:?}")]
-    Multiple(Vec<OpenCodeError>),
-}
-
-#[derive(Debug, Error)]
-pub enum NetworkError {
-    #[error("Connection refused")]
-    ConnectionRefused,
-    
-    #[error("Operation '{operation}' timed out after {duration:?}")]
-    Timeout {
-        operation: String,
-        duration: std::time::Duration,
-    },
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum ErrorCategory {
-    Transient,
-    Configuration,
-    Internal,
-    External,
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum RetryPolicy {
-    None,
-    Exponential {
-        max_attempts: u32,
-        base_delay: std::time::Duration,
-        max_delay: std::time::Duration,
-    },
-    Fixed {
-        attempts: u32,
-        delay: std::time::Duration,
-    },
-}
-
-#[derive(Debug, Clone)]
-pub enum Recovery {
-    Retry {
-        after: std::time::Duration,
-        max_attempts: u32,
-    },
-    Fallback {
-        alternative: String,
-    },
-    Manual(String),
-}
-
-#[derive(Debug, Clone)]
-pub struct ErrorTelemetry {
-    pub timestamp: std::time::SystemTime,
-    pub request_id: Option<String>,
-    pub user_id: Option<String>,
-    pub additional_data: HashMap<String, String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct SerializedError {
-    #[serde(rename = "type")]
-    error_type: String,
-    message: String,
-    contexts: Vec<String>,
-    recovery: Option<SerializedRecovery>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct SerializedRecovery {
-    #[serde(rename = "type")]
-    recovery_type: String,
-    #[serde(flatten)]
-    data: serde_json::Value,
-}
-
-// Error enhancement implementation
-struct ErrorEnhancement {
-    contexts: Vec<String>,
-    recovery: Option<Recovery>,
-    telemetry: Option<ErrorTelemetry>,
-}
-
-// Extension trait for error enhancement
-impl OpenCodeError {
-    pub fn with_context(self, context: impl Into<String>) -> Self {
-        // Implementation would store context
-        self
-    }
-    
-    pub fn with_recovery(self, recovery: Recovery) -> Self {
-        // Implementation would store recovery
-        self
-    }
-    
-    pub fn with_telemetry(self, telemetry: ErrorTelemetry) -> Self {
-        // Implementation would store telemetry
-        self
-    }
-    
-    pub fn contexts(&self) -> Vec<String> {
-        // Implementation would return stored contexts
-        vec![]
-    }
-    
-    pub fn recovery(&self) -> Option<&Recovery> {
-        // Implementation would return stored recovery
-        None
-    }
-    
-    pub fn telemetry(&self) -> Option<&ErrorTelemetry> {
-        // Implementation would return stored telemetry
-        None
-    }
-    
-    pub fn full_message(&self) -> String {
-        // Implementation would build full error message with context
-        self.to_string()
-    }
-    
-    pub fn category(&self) -> ErrorCategory {
-        match self {
-            Self::Provider(ProviderError::RateLimitExceeded) => ErrorCategory::Transient,
-            Self::Network(_) => ErrorCategory::Transient,
-            Self::Configuration(_) => ErrorCategory::Configuration,
-            Self::Internal(_) => ErrorCategory::Internal,
-            _ => ErrorCategory::External,
-        }
-    }
-    
-    pub fn retry_policy(&self) -> RetryPolicy {
-        match self.category() {
-            ErrorCategory::Transient => RetryPolicy::Exponential {
-                max_attempts: 3,
-                base_delay: std::time::Duration::from_secs(1),
-                max_delay: std::time::Duration::from_secs(60),
-            },
-            _ => RetryPolicy::None,
-        }
-    }
-    
-    pub fn to_json(&self) -> String {
-        // Implementation would serialize to JSON
-        serde_json::to_string_pretty(&SerializedError {
-            error_type: "Provider".to_string(),
-            message: self.to_string(),
-            contexts: self.contexts(),
-            recovery: None,
-        }).unwrap()
-    }
-}
\ No newline at end of file
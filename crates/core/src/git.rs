@@ -0,0 +1,381 @@
+use crate::error::{Error, Result};
+use git2::{BranchType, Commit, DiffFormat, ObjectType, Repository, Signature};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Name and email recorded on checkpoint commits and tags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+impl Default for CheckpointAuthor {
+    fn default() -> Self {
+        Self {
+            name: "OpenCode Agent".to_string(),
+            email: "agent@opencode.dev".to_string(),
+        }
+    }
+}
+
+/// How a checkpoint is recorded. The default is an annotated tag signed by
+/// the OpenCode agent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckpointOptions {
+    /// Create a lightweight tag (a plain ref) instead of an annotated tag object
+    pub lightweight: bool,
+    /// Commit and tag as someone other than the default agent identity
+    pub author: Option<CheckpointAuthor>,
+}
+
+/// Git operations used by the checkpoint manager, behind a trait so tests
+/// can substitute a mock for a real repository
+#[cfg_attr(test, mockall::automock)]
+pub trait GitOperations: Send + Sync {
+    /// Commit all changes on `branch_name` and tag the commit. Returns the tag name.
+    fn save_checkpoint(
+        &self,
+        repo_path: &Path,
+        branch_name: &str,
+        message: &str,
+        options: &CheckpointOptions,
+    ) -> Result<String>;
+
+    /// Checkpoint tags of an agent
+    fn list_checkpoints(&self, repo_path: &Path, agent_id: &str) -> Result<Vec<String>>;
+
+    /// Create a branch for `new_agent_id` at the checkpoint. Returns the branch name.
+    fn restore_checkpoint(&self, repo_path: &Path, checkpoint_tag: &str, new_agent_id: &str) -> Result<String>;
+
+    /// Unified diff of the checkpoint commit against its parent
+    fn diff_checkpoint(&self, repo_path: &Path, checkpoint_tag: &str) -> Result<String>;
+}
+
+/// Name of the tag prefix holding an agent's checkpoints
+fn checkpoint_prefix(agent_id: &str) -> String {
+    format!("cp/{}", agent_id)
+}
+
+/// The agent an agent branch belongs to; branches are named `agent/<id>`
+/// by the CLI and `agent-<id>` by the supervisor
+fn agent_id_from_branch(branch_name: &str) -> &str {
+    branch_name
+        .strip_prefix("agent/")
+        .or_else(|| branch_name.strip_prefix("agent-"))
+        .unwrap_or(branch_name)
+}
+
+/// The commit a checkpoint tag points at, whether the tag is annotated or lightweight
+fn checkpoint_commit<'r>(repo: &'r Repository, checkpoint_tag: &str) -> Result<Commit<'r>> {
+    let commit = repo
+        .revparse_single(&format!("refs/tags/{}", checkpoint_tag))
+        .map_err(|_| Error::Other(format!("Checkpoint '{}' not found", checkpoint_tag)))?
+        .peel_to_commit()?;
+    Ok(commit)
+}
+
+/// `GitOperations` backed by libgit2
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealGitOperations;
+
+impl GitOperations for RealGitOperations {
+    fn save_checkpoint(
+        &self,
+        repo_path: &Path,
+        branch_name: &str,
+        message: &str,
+        options: &CheckpointOptions,
+    ) -> Result<String> {
+        let repo = Repository::open(repo_path)?;
+        let author = options.author.clone().unwrap_or_default();
+        let signature = Signature::now(&author.name, &author.email)?;
+
+        // The index only describes the checked-out branch, so committing it
+        // anywhere else would record the wrong files
+        let head = repo.head()?;
+        if head.shorthand() != Some(branch_name) {
+            return Err(Error::Other(format!(
+                "Branch '{}' is not checked out in {}",
+                branch_name,
+                repo_path.display()
+            )));
+        }
+
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+
+        let parent = head.peel_to_commit()?;
+        let commit_id = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[&parent])?;
+
+        let tag_name = format!(
+            "{}/{}",
+            checkpoint_prefix(agent_id_from_branch(branch_name)),
+            Uuid::new_v4()
+        );
+        let commit = repo.find_object(commit_id, Some(ObjectType::Commit))?;
+        if options.lightweight {
+            repo.tag_lightweight(&tag_name, &commit, false)?;
+        } else {
+            repo.tag(&tag_name, &commit, &signature, "OpenCode Checkpoint", false)?;
+        }
+
+        Ok(tag_name)
+    }
+
+    fn list_checkpoints(&self, repo_path: &Path, agent_id: &str) -> Result<Vec<String>> {
+        let repo = Repository::open(repo_path)?;
+        let tags = repo.tag_names(Some(&format!("{}/*", checkpoint_prefix(agent_id))))?;
+        Ok(tags.iter().flatten().map(String::from).collect())
+    }
+
+    fn restore_checkpoint(&self, repo_path: &Path, checkpoint_tag: &str, new_agent_id: &str) -> Result<String> {
+        let repo = Repository::open(repo_path)?;
+        let commit = checkpoint_commit(&repo, checkpoint_tag)?;
+
+        let branch_name = format!("agent/{}", new_agent_id);
+        if repo.find_branch(&branch_name, BranchType::Local).is_ok() {
+            return Err(Error::Other(format!("Branch '{}' already exists", branch_name)));
+        }
+        repo.branch(&branch_name, &commit, false)?;
+
+        Ok(branch_name)
+    }
+
+    fn diff_checkpoint(&self, repo_path: &Path, checkpoint_tag: &str) -> Result<String> {
+        let repo = Repository::open(repo_path)?;
+        let commit = checkpoint_commit(&repo, checkpoint_tag)?;
+        let parent_tree = match commit.parent_count() {
+            0 => None,
+            _ => Some(commit.parent(0)?.tree()?),
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+
+        let mut patch = String::new();
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            // File and hunk headers carry their own text; content lines need their marker
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+        Ok(patch)
+    }
+}
+
+/// Saves, lists, inspects and restores agent checkpoints in one repository
+#[derive(Clone)]
+pub struct GitCheckpointManager {
+    git: Arc<dyn GitOperations>,
+    repo_path: PathBuf,
+}
+
+impl GitCheckpointManager {
+    pub fn new(repo_path: impl Into<PathBuf>) -> Self {
+        Self::with_operations(Arc::new(RealGitOperations), repo_path)
+    }
+
+    pub fn with_operations(git: Arc<dyn GitOperations>, repo_path: impl Into<PathBuf>) -> Self {
+        Self {
+            git,
+            repo_path: repo_path.into(),
+        }
+    }
+
+    pub fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
+    /// Save a checkpoint with the default options
+    pub fn save_checkpoint(&self, branch_name: &str, message: &str) -> Result<String> {
+        self.save_checkpoint_with(branch_name, message, &CheckpointOptions::default())
+    }
+
+    pub fn save_checkpoint_with(
+        &self,
+        branch_name: &str,
+        message: &str,
+        options: &CheckpointOptions,
+    ) -> Result<String> {
+        self.git.save_checkpoint(&self.repo_path, branch_name, message, options)
+    }
+
+    pub fn list_checkpoints(&self, agent_id: &str) -> Result<Vec<String>> {
+        self.git.list_checkpoints(&self.repo_path, agent_id)
+    }
+
+    pub fn restore_checkpoint(&self, checkpoint_tag: &str, new_agent_id: &str) -> Result<String> {
+        self.git.restore_checkpoint(&self.repo_path, checkpoint_tag, new_agent_id)
+    }
+
+    pub fn diff_checkpoint(&self, checkpoint_tag: &str) -> Result<String> {
+        self.git.diff_checkpoint(&self.repo_path, checkpoint_tag)
+    }
+}
+
+impl std::fmt::Debug for GitCheckpointManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitCheckpointManager")
+            .field("repo_path", &self.repo_path)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::predicate::eq;
+
+    /// A repository with one commit on `agent/alice`, checked out
+    fn repo_on_agent_branch() -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        {
+            let signature = Signature::now("Test", "test@example.com").unwrap();
+            let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+            let commit = repo
+                .commit(None, &signature, &signature, "initial", &tree, &[])
+                .unwrap();
+            repo.branch("agent/alice", &repo.find_commit(commit).unwrap(), false)
+                .unwrap();
+            repo.set_head("refs/heads/agent/alice").unwrap();
+        }
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_agent_id_from_branch() {
+        assert_eq!(agent_id_from_branch("agent/alice"), "alice");
+        assert_eq!(agent_id_from_branch("agent-alice"), "alice");
+        assert_eq!(agent_id_from_branch("main"), "main");
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let (dir, repo) = repo_on_agent_branch();
+        std::fs::write(dir.path().join("notes.txt"), "work in progress").unwrap();
+        let git = RealGitOperations;
+
+        let tag = git
+            .save_checkpoint(dir.path(), "agent/alice", "Initial work", &CheckpointOptions::default())
+            .unwrap();
+        assert!(tag.starts_with("cp/alice/"));
+        assert_eq!(git.list_checkpoints(dir.path(), "alice").unwrap(), vec![tag.clone()]);
+        assert!(git.list_checkpoints(dir.path(), "bob").unwrap().is_empty());
+
+        let branch = git.restore_checkpoint(dir.path(), &tag, "alice-2").unwrap();
+        assert_eq!(branch, "agent/alice-2");
+        let restored = repo.find_branch(&branch, BranchType::Local).unwrap();
+        let commit = restored.get().peel_to_commit().unwrap();
+        assert_eq!(commit.message(), Some("Initial work"));
+        assert!(commit.tree().unwrap().get_name("notes.txt").is_some());
+    }
+
+    #[test]
+    fn test_save_checkpoint_requires_checked_out_branch() {
+        let (dir, _repo) = repo_on_agent_branch();
+        let err = RealGitOperations
+            .save_checkpoint(dir.path(), "agent/bob", "work", &CheckpointOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("not checked out"));
+    }
+
+    #[test]
+    fn test_annotated_checkpoint_uses_author() {
+        let (dir, repo) = repo_on_agent_branch();
+        let options = CheckpointOptions {
+            author: Some(CheckpointAuthor {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+            }),
+            ..CheckpointOptions::default()
+        };
+
+        let tag_name = RealGitOperations
+            .save_checkpoint(dir.path(), "agent/alice", "work", &options)
+            .unwrap();
+
+        let tag_id = repo.refname_to_id(&format!("refs/tags/{}", tag_name)).unwrap();
+        let tag = repo.find_tag(tag_id).unwrap();
+        assert_eq!(tag.tagger().unwrap().name(), Some("Alice"));
+        let commit = tag.target().unwrap().peel_to_commit().unwrap();
+        assert_eq!(commit.author().email(), Some("alice@example.com"));
+    }
+
+    #[test]
+    fn test_lightweight_checkpoint() {
+        let (dir, repo) = repo_on_agent_branch();
+        let options = CheckpointOptions {
+            lightweight: true,
+            ..CheckpointOptions::default()
+        };
+
+        let tag_name = RealGitOperations
+            .save_checkpoint(dir.path(), "agent/alice", "work", &options)
+            .unwrap();
+
+        // A lightweight tag points straight at the commit, with no tag object
+        let target = repo.refname_to_id(&format!("refs/tags/{}", tag_name)).unwrap();
+        assert!(repo.find_tag(target).is_err());
+        let commit = repo.find_commit(target).unwrap();
+        assert_eq!(commit.author().name(), Some("OpenCode Agent"));
+
+        // Lightweight checkpoints can be listed and restored like annotated ones
+        let git = RealGitOperations;
+        assert_eq!(git.list_checkpoints(dir.path(), "alice").unwrap(), vec![tag_name.clone()]);
+        assert!(git.restore_checkpoint(dir.path(), &tag_name, "alice-2").is_ok());
+    }
+
+    #[test]
+    fn test_diff_checkpoint() {
+        let (dir, _repo) = repo_on_agent_branch();
+        std::fs::write(dir.path().join("notes.txt"), "first line\n").unwrap();
+        let git = RealGitOperations;
+        git.save_checkpoint(dir.path(), "agent/alice", "add notes", &CheckpointOptions::default())
+            .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "first line\nsecond line\n").unwrap();
+        let tag = git
+            .save_checkpoint(dir.path(), "agent/alice", "extend notes", &CheckpointOptions::default())
+            .unwrap();
+
+        let diff = git.diff_checkpoint(dir.path(), &tag).unwrap();
+        assert!(diff.contains("diff --git a/notes.txt b/notes.txt"), "{}", diff);
+        assert!(diff.contains("\n first line\n+second line\n"), "{}", diff);
+        assert!(git.diff_checkpoint(dir.path(), "cp/alice/missing").is_err());
+    }
+
+    #[test]
+    fn test_restore_unknown_checkpoint() {
+        let (dir, _repo) = repo_on_agent_branch();
+        assert!(RealGitOperations
+            .restore_checkpoint(dir.path(), "cp/alice/missing", "alice-2")
+            .is_err());
+    }
+
+    #[test]
+    fn test_manager_forwards_repo_path() {
+        let mut git = MockGitOperations::new();
+        git.expect_save_checkpoint()
+            .with(
+                eq(Path::new("/repo")),
+                eq("agent/alice"),
+                eq("msg"),
+                eq(CheckpointOptions::default()),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok("cp/alice/1".to_string()));
+
+        git.expect_diff_checkpoint()
+            .with(eq(Path::new("/repo")), eq("cp/alice/1"))
+            .times(1)
+            .returning(|_, _| Ok("+new line\n".to_string()));
+
+        let manager = GitCheckpointManager::with_operations(Arc::new(git), "/repo");
+        assert_eq!(manager.save_checkpoint("agent/alice", "msg").unwrap(), "cp/alice/1");
+        assert_eq!(manager.diff_checkpoint("cp/alice/1").unwrap(), "+new line\n");
+    }
+}
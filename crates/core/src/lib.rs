@@ -1,28 +1,59 @@
+pub mod behavior;
+pub mod cache;
 pub mod config;
+pub mod container;
+pub mod di;
+pub mod embeddings_store;
 pub mod error;
+pub mod git;
 pub mod personas;
 pub mod provider;
+pub mod rate_limit;
+pub mod retry;
+pub mod security;
 pub mod service;
 pub mod slash;
+pub mod supervisor;
+pub mod swarm;
+pub mod template;
+pub mod tokenize;
 
 #[cfg(test)]
 mod additional_tests;
 
 use config::Config;
-use error::Result;
-use provider::{CompletionRequest, Message};
+use error::{Error, Result};
+use futures::stream::BoxStream;
+use personas::{Persona, DEFAULT_PERSONA};
+use provider::{CompletionRequest, CompletionRequestBuilder, CompletionResponse, Message, Role, StreamChunk};
 use service::ServiceContainer;
+use std::collections::HashMap;
 use std::sync::OnceLock;
 
 static SERVICE_CONTAINER: OnceLock<ServiceContainer> = OnceLock::new();
 
-/// Initialize the global service container
+/// Install a tracing subscriber that logs to stderr, filtered by `RUST_LOG`
+/// (warnings and errors only when it is unset). Does nothing if a subscriber
+/// is already installed.
+pub fn init_tracing() {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn"));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+/// Initialize the global service container. Fails if it is already
+/// initialized; see `init_or_get` for a lenient alternative.
 pub fn init(config: Config) -> Result<()> {
-    let container = ServiceContainer::new(config)?;
-    SERVICE_CONTAINER
-        .set(container)
-        .map_err(|_| error::Error::Service("Service container already initialized".into()))?;
-    Ok(())
+    init_in(&SERVICE_CONTAINER, config)
+}
+
+/// Initialize the global service container unless it already is, and return
+/// it. When it already exists `config` is ignored.
+pub fn init_or_get(config: Config) -> Result<&'static ServiceContainer> {
+    init_or_get_in(&SERVICE_CONTAINER, config)
 }
 
 /// Get the global service container
@@ -32,98 +63,162 @@ pub fn get_service_container() -> Result<&'static ServiceContainer> {
         .ok_or_else(|| error::Error::Service("Service container not initialized".into()))
 }
 
+/// Get the global service container, first initializing it from the
+/// default config files and environment if nothing has yet
+pub fn ensure_initialized() -> Result<&'static ServiceContainer> {
+    if let Some(container) = SERVICE_CONTAINER.get() {
+        return Ok(container);
+    }
+    init_or_get(Config::load_layered(&Config::default_paths())?)
+}
+
+fn init_in(cell: &OnceLock<ServiceContainer>, config: Config) -> Result<()> {
+    let container = ServiceContainer::new(config)?;
+    cell.set(container)
+        .map_err(|_| error::Error::Service("Service container already initialized".into()))
+}
+
+fn init_or_get_in(cell: &OnceLock<ServiceContainer>, config: Config) -> Result<&ServiceContainer> {
+    if let Some(container) = cell.get() {
+        return Ok(container);
+    }
+    let container = ServiceContainer::new(config)?;
+    // Another thread may have initialized it meanwhile; its container wins
+    Ok(cell.get_or_init(|| container))
+}
+
 /// Backward compatible ask function
 pub async fn ask(prompt: &str) -> Result<String> {
     let container = get_service_container()?;
-    let provider = container.get_default_provider()?;
-
-    let request = CompletionRequest {
-        model: container.config().openai.default_model.clone(),
-        messages: vec![Message {
-            role: "user".to_string(),
-            content: prompt.to_string(),
-        }],
-        temperature: Some(0.7),
-        max_tokens: Some(1000),
-        stream: false,
-    };
 
-    let response = provider.complete(request).await?;
+    let request = ask_request(&container.config().openai.default_model, vec![user_message(prompt)]).build();
+
+    let response = container.complete(request).await?;
     Ok(response.content)
 }
 
+/// Ask a question and stream the answer as it is generated
+pub async fn ask_stream(prompt: &str) -> Result<BoxStream<'static, Result<StreamChunk>>> {
+    let container = get_service_container()?;
+
+    let request = ask_request(&container.config().openai.default_model, vec![user_message(prompt)])
+        .stream(true)
+        .build();
+
+    container.stream(request).await
+}
+
 /// Ask with a specific model
 pub async fn ask_with_model(prompt: &str, model: &str) -> Result<String> {
     let container = get_service_container()?;
-    let provider = container.get_default_provider()?;
-
-    let request = CompletionRequest {
-        model: model.to_string(),
-        messages: vec![Message {
-            role: "user".to_string(),
-            content: prompt.to_string(),
-        }],
-        temperature: Some(0.7),
-        max_tokens: Some(1000),
-        stream: false,
-    };
 
-    let response = provider.complete(request).await?;
+    let request = ask_request(model, vec![user_message(prompt)]).build();
+
+    let response = container.complete(request).await?;
     Ok(response.content)
 }
 
 /// Ask with messages (conversation context)
 pub async fn ask_with_messages(messages: Vec<Message>) -> Result<String> {
-    let container = get_service_container()?;
-    let provider = container.get_default_provider()?;
-
-    let request = CompletionRequest {
-        model: container.config().openai.default_model.clone(),
-        messages,
-        temperature: Some(0.7),
-        max_tokens: Some(1000),
-        stream: false,
-    };
+    Ok(ask_with_messages_response(messages).await?.content)
+}
 
-    let response = provider.complete(request).await?;
-    Ok(response.content)
+/// Ask with messages, returning the full response with model and token usage
+pub async fn ask_with_messages_response(messages: Vec<Message>) -> Result<CompletionResponse> {
+    ask_with_messages_as(messages, None).await
+}
+
+/// Ask with messages as `persona`, if given
+async fn ask_with_messages_as(messages: Vec<Message>, persona: Option<&Persona>) -> Result<CompletionResponse> {
+    let container = get_service_container()?;
+    let request = messages_request(&container.config().openai.default_model, messages, persona);
+    container.complete(request).await
 }
 
 /// Ask with a persona
 pub async fn ask_with_persona(prompt: &str, persona: &str) -> Result<String> {
+    Ok(ask_with_persona_response(prompt, persona).await?.content)
+}
+
+/// Ask with a persona, returning the full response with model and token usage.
+/// The persona is one of the built-ins or from personas.yml; an unknown
+/// persona falls back to the built-in "default" one.
+pub async fn ask_with_persona_response(prompt: &str, persona: &str) -> Result<CompletionResponse> {
+    let personas = available_personas();
+    let persona = find_persona(&personas, persona)?;
+    ask_with_messages_as(persona_messages(persona, prompt), Some(persona)).await
+}
+
+/// Ask with a persona and stream the answer as it is generated
+pub async fn ask_with_persona_stream(
+    prompt: &str,
+    persona: &str,
+) -> Result<BoxStream<'static, Result<StreamChunk>>> {
+    let personas = available_personas();
+    let persona = find_persona(&personas, persona)?;
+
     let container = get_service_container()?;
-    let provider = container.get_default_provider()?;
-
-    // Create system message with persona context
-    let system_message = match persona {
-        "default" => "You are a helpful assistant.".to_string(),
-        "expert" => "You are an expert software developer with deep knowledge of programming languages, best practices, and system design.".to_string(),
-        "rusty" => "You are a Rust programming expert who loves systems programming, memory safety, and performance optimization.".to_string(),
-        "pythonic" => "You are a Python expert who values clean, readable code and knows the ecosystem deeply.".to_string(),
-        _ => format!("You are a helpful assistant with the personality of a {} expert.", persona),
-    };
+    let messages = persona_messages(persona, prompt);
+    let request = messages_request(&container.config().openai.default_model, messages, Some(persona));
+    container.stream(request).await
+}
+
+/// The built-in personas merged with personas.yml, or just the built-ins
+/// when the file can't be read
+fn available_personas() -> HashMap<String, Persona> {
+    personas::load_personas().unwrap_or_else(|e| {
+        tracing::warn!("Ignoring unreadable personas file: {}", e);
+        personas::builtin_personas()
+            .into_iter()
+            .map(|persona| (persona.name.clone(), persona))
+            .collect()
+    })
+}
 
-    let messages = vec![
-        Message {
-            role: "system".to_string(),
-            content: system_message,
-        },
-        Message {
-            role: "user".to_string(),
-            content: prompt.to_string(),
-        },
-    ];
-
-    let request = CompletionRequest {
-        model: container.config().openai.default_model.clone(),
-        messages,
-        temperature: Some(0.7),
-        max_tokens: Some(1000),
-        stream: false,
+/// Persona `name`, or the default persona when there is none by that name
+fn find_persona<'a>(personas: &'a HashMap<String, Persona>, name: &str) -> Result<&'a Persona> {
+    if let Some(persona) = personas.get(name) {
+        return Ok(persona);
+    }
+    tracing::warn!("Persona '{}' not found; using the '{}' persona", name, DEFAULT_PERSONA);
+    personas
+        .get(DEFAULT_PERSONA)
+        .ok_or_else(|| Error::Config(format!("Persona '{}' not found", name)))
+}
+
+/// The persona's system prompt followed by `prompt`
+fn persona_messages(persona: &Persona, prompt: &str) -> Vec<Message> {
+    let system = Message {
+        role: Role::System,
+        content: persona.system_prompt.clone(),
     };
+    vec![system, user_message(prompt)]
+}
 
-    let response = provider.complete(request).await?;
-    Ok(response.content)
+/// Request sending `messages` with the `ask` defaults. When answering as
+/// `persona`, its model and temperature replace the defaults where set.
+fn messages_request(default_model: &str, messages: Vec<Message>, persona: Option<&Persona>) -> CompletionRequest {
+    let mut request = ask_request(default_model, messages).build();
+    if let Some(persona) = persona {
+        persona.apply_to(&mut request);
+    }
+    request
+}
+
+/// Builder for a request with the sampling defaults every `ask` function uses
+fn ask_request(model: &str, messages: Vec<Message>) -> CompletionRequestBuilder {
+    CompletionRequest::builder()
+        .model(model)
+        .messages(messages)
+        .temperature(0.7)
+        .max_tokens(1000)
+}
+
+fn user_message(prompt: &str) -> Message {
+    Message {
+        role: Role::User,
+        content: prompt.to_string(),
+    }
 }
 
 #[cfg(test)]
@@ -160,6 +255,34 @@ mod tests {
         assert!(result.is_ok() || result.is_err());
     }
 
+    fn config_with_model(model: &str) -> Config {
+        let mut config = Config::default();
+        config.openai.default_model = model.to_string();
+        config
+    }
+
+    #[test]
+    fn test_init_or_get_initializes_once() {
+        let cell = OnceLock::new();
+
+        let first = init_or_get_in(&cell, config_with_model("first")).unwrap();
+        assert_eq!(first.config().openai.default_model, "first");
+
+        let second = init_or_get_in(&cell, config_with_model("second")).unwrap();
+        assert!(std::ptr::eq(first, second));
+        assert_eq!(second.config().openai.default_model, "first");
+    }
+
+    #[test]
+    fn test_strict_init_fails_on_second_call() {
+        let cell = OnceLock::new();
+
+        init_in(&cell, config_with_model("first")).unwrap();
+        let err = init_in(&cell, config_with_model("second")).unwrap_err();
+        assert!(err.to_string().contains("already initialized"));
+        assert_eq!(cell.get().unwrap().config().openai.default_model, "first");
+    }
+
     #[tokio::test]
     async fn test_ask_backward_compatibility() {
         // For this test, we'll test the ask function logic without global state
@@ -169,12 +292,12 @@ mod tests {
         let request = CompletionRequest {
             model: "test-model".to_string(),
             messages: vec![Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Hello".to_string(),
             }],
             temperature: Some(0.7),
             max_tokens: Some(1000),
-            stream: false,
+            ..Default::default()
         };
 
         let response = provider.complete(request).await.unwrap();
@@ -189,12 +312,12 @@ mod tests {
         let request = CompletionRequest {
             model: "gpt-4".to_string(),
             messages: vec![Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Test with specific model".to_string(),
             }],
             temperature: Some(0.7),
             max_tokens: Some(1000),
-            stream: false,
+            ..Default::default()
         };
 
         let response = provider.complete(request).await.unwrap();
@@ -208,19 +331,19 @@ mod tests {
 
         let messages = vec![
             Message {
-                role: "system".to_string(),
+                role: Role::System,
                 content: "You are a helpful assistant".to_string(),
             },
             Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Hello".to_string(),
             },
             Message {
-                role: "assistant".to_string(),
+                role: Role::Assistant,
                 content: "Hi there!".to_string(),
             },
             Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "How are you?".to_string(),
             },
         ];
@@ -230,7 +353,7 @@ mod tests {
             messages,
             temperature: Some(0.7),
             max_tokens: Some(1000),
-            stream: false,
+            ..Default::default()
         };
 
         let response = provider.complete(request).await.unwrap();
@@ -246,17 +369,17 @@ mod tests {
             model: container.config().openai.default_model.clone(),
             messages: vec![
                 Message {
-                    role: "system".to_string(),
+                    role: Role::System,
                     content: "You are a helpful assistant.".to_string(),
                 },
                 Message {
-                    role: "user".to_string(),
+                    role: Role::User,
                     content: "Hello".to_string(),
                 },
             ],
             temperature: Some(0.7),
             max_tokens: Some(1000),
-            stream: false,
+            ..Default::default()
         };
 
         let response = provider.complete(request).await.unwrap();
@@ -272,17 +395,17 @@ mod tests {
             model: container.config().openai.default_model.clone(),
             messages: vec![
                 Message {
-                    role: "system".to_string(),
+                    role: Role::System,
                     content: "You are an expert software developer with deep knowledge of programming languages, best practices, and system design.".to_string(),
                 },
                 Message {
-                    role: "user".to_string(),
+                    role: Role::User,
                     content: "Test expert persona".to_string(),
                 },
             ],
             temperature: Some(0.7),
             max_tokens: Some(1000),
-            stream: false,
+            ..Default::default()
         };
 
         let response = provider.complete(request).await.unwrap();
@@ -298,23 +421,72 @@ mod tests {
             model: container.config().openai.default_model.clone(),
             messages: vec![
                 Message {
-                    role: "system".to_string(),
+                    role: Role::System,
                     content: "You are a helpful assistant with the personality of a custom expert.".to_string(),
                 },
                 Message {
-                    role: "user".to_string(),
+                    role: Role::User,
                     content: "Test custom persona".to_string(),
                 },
             ],
             temperature: Some(0.7),
             max_tokens: Some(1000),
-            stream: false,
+            ..Default::default()
         };
 
         let response = provider.complete(request).await.unwrap();
         assert_eq!(response.content, "Test response from global");
     }
 
+    #[test]
+    fn test_find_persona_falls_back_to_the_default() {
+        let personas: HashMap<String, Persona> = personas::builtin_personas()
+            .into_iter()
+            .map(|persona| (persona.name.clone(), persona))
+            .collect();
+
+        for name in ["default", "expert", "rusty", "pythonic"] {
+            assert_eq!(find_persona(&personas, name).unwrap().name, name);
+        }
+        assert_eq!(find_persona(&personas, "pirate").unwrap().name, DEFAULT_PERSONA);
+    }
+
+    #[test]
+    fn test_messages_request_applies_persona_model_and_temperature() {
+        let configured = HashMap::from([
+            (
+                "creative".to_string(),
+                personas::Persona {
+                    name: "creative".to_string(),
+                    system_prompt: "You brainstorm".to_string(),
+                    model: Some("gpt-4o".to_string()),
+                    temperature: Some(1.2),
+                    ..Default::default()
+                },
+            ),
+            (
+                "plain".to_string(),
+                personas::Persona {
+                    name: "plain".to_string(),
+                    system_prompt: "You answer".to_string(),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let messages = persona_messages(&configured["creative"], "Ideas?");
+        let request = messages_request("gpt-4", messages, configured.get("creative"));
+        assert_eq!(request.model, "gpt-4o");
+        assert_eq!(request.temperature, Some(1.2));
+        assert_eq!(request.messages[0].content, "You brainstorm");
+        assert_eq!(request.messages[1].content, "Ideas?");
+
+        let messages = persona_messages(&configured["plain"], "Ideas?");
+        let request = messages_request("gpt-4", messages, configured.get("plain"));
+        assert_eq!(request.model, "gpt-4");
+        assert_eq!(request.temperature, Some(0.7));
+    }
+
     #[test]
     fn test_service_not_initialized() {
         // This test verifies the error when service is not initialized
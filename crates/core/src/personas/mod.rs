@@ -1,3 +1,4 @@
+use crate::provider::CompletionRequest;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -8,48 +9,261 @@ use std::path::PathBuf;
 mod tests;
 
 /// Represents a persona with a name and system prompt
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
 pub struct Persona {
     pub name: String,
     #[serde(rename = "system-prompt")]
     pub system_prompt: String,
+    /// Name of the persona this one builds on. Once loaded, the system prompt
+    /// is the base persona's (itself resolved) followed by this one's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Model to ask as this persona instead of the configured default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Sampling temperature for this persona, between 0.0 and 2.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
 }
 
-/// Loads personas from the configuration file
+impl Persona {
+    /// The system prompt with its `{{var}}` placeholders filled in from `vars`
+    pub fn render_system_prompt(&self, vars: &HashMap<String, String>) -> Result<String> {
+        crate::template::render_template(&self.system_prompt, vars)
+            .with_context(|| format!("Failed to render the system prompt of persona '{}'", self.name))
+    }
+
+    /// Use this persona's model and temperature in `request`, where set
+    pub fn apply_to(&self, request: &mut CompletionRequest) {
+        if let Some(model) = &self.model {
+            request.model = model.clone();
+        }
+        if let Some(temperature) = self.temperature {
+            request.temperature = Some(temperature);
+        }
+    }
+}
+
+/// A persona entry as written in personas.yml, before validation
+#[derive(Debug, Deserialize)]
+struct RawPersona {
+    name: Option<String>,
+    #[serde(rename = "system-prompt")]
+    system_prompt: Option<String>,
+    extends: Option<String>,
+    model: Option<String>,
+    temperature: Option<f32>,
+}
+
+/// Built-in persona used when none, or an unknown one, is asked for
+pub const DEFAULT_PERSONA: &str = "default";
+
+/// Personas that are always available, even without a personas.yml.
+/// `(name, system prompt)` pairs.
+const BUILTIN_PERSONAS: &[(&str, &str)] = &[
+    (DEFAULT_PERSONA, "You are a helpful coding assistant. Answer clearly and concisely."),
+    (
+        "expert",
+        "You are an expert software developer with deep knowledge of programming languages, best practices, and system design.",
+    ),
+    (
+        "rusty",
+        "You are an expert Rust developer. Write idiomatic, safe Rust and explain ownership and lifetimes when they matter.",
+    ),
+    (
+        "security-expert",
+        "You are a cybersecurity expert. Look for vulnerabilities, unsafe defaults and leaked secrets, and suggest concrete fixes.",
+    ),
+    (
+        "architect",
+        "You are a software architect. Focus on module boundaries, data flow and trade-offs between designs.",
+    ),
+    (
+        "frontend",
+        "You are a frontend developer. Favor accessible, responsive interfaces and explain browser behavior when relevant.",
+    ),
+    (
+        "pythonic",
+        "You are a Python expert who values clean, readable code and knows the ecosystem deeply.",
+    ),
+];
+
+/// The built-in personas, which personas.yml can override by name
+pub fn builtin_personas() -> Vec<Persona> {
+    BUILTIN_PERSONAS
+        .iter()
+        .map(|(name, system_prompt)| Persona {
+            name: name.to_string(),
+            system_prompt: system_prompt.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Loads the built-in personas together with those in the configuration file
 pub fn load_personas() -> Result<HashMap<String, Persona>> {
     let config_path = get_config_path()?.join("personas.yml");
-    if !config_path.exists() {
-        return Ok(HashMap::new());
-    }
+    load_personas_with_builtins(&config_path)
+}
 
-    let file_content = fs::read_to_string(config_path)?;
-    let personas: Vec<Persona> = serde_yml::from_str(&file_content)
-        .context("Failed to parse personas.yml")?;
+/// Loads personas from a specific file path (for testing), resolving
+/// `extends` chains
+pub fn load_personas_from_path(path: &PathBuf) -> Result<HashMap<String, Persona>> {
+    resolve_inheritance(read_personas_file(path)?)
+}
 
-    let persona_map = personas
+/// Loads the built-in personas merged with those in `path`. A file persona
+/// replaces the built-in of the same name, and may extend any built-in.
+pub fn load_personas_with_builtins(path: &PathBuf) -> Result<HashMap<String, Persona>> {
+    let from_file = read_personas_file(path)?;
+    let mut personas: Vec<Persona> = builtin_personas()
         .into_iter()
-        .map(|p| (p.name.clone(), p))
+        .filter(|builtin| !from_file.iter().any(|p| p.name == builtin.name))
         .collect();
+    personas.extend(from_file);
 
-    Ok(persona_map)
+    resolve_inheritance(personas)
 }
 
-/// Loads personas from a specific file path (for testing)
-pub fn load_personas_from_path(path: &PathBuf) -> Result<HashMap<String, Persona>> {
+fn read_personas_file(path: &PathBuf) -> Result<Vec<Persona>> {
     if !path.exists() {
-        return Ok(HashMap::new());
+        return Ok(Vec::new());
     }
 
-    let file_content = fs::read_to_string(path)?;
-    let personas: Vec<Persona> = serde_yml::from_str(&file_content)
-        .context("Failed to parse personas.yml")?;
+    parse_personas(&fs::read_to_string(path)?)
+}
 
-    let persona_map = personas
+/// Resolve `extends` so each persona's system prompt is the whole chain's,
+/// from the root base persona down to the persona itself, separated by blank
+/// lines. Unknown base personas and cycles are errors.
+pub fn resolve_inheritance(personas: Vec<Persona>) -> Result<HashMap<String, Persona>> {
+    let declared: HashMap<String, Persona> = personas
         .into_iter()
         .map(|p| (p.name.clone(), p))
         .collect();
 
-    Ok(persona_map)
+    let mut names: Vec<&String> = declared.keys().collect();
+    names.sort();
+
+    let mut resolved = HashMap::with_capacity(declared.len());
+    for name in names {
+        let mut chain = vec![name.as_str()];
+        let mut current = &declared[name];
+        while let Some(base) = current.extends.as_deref() {
+            if chain.contains(&base) {
+                chain.push(base);
+                anyhow::bail!("Persona inheritance cycle: {}", chain.join(" -> "));
+            }
+            current = declared.get(base).ok_or_else(|| {
+                anyhow::anyhow!("Persona '{}' extends unknown persona '{}'", chain[chain.len() - 1], base)
+            })?;
+            chain.push(base);
+        }
+
+        let persona = &declared[name];
+        let mut system_prompt = String::new();
+        for base in chain[1..].iter().rev() {
+            system_prompt.push_str(declared[*base].system_prompt.trim_end());
+            system_prompt.push_str("\n\n");
+        }
+        system_prompt.push_str(&persona.system_prompt);
+        resolved.insert(name.clone(), Persona { system_prompt, ..persona.clone() });
+    }
+
+    Ok(resolved)
+}
+
+/// Parses and validates the contents of a personas.yml file.
+///
+/// Errors name the offending entry (1-based) and, for YAML errors, the line
+/// and column. Duplicate names are rejected.
+pub fn parse_personas(content: &str) -> Result<Vec<Persona>> {
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let entries: Vec<RawPersona> = serde_yml::from_str(content).map_err(|e| match e.location() {
+        Some(location) => anyhow::anyhow!(
+            "Failed to parse personas.yml at line {}, column {}: {}",
+            location.line(),
+            location.column(),
+            e
+        ),
+        None => anyhow::anyhow!("Failed to parse personas.yml: {}", e),
+    })?;
+
+    let mut personas: Vec<Persona> = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.into_iter().enumerate() {
+        let number = index + 1;
+
+        let name = match entry.name {
+            Some(name) if !name.trim().is_empty() => name,
+            _ => anyhow::bail!("Persona entry {} is missing `name`", number),
+        };
+
+        let system_prompt = match entry.system_prompt {
+            Some(prompt) if !prompt.trim().is_empty() => prompt,
+            _ => anyhow::bail!("Persona entry {} ('{}') is missing `system-prompt`", number, name),
+        };
+
+        if let Some(temperature) = entry.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                anyhow::bail!(
+                    "Persona entry {} ('{}') has temperature {}; expected 0.0 to 2.0",
+                    number,
+                    name,
+                    temperature
+                );
+            }
+        }
+
+        if let Some(first) = personas.iter().position(|p| p.name == name) {
+            anyhow::bail!(
+                "Duplicate persona name '{}' in entries {} and {}",
+                name,
+                first + 1,
+                number
+            );
+        }
+
+        personas.push(Persona {
+            name,
+            system_prompt,
+            extends: entry.extends,
+            model: entry.model,
+            temperature: entry.temperature,
+        });
+    }
+
+    Ok(personas)
+}
+
+/// Appends a persona to the configuration file, creating it if needed
+pub fn add_persona(persona: Persona) -> Result<PathBuf> {
+    let path = get_config_path()?.join("personas.yml");
+    add_persona_to_path(&path, persona)?;
+    Ok(path)
+}
+
+/// Appends a persona to a specific file path, rejecting duplicate names
+pub fn add_persona_to_path(path: &PathBuf, persona: Persona) -> Result<()> {
+    let mut personas = if path.exists() {
+        parse_personas(&fs::read_to_string(path)?)?
+    } else {
+        Vec::new()
+    };
+
+    if personas.iter().any(|p| p.name == persona.name) {
+        anyhow::bail!("A persona named '{}' already exists in {}", persona.name, path.display());
+    }
+    personas.push(persona);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file_content = serde_yml::to_string(&personas).context("Failed to serialize personas")?;
+    fs::write(path, file_content)?;
+    Ok(())
 }
 
 /// Gets the configuration directory path
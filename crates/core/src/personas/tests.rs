@@ -15,6 +15,7 @@ fn test_persona_struct_creation() {
     let persona = Persona {
         name: "test".to_string(),
         system_prompt: "You are a test persona".to_string(),
+        ..Default::default()
     };
     
     assert_eq!(persona.name, "test");
@@ -26,6 +27,7 @@ fn test_persona_serialization() {
     let persona = Persona {
         name: "rusty".to_string(),
         system_prompt: "You are a Rust expert".to_string(),
+        ..Default::default()
     };
 
     let serialized = serde_yml::to_string(&persona).expect("Failed to serialize");
@@ -103,20 +105,220 @@ fn test_get_config_path_no_create() {
 }
 
 #[rstest]
-fn test_duplicate_persona_names_overwrites() {
+fn test_duplicate_persona_names_rejected() {
     let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
     let personas_path = temp_dir.path().join("personas.yml");
     let yaml_content = r#"
 - name: "duplicate"
   system-prompt: "First prompt"
+- name: "other"
+  system-prompt: "Other prompt"
 - name: "duplicate"
   system-prompt: "Second prompt"
 "#;
     fs::write(&personas_path, yaml_content).expect("Failed to write file");
 
-    let result = load_personas_from_path(&personas_path).expect("Should load personas");
-    assert_eq!(result.len(), 1);
-    
-    let persona = result.get("duplicate").expect("Should contain persona");
-    assert_eq!(persona.system_prompt, "Second prompt"); // Last one wins
-}
\ No newline at end of file
+    let err = load_personas_from_path(&personas_path).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate persona name 'duplicate' in entries 1 and 3");
+}
+
+#[test_case("- system-prompt: \"No name\"", "Persona entry 1 is missing `name`" ; "missing name")]
+#[test_case("- name: \"\"\n  system-prompt: \"Blank name\"", "Persona entry 1 is missing `name`" ; "blank name")]
+#[test_case(
+    "- name: ok\n  system-prompt: fine\n- name: broken\n  system_prompt: typo",
+    "Persona entry 2 ('broken') is missing `system-prompt`" ;
+    "missing system prompt"
+)]
+fn test_parse_personas_reports_entry(yaml_content: &str, expected: &str) {
+    let err = parse_personas(yaml_content).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+}
+
+#[rstest]
+fn test_parse_personas_reports_location() {
+    let yaml_content = "- name: ok\n  system-prompt: fine\n- name: [unclosed\n";
+    let err = parse_personas(yaml_content).unwrap_err().to_string();
+    assert!(err.starts_with("Failed to parse personas.yml at line"), "{}", err);
+}
+
+#[rstest]
+fn test_parse_personas_empty_content() {
+    assert!(parse_personas("").unwrap().is_empty());
+    assert!(parse_personas("  \n").unwrap().is_empty());
+}
+
+#[rstest]
+fn test_add_persona_creates_file(temp_config_dir: TempDir) {
+    let personas_path = temp_config_dir.path().join("nested").join("personas.yml");
+    let persona = Persona {
+        name: "reviewer".to_string(),
+        system_prompt: "You review code carefully.\nBe concise.".to_string(),
+        ..Default::default()
+    };
+
+    add_persona_to_path(&personas_path, persona.clone()).expect("Should add persona");
+
+    let personas = load_personas_from_path(&personas_path).expect("Should reload personas");
+    assert_eq!(personas.len(), 1);
+    assert_eq!(personas.get("reviewer"), Some(&persona));
+}
+
+#[rstest]
+fn test_add_persona_appends(temp_config_dir: TempDir) {
+    let personas_path = temp_config_dir.path().join("personas.yml");
+    fs::write(
+        &personas_path,
+        "- name: rusty\n  system-prompt: You are a Rust expert\n",
+    )
+    .expect("Failed to write file");
+
+    add_persona_to_path(
+        &personas_path,
+        Persona {
+            name: "pythonic".to_string(),
+            system_prompt: "You are a Python expert".to_string(),
+            ..Default::default()
+        },
+    )
+    .expect("Should add persona");
+
+    let personas = load_personas_from_path(&personas_path).expect("Should reload personas");
+    assert_eq!(personas.len(), 2);
+    assert!(personas.contains_key("rusty"));
+    assert!(personas.contains_key("pythonic"));
+}
+
+#[rstest]
+fn test_add_duplicate_persona(temp_config_dir: TempDir) {
+    let personas_path = temp_config_dir.path().join("personas.yml");
+    let persona = Persona {
+        name: "rusty".to_string(),
+        system_prompt: "You are a Rust expert".to_string(),
+        ..Default::default()
+    };
+
+    add_persona_to_path(&personas_path, persona.clone()).expect("Should add persona");
+    let err = add_persona_to_path(&personas_path, persona).unwrap_err();
+    assert!(err.to_string().contains("already exists"));
+
+    let personas = load_personas_from_path(&personas_path).expect("Should reload personas");
+    assert_eq!(personas.len(), 1);
+}
+
+
+#[rstest]
+fn test_render_system_prompt_substitutes_variables() {
+    let persona = Persona {
+        name: "reviewer".to_string(),
+        system_prompt: "You review {{ source }} carefully".to_string(),
+        ..Default::default()
+    };
+    let vars = HashMap::from([("source".to_string(), "src/lib.rs".to_string())]);
+
+    let prompt = persona.render_system_prompt(&vars).expect("Should render prompt");
+    assert_eq!(prompt, "You review src/lib.rs carefully");
+
+    let err = persona.render_system_prompt(&HashMap::new()).unwrap_err();
+    assert!(format!("{:#}", err).contains("Unknown template variable 'source'"));
+}
+
+#[rstest]
+fn test_extends_prepends_base_prompts(temp_config_dir: TempDir) {
+    let personas_path = temp_config_dir.path().join("personas.yml");
+    let yaml_content = r#"
+- name: "reviewer"
+  extends: "rusty"
+  system-prompt: "You review pull requests"
+- name: "base"
+  system-prompt: |
+    You are helpful
+- name: "rusty"
+  extends: "base"
+  system-prompt: "You write Rust"
+"#;
+    fs::write(&personas_path, yaml_content).expect("Failed to write file");
+
+    let personas = load_personas_from_path(&personas_path).expect("Should load personas");
+    assert_eq!(personas["base"].system_prompt, "You are helpful\n");
+    assert_eq!(personas["rusty"].system_prompt, "You are helpful\n\nYou write Rust");
+    assert_eq!(
+        personas["reviewer"].system_prompt,
+        "You are helpful\n\nYou write Rust\n\nYou review pull requests"
+    );
+    assert_eq!(personas["reviewer"].extends.as_deref(), Some("rusty"));
+}
+
+#[rstest]
+fn test_extends_unknown_persona_is_an_error() {
+    let personas = parse_personas("- name: child\n  extends: ghost\n  system-prompt: Hi").unwrap();
+    let err = resolve_inheritance(personas).unwrap_err();
+    assert_eq!(err.to_string(), "Persona 'child' extends unknown persona 'ghost'");
+}
+
+#[test_case("- name: loop\n  extends: loop\n  system-prompt: Hi", "loop -> loop" ; "self reference")]
+#[test_case(
+    "- name: a\n  extends: b\n  system-prompt: A\n- name: b\n  extends: a\n  system-prompt: B",
+    "a -> b -> a" ;
+    "two personas"
+)]
+fn test_extends_cycle_is_an_error(yaml_content: &str, cycle: &str) {
+    let personas = parse_personas(yaml_content).unwrap();
+    let err = resolve_inheritance(personas).unwrap_err();
+    assert_eq!(err.to_string(), format!("Persona inheritance cycle: {}", cycle));
+}
+
+#[rstest]
+fn test_add_persona_keeps_extends_unresolved(temp_config_dir: TempDir) {
+    let personas_path = temp_config_dir.path().join("personas.yml");
+    fs::write(&personas_path, "- name: base\n  system-prompt: Be kind\n").expect("Failed to write file");
+
+    let child = Persona {
+        name: "child".to_string(),
+        system_prompt: "Be brief".to_string(),
+        extends: Some("base".to_string()),
+        ..Default::default()
+    };
+    add_persona_to_path(&personas_path, child.clone()).expect("Should add persona");
+
+    assert_eq!(parse_personas(&fs::read_to_string(&personas_path).unwrap()).unwrap()[1], child);
+    let personas = load_personas_from_path(&personas_path).unwrap();
+    assert_eq!(personas["child"].system_prompt, "Be kind\n\nBe brief");
+}
+
+#[rstest]
+fn test_persona_model_and_temperature_are_loaded() {
+    let personas = parse_personas("- name: creative\n  system-prompt: Brainstorm\n  model: gpt-4o\n  temperature: 1.2").unwrap();
+    assert_eq!(personas[0].model.as_deref(), Some("gpt-4o"));
+    assert_eq!(personas[0].temperature, Some(1.2));
+
+    let err = parse_personas("- name: wild\n  system-prompt: Anything\n  temperature: 2.5").unwrap_err();
+    assert_eq!(err.to_string(), "Persona entry 1 ('wild') has temperature 2.5; expected 0.0 to 2.0");
+}
+
+#[rstest]
+fn test_builtin_personas_resolve_without_a_file(temp_config_dir: TempDir) {
+    let personas_path = temp_config_dir.path().join("personas.yml");
+    let personas = load_personas_with_builtins(&personas_path).expect("Should load built-in personas");
+
+    for name in ["default", "expert", "rusty", "security-expert", "architect", "frontend", "pythonic"] {
+        assert!(personas.contains_key(name), "missing built-in persona {}", name);
+    }
+    let command = crate::slash::parse_with_personas("/test --persona rusty", personas).unwrap();
+    assert_eq!(command.persona.unwrap().name, "rusty");
+}
+
+#[rstest]
+fn test_file_personas_override_and_extend_builtins(temp_config_dir: TempDir) {
+    let personas_path = temp_config_dir.path().join("personas.yml");
+    fs::write(
+        &personas_path,
+        "- name: rusty\n  system-prompt: You write no_std Rust\n- name: reviewer\n  system-prompt: Review this\n  extends: architect\n",
+    )
+    .expect("Failed to write file");
+
+    let personas = load_personas_with_builtins(&personas_path).unwrap();
+    assert_eq!(personas["rusty"].system_prompt, "You write no_std Rust");
+    assert!(personas["reviewer"].system_prompt.starts_with("You are a software architect."));
+    assert!(personas["reviewer"].system_prompt.ends_with("\n\nReview this"));
+    assert!(personas.contains_key("frontend"));
+}
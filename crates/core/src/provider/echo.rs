@@ -0,0 +1,179 @@
+use super::*;
+use crate::tokenize::{count_message_tokens, HeuristicTokenizer};
+use std::time::Duration;
+
+/// Provider that answers every request with its last user message, without
+/// any network. Responses can be delayed to simulate a real backend and
+/// report token usage estimated from the text, so `ServiceContainer`
+/// throughput and rate limiting can be benchmarked deterministically.
+/// Available with the `echo-provider` feature.
+#[derive(Debug, Clone)]
+pub struct EchoProvider {
+    name: String,
+    latency: Duration,
+    chunk_size: usize,
+}
+
+impl EchoProvider {
+    /// An "echo" provider that answers immediately, streaming 16 characters per chunk
+    pub fn new() -> Self {
+        Self {
+            name: "echo".to_string(),
+            latency: Duration::ZERO,
+            chunk_size: 16,
+        }
+    }
+
+    /// Set the name the provider reports
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Wait this long before answering. Streams wait once, before their first chunk.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Characters per streamed chunk; at least one
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    async fn simulate_latency(&self) {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+    }
+}
+
+impl Default for EchoProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Content of the last user message, or nothing when there is none
+fn echo(request: &CompletionRequest) -> String {
+    request
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == Role::User)
+        .map(|m| m.content.clone())
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl LLMProvider for EchoProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_streaming: true,
+            supports_function_calling: false,
+            supports_vision: false,
+            models: Vec::new(),
+        }
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        self.simulate_latency().await;
+
+        let content = echo(&request);
+        let prompt_tokens = count_message_tokens(&HeuristicTokenizer, &request.messages) as u32;
+        let completion_tokens = HeuristicTokenizer.count_tokens(&content) as u32;
+        Ok(CompletionResponse {
+            content,
+            model: request.model,
+            usage: Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+            created: None,
+            finish_reason: Some("stop".to_string()),
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<BoxStream<'static, Result<StreamChunk>>> {
+        self.simulate_latency().await;
+
+        let characters: Vec<char> = echo(&request).chars().collect();
+        let mut chunks: Vec<StreamChunk> = characters
+            .chunks(self.chunk_size)
+            .map(|chunk| StreamChunk {
+                delta: chunk.iter().collect(),
+                finish_reason: None,
+            })
+            .collect();
+        chunks.push(StreamChunk {
+            delta: String::new(),
+            finish_reason: Some("stop".to_string()),
+        });
+
+        Ok(Box::pin(tokio_stream::iter(chunks.into_iter().map(Ok))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn request(messages: &[(Role, &str)]) -> CompletionRequest {
+        CompletionRequest::builder()
+            .model("bench")
+            .messages(
+                messages
+                    .iter()
+                    .map(|(role, content)| Message {
+                        role: *role,
+                        content: content.to_string(),
+                    })
+                    .collect(),
+            )
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_complete_echoes_last_user_message() {
+        let request = request(&[(Role::System, "be brief"), (Role::User, "first"), (Role::Assistant, "ok"), (Role::User, "again")]);
+        let response = EchoProvider::new().complete(request).await.unwrap();
+
+        assert_eq!(response.content, "again");
+        assert_eq!(response.model, "bench");
+        assert_eq!(response.usage.completion_tokens, 2);
+        assert_eq!(
+            response.usage.total_tokens,
+            response.usage.prompt_tokens + response.usage.completion_tokens
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_splits_content_into_chunks() {
+        let provider = EchoProvider::new().with_chunk_size(4);
+        let stream = provider.stream(request(&[(Role::User, "héllo world")])).await.unwrap();
+        let chunks: Vec<StreamChunk> = stream.map(|chunk| chunk.unwrap()).collect().await;
+
+        let deltas: Vec<&str> = chunks.iter().map(|c| c.delta.as_str()).collect();
+        assert_eq!(deltas, vec!["héll", "o wo", "rld", ""]);
+        assert_eq!(chunks.last().unwrap().finish_reason.as_deref(), Some("stop"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_is_simulated() {
+        let provider = EchoProvider::new().with_latency(Duration::from_millis(500));
+        let started = tokio::time::Instant::now();
+
+        provider.complete(request(&[(Role::User, "ping")])).await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_millis(500));
+    }
+}
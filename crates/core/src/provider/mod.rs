@@ -2,25 +2,213 @@ use crate::error::{Error, Result};
 use async_trait::async_trait;
 use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
+use crate::tokenize::Tokenizer;
 
 #[cfg(test)]
 pub mod tests;
 
+/// Author of a message in a conversation, serialized as the lowercase
+/// strings chat APIs expect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<&str> for Role {
+    type Error = Error;
+
+    fn try_from(role: &str) -> Result<Self> {
+        match role {
+            "system" => Ok(Role::System),
+            "user" => Ok(Role::User),
+            "assistant" => Ok(Role::Assistant),
+            "tool" => Ok(Role::Tool),
+            _ => Err(Error::InvalidRequest(format!(
+                "unknown message role '{}', expected one of system, user, assistant, tool",
+                role
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = Error;
+
+    fn try_from(role: String) -> Result<Self> {
+        Role::try_from(role.as_str())
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = Error;
+
+    fn from_str(role: &str) -> Result<Self> {
+        Role::try_from(role)
+    }
+}
+
 /// Message in a conversation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
-    pub role: String,
+    pub role: Role,
     pub content: String,
 }
 
-/// Request for LLM completion
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Request for LLM completion. `Default` leaves every option unset, so
+/// literals can name just the fields they need.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionRequest {
     pub model: String,
     pub messages: Vec<Message>,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
     pub stream: bool,
+    /// Sequences that end generation when the model produces them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// Ask for deterministic sampling. Best effort: not every provider or
+    /// model honors it, and those that do may still vary between versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Nucleus sampling: only tokens within this much probability mass are
+    /// considered, from `0.0` to `1.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Constrain the shape of the response; plain text when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// Shape a completion's content must take
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    /// Any valid JSON object
+    JsonObject,
+    /// JSON matching this JSON Schema. Its `title`, when present, names the
+    /// schema for providers that require a name.
+    JsonSchema(serde_json::Value),
+}
+
+impl CompletionRequest {
+    /// Start building a request
+    pub fn builder() -> CompletionRequestBuilder {
+        CompletionRequestBuilder::default()
+    }
+
+    /// Reject sampling settings no provider accepts: a temperature outside
+    /// `0.0..=2.0`, a `top_p` outside `0.0..=1.0` or a `max_tokens` of zero.
+    /// Unset values are fine.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(Error::InvalidRequest(format!(
+                    "temperature must be between 0.0 and 2.0, got {}",
+                    temperature
+                )));
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(Error::InvalidRequest(format!(
+                    "top_p must be between 0.0 and 1.0, got {}",
+                    top_p
+                )));
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err(Error::InvalidRequest("max_tokens must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Fluent builder for [`CompletionRequest`]
+#[derive(Debug, Clone, Default)]
+pub struct CompletionRequestBuilder {
+    request: CompletionRequest,
+}
+
+impl CompletionRequestBuilder {
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.request.model = model.into();
+        self
+    }
+
+    /// Replace the messages sent with the request
+    pub fn messages(mut self, messages: Vec<Message>) -> Self {
+        self.request.messages = messages;
+        self
+    }
+
+    /// Append a single message
+    pub fn message(mut self, message: Message) -> Self {
+        self.request.messages.push(message);
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.request.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.request.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.request.stream = stream;
+        self
+    }
+
+    /// Add a sequence that ends generation; may be called several times
+    pub fn stop(mut self, sequence: impl Into<String>) -> Self {
+        self.request.stop.get_or_insert_with(Vec::new).push(sequence.into());
+        self
+    }
+
+    /// Ask for deterministic sampling; not every provider honors it
+    pub fn seed(mut self, seed: i64) -> Self {
+        self.request.seed = Some(seed);
+        self
+    }
+
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.request.top_p = Some(top_p);
+        self
+    }
+
+    pub fn response_format(mut self, format: ResponseFormat) -> Self {
+        self.request.response_format = Some(format);
+        self
+    }
+
+    pub fn build(self) -> CompletionRequest {
+        self.request
+    }
 }
 
 /// Response from LLM completion
@@ -29,16 +217,30 @@ pub struct CompletionResponse {
     pub content: String,
     pub model: String,
     pub usage: Usage,
+    /// Unix timestamp (seconds) at which the provider created the response
+    #[serde(default)]
+    pub created: Option<i64>,
+    /// Why the model stopped generating, e.g. "stop" or "length"
+    #[serde(default)]
+    pub finish_reason: Option<String>,
 }
 
 /// Token usage information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
 /// Streaming chunk from LLM
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamChunk {
@@ -46,12 +248,41 @@ pub struct StreamChunk {
     pub finish_reason: Option<String>,
 }
 
+/// Features a provider supports, so callers can check before sending a request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderCapabilities {
+    pub supports_streaming: bool,
+    pub supports_function_calling: bool,
+    pub supports_vision: bool,
+    /// Models the provider is known to serve; empty when unknown
+    pub models: Vec<String>,
+}
+
+/// A model a provider serves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    /// Organization that owns the model, when the provider reports it
+    pub owned_by: Option<String>,
+    /// Maximum prompt plus completion tokens, when known
+    pub context_window: Option<u32>,
+}
+
 /// Trait for LLM providers
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     /// Get the name of the provider
     fn name(&self) -> &str;
 
+    /// Describe what the provider supports. Every provider implements
+    /// `stream`, so streaming is assumed unless overridden.
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_streaming: true,
+            ..Default::default()
+        }
+    }
+
     /// Complete a request and return the full response
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse>;
 
@@ -60,8 +291,81 @@ pub trait LLMProvider: Send + Sync {
         &self,
         request: CompletionRequest,
     ) -> Result<BoxStream<'static, Result<StreamChunk>>>;
+
+    /// Models the provider offers. Providers that can't enumerate their
+    /// models return an empty list.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Embedding model used when callers don't pick one
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Trait for providers that turn text into embedding vectors
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embed every input with `model`, returning one vector per input in the
+    /// same order
+    async fn embed(&self, input: Vec<String>, model: &str) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Split off the system prompt for providers that take it separately from
+/// the conversation. Leading system messages are joined with a blank line;
+/// system messages later in the conversation are left where they are, since
+/// moving them would change what the model saw when.
+pub fn split_system(messages: &[Message]) -> (Option<String>, Vec<Message>) {
+    let leading = messages
+        .iter()
+        .take_while(|message| message.role == Role::System)
+        .count();
+    let system = (leading > 0).then(|| {
+        messages[..leading]
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    });
+    (system, messages[leading..].to_vec())
+}
+
+/// Drain a response stream into a single response. Deltas are concatenated
+/// and the last finish reason wins. Streams carry neither the model name nor
+/// token usage, so `model` is left empty and the completion tokens are
+/// estimated from the content.
+pub async fn collect_stream(
+    mut stream: BoxStream<'static, Result<StreamChunk>>,
+) -> Result<CompletionResponse> {
+    use futures::StreamExt;
+
+    let mut content = String::new();
+    let mut finish_reason = None;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        content.push_str(&chunk.delta);
+        if chunk.finish_reason.is_some() {
+            finish_reason = chunk.finish_reason;
+        }
+    }
+
+    let completion_tokens = crate::tokenize::HeuristicTokenizer.count_tokens(&content) as u32;
+    Ok(CompletionResponse {
+        content,
+        model: String::new(),
+        usage: Usage {
+            prompt_tokens: 0,
+            completion_tokens,
+            total_tokens: completion_tokens,
+        },
+        created: None,
+        finish_reason,
+    })
 }
 
+#[cfg(feature = "echo-provider")]
+pub mod echo;
 pub mod openai;
 
+#[cfg(feature = "echo-provider")]
+pub use echo::EchoProvider;
 pub use openai::OpenAIProvider;
\ No newline at end of file
@@ -1,49 +1,139 @@
 use super::*;
-use crate::config::OpenAIConfig;
+use crate::config::{OpenAIConfig, Secret};
 use async_openai::{
+    error::OpenAIError,
     types::{
         ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
         ChatCompletionRequestUserMessageArgs, ChatCompletionRequestAssistantMessageArgs,
-        CreateChatCompletionRequestArgs, CreateChatCompletionStreamResponse,
+        ChatCompletionResponseStream, CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+        CreateChatCompletionResponse, CreateChatCompletionStreamResponse, CreateEmbeddingRequestArgs,
+        CreateEmbeddingResponse, FinishReason, Model, ResponseFormat as OpenAIResponseFormat, ResponseFormatJsonSchema,
+        Stop,
     },
     Client,
 };
 use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{field, instrument, warn, Span};
+
+/// Wait before the first stream reconnect; doubles with every further attempt
+const STREAM_RECONNECT_BASE_DELAY: Duration = Duration::from_millis(250);
+const STREAM_RECONNECT_MAX_DELAY: Duration = Duration::from_secs(8);
 
 /// OpenAI provider implementation
 pub struct OpenAIProvider {
+    name: String,
     client: Client<async_openai::config::OpenAIConfig>,
     config: OpenAIConfig,
+    models: Vec<String>,
 }
 
 impl OpenAIProvider {
     /// Create a new OpenAI provider
-    pub fn new(api_key: String, config: OpenAIConfig) -> Self {
+    pub fn new(api_key: Secret<String>, config: OpenAIConfig) -> Self {
         let openai_config = async_openai::config::OpenAIConfig::new()
-            .with_api_key(api_key)
+            .with_api_key(api_key.expose())
             .with_api_base(config.api_base.clone());
 
         Self {
+            name: "openai".to_string(),
             client: Client::with_config(openai_config),
+            models: vec![config.default_model.clone()],
             config,
         }
     }
 
+    /// Set the models reported by `capabilities()`; defaults to the configured default model
+    pub fn with_models(mut self, models: Vec<String>) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Set the name the provider reports, for providers registered under a
+    /// custom name (e.g. a local OpenAI-compatible server)
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Send extra HTTP headers with every request
+    pub fn with_headers(mut self, headers: &BTreeMap<String, String>) -> Result<Self> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::Config(format!("Invalid header name '{}': {}", name, e)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| Error::Config(format!("Invalid value for header '{}': {}", name, e)))?;
+            header_map.insert(name, value);
+        }
+
+        let http_client = reqwest::Client::builder()
+            .default_headers(header_map)
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to build HTTP client: {}", e)))?;
+        self.client = self.client.with_http_client(http_client);
+        Ok(self)
+    }
+
+    /// The API request for `request`; unset options are left out of the payload
+    fn build_request(&self, request: CompletionRequest, stream: bool) -> Result<CreateChatCompletionRequest> {
+        let mut builder = CreateChatCompletionRequestArgs::default();
+        builder
+            .model(&request.model)
+            .messages(self.convert_messages(request.messages));
+
+        if stream {
+            builder.stream(true);
+        }
+
+        if let Some(temp) = request.temperature {
+            builder.temperature(temp);
+        }
+
+        if let Some(max_tokens) = request.max_tokens {
+            builder.max_tokens(max_tokens as u16);
+        }
+
+        if let Some(stop) = request.stop {
+            builder.stop(Stop::StringArray(stop));
+        }
+
+        if let Some(seed) = request.seed {
+            builder.seed(seed);
+        }
+
+        if let Some(top_p) = request.top_p {
+            builder.top_p(top_p);
+        }
+
+        if let Some(format) = request.response_format {
+            builder.response_format(convert_response_format(format));
+        }
+
+        builder
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to build request: {}", e)))
+    }
+
     fn convert_messages(&self, messages: Vec<Message>) -> Vec<ChatCompletionRequestMessage> {
         messages
             .into_iter()
-            .map(|msg| match msg.role.as_str() {
-                "system" => ChatCompletionRequestSystemMessageArgs::default()
+            .map(|msg| match msg.role {
+                Role::System => ChatCompletionRequestSystemMessageArgs::default()
                     .content(msg.content)
                     .build()
                     .unwrap()
                     .into(),
-                "assistant" => ChatCompletionRequestAssistantMessageArgs::default()
+                Role::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
                     .content(msg.content)
                     .build()
                     .unwrap()
                     .into(),
-                _ => ChatCompletionRequestUserMessageArgs::default()
+                // Tool results need the id of the call they answer, which
+                // messages don't carry, so they are sent as user messages
+                Role::User | Role::Tool => ChatCompletionRequestUserMessageArgs::default()
                     .content(msg.content)
                     .build()
                     .unwrap()
@@ -56,94 +146,333 @@ impl OpenAIProvider {
 #[async_trait]
 impl LLMProvider for OpenAIProvider {
     fn name(&self) -> &str {
-        "openai"
+        &self.name
     }
 
-    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
-        let mut builder = CreateChatCompletionRequestArgs::default();
-        builder
-            .model(&request.model)
-            .messages(self.convert_messages(request.messages));
-
-        if let Some(temp) = request.temperature {
-            builder.temperature(temp);
-        }
-
-        if let Some(max_tokens) = request.max_tokens {
-            builder.max_tokens(max_tokens as u16);
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_streaming: true,
+            supports_function_calling: true,
+            supports_vision: false,
+            models: self.models.clone(),
         }
+    }
 
-        let openai_request = builder
-            .build()
-            .map_err(|e| Error::Provider(format!("Failed to build request: {}", e)))?;
+    #[instrument(
+        name = "provider.complete",
+        skip_all,
+        fields(
+            provider = %self.name,
+            model = %request.model,
+            prompt_tokens = field::Empty,
+            completion_tokens = field::Empty,
+        )
+    )]
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let model = request.model.clone();
+        let openai_request = self.build_request(request, false)?;
 
         let response = self
             .client
             .chat()
             .create(openai_request)
             .await
-            .map_err(|e| Error::Provider(format!("OpenAI API error: {}", e)))?;
-
-        let content = response
-            .choices
-            .first()
-            .and_then(|c| c.message.content.as_ref())
-            .ok_or_else(|| Error::Provider("No content in response".into()))?
-            .clone();
-
-        Ok(CompletionResponse {
-            content,
-            model: response.model,
-            usage: Usage {
-                prompt_tokens: response.usage.as_ref().map(|u| u.prompt_tokens).unwrap_or(0) as u32,
-                completion_tokens: response
-                    .usage
-                    .as_ref()
-                    .map(|u| u.completion_tokens)
-                    .unwrap_or(0) as u32,
-                total_tokens: response.usage.as_ref().map(|u| u.total_tokens).unwrap_or(0) as u32,
-            },
-        })
+            .map_err(|e| map_api_error(e, &model))?;
+
+        let response = convert_response(response)?;
+        Span::current()
+            .record("prompt_tokens", response.usage.prompt_tokens)
+            .record("completion_tokens", response.usage.completion_tokens);
+        Ok(response)
     }
 
+    #[instrument(
+        name = "provider.stream",
+        skip_all,
+        fields(provider = %self.name, model = %request.model)
+    )]
     async fn stream(
         &self,
         request: CompletionRequest,
     ) -> Result<BoxStream<'static, Result<StreamChunk>>> {
-        let mut builder = CreateChatCompletionRequestArgs::default();
-        builder
-            .model(&request.model)
-            .messages(self.convert_messages(request.messages))
-            .stream(true);
+        let model = request.model.clone();
+        let openai_request = self.build_request(request, true)?;
 
-        if let Some(temp) = request.temperature {
-            builder.temperature(temp);
+        let stream = self
+            .client
+            .chat()
+            .create_stream(openai_request.clone())
+            .await
+            .map_err(|e| map_api_error(e, &model))?;
+
+        if !self.config.reconnect_streams {
+            return Ok(Box::pin(stream.map(map_stream_item)));
         }
 
-        if let Some(max_tokens) = request.max_tokens {
-            builder.max_tokens(max_tokens as u16);
+        let reconnect = StreamReconnect {
+            client: self.client.clone(),
+            request: openai_request,
+            max_retries: self.config.max_retries,
+            attempts: 0,
+            content: String::new(),
+            finished: false,
+        };
+        Ok(reconnect.into_stream(stream))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let response = self
+            .client
+            .models()
+            .list()
+            .await
+            .map_err(|e| map_api_error(e, &self.config.default_model))?;
+
+        let mut models: Vec<ModelInfo> = response.data.into_iter().map(convert_model).collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIProvider {
+    #[instrument(
+        name = "provider.embed",
+        skip_all,
+        fields(provider = %self.name, model = %model, inputs = input.len())
+    )]
+    async fn embed(&self, input: Vec<String>, model: &str) -> Result<Vec<Vec<f32>>> {
+        if input.is_empty() {
+            return Ok(Vec::new());
         }
 
-        let openai_request = builder
+        let expected = input.len();
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(model)
+            .input(input)
             .build()
             .map_err(|e| Error::Provider(format!("Failed to build request: {}", e)))?;
 
-        let stream = self
+        let response = self
             .client
-            .chat()
-            .create_stream(openai_request)
+            .embeddings()
+            .create(request)
             .await
-            .map_err(|e| Error::Provider(format!("OpenAI API error: {}", e)))?;
+            .map_err(|e| map_api_error(e, model))?;
+        convert_embeddings(response, expected)
+    }
+}
 
-        let mapped_stream = stream.map(|result| match result {
-            Ok(response) => {
-                let chunk = extract_chunk(response);
-                Ok(chunk)
+/// Vectors in the order of the inputs; the API tags each with its input's index
+fn convert_embeddings(response: CreateEmbeddingResponse, expected: usize) -> Result<Vec<Vec<f32>>> {
+    if response.data.len() != expected {
+        return Err(Error::Provider(format!(
+            "Expected {} embeddings, got {}",
+            expected,
+            response.data.len()
+        )));
+    }
+
+    let mut data = response.data;
+    data.sort_by_key(|embedding| embedding.index);
+    Ok(data.into_iter().map(|embedding| embedding.embedding).collect())
+}
+
+fn convert_response_format(format: ResponseFormat) -> OpenAIResponseFormat {
+    match format {
+        ResponseFormat::Text => OpenAIResponseFormat::Text,
+        ResponseFormat::JsonObject => OpenAIResponseFormat::JsonObject,
+        ResponseFormat::JsonSchema(schema) => {
+            // OpenAI requires a name; fall back to a generic one
+            let name = schema
+                .get("title")
+                .and_then(|title| title.as_str())
+                .unwrap_or("response")
+                .to_string();
+            OpenAIResponseFormat::JsonSchema {
+                json_schema: ResponseFormatJsonSchema {
+                    description: None,
+                    name,
+                    schema: Some(schema),
+                    strict: None,
+                },
             }
-            Err(e) => Err(Error::Provider(format!("Stream error: {}", e))),
-        });
+        }
+    }
+}
+
+fn convert_response(response: CreateChatCompletionResponse) -> Result<CompletionResponse> {
+    let choice = response
+        .choices
+        .first()
+        .ok_or_else(|| Error::Provider("empty response: no choices returned".into()))?;
+    let finish_reason = choice.finish_reason.as_ref().map(finish_reason_name);
+
+    // Content filters and some compatible servers answer with nothing at all
+    let content = match choice.message.content.as_deref() {
+        Some(content) if !content.is_empty() => content.to_string(),
+        _ => {
+            let reason = match &finish_reason {
+                Some(reason) => format!("finish reason {}", reason),
+                None => "no content returned".to_string(),
+            };
+            return Err(Error::Provider(format!("empty response: {}", reason)));
+        }
+    };
+
+    Ok(CompletionResponse {
+        content,
+        model: response.model,
+        usage: Usage {
+            prompt_tokens: response.usage.as_ref().map(|u| u.prompt_tokens).unwrap_or(0),
+            completion_tokens: response
+                .usage
+                .as_ref()
+                .map(|u| u.completion_tokens)
+                .unwrap_or(0),
+            total_tokens: response.usage.as_ref().map(|u| u.total_tokens).unwrap_or(0),
+        },
+        // Some OpenAI-compatible servers send 0 instead of a real timestamp
+        created: (response.created != 0).then_some(response.created as i64),
+        finish_reason,
+    })
+}
+
+fn convert_model(model: Model) -> ModelInfo {
+    ModelInfo {
+        context_window: known_context_window(&model.id),
+        owned_by: Some(model.owned_by).filter(|owner| !owner.is_empty()),
+        id: model.id,
+    }
+}
+
+/// Context windows of well-known OpenAI models; `/models` doesn't report them
+fn known_context_window(id: &str) -> Option<u32> {
+    const WINDOWS: &[(&str, u32)] = &[
+        ("gpt-4.1", 1_047_576),
+        ("gpt-4o", 128_000),
+        ("gpt-4-turbo", 128_000),
+        ("gpt-4-32k", 32_768),
+        ("gpt-4", 8_192),
+        ("gpt-3.5-turbo", 16_385),
+        ("o1", 200_000),
+        ("o3", 200_000),
+        ("o4-mini", 200_000),
+    ];
+
+    // Longest prefix first, so "gpt-4o-mini" isn't matched as "gpt-4"
+    WINDOWS
+        .iter()
+        .filter(|(prefix, _)| id.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, window)| *window)
+}
+
+/// Map an OpenAI client error to our error type, singling out unknown models,
+/// rate limiting and requests the API refuses outright
+fn map_api_error(err: OpenAIError, model: &str) -> Error {
+    match err {
+        OpenAIError::ApiError(api_error) if api_error.code.as_deref() == Some("model_not_found") => {
+            Error::ModelNotFound(model.to_string())
+        }
+        OpenAIError::ApiError(api_error) if api_error.code.as_deref() == Some("rate_limit_exceeded") => {
+            Error::RateLimited(api_error.message)
+        }
+        OpenAIError::ApiError(api_error)
+            if matches!(
+                api_error.r#type.as_deref(),
+                Some("invalid_request_error" | "authentication_error")
+            ) || api_error.code.as_deref() == Some("invalid_api_key") =>
+        {
+            Error::InvalidRequest(api_error.message)
+        }
+        e => Error::Provider(format!("OpenAI API error: {}", e)),
+    }
+}
+
+fn map_stream_item(result: Result<CreateChatCompletionStreamResponse, OpenAIError>) -> Result<StreamChunk> {
+    match result {
+        Ok(response) => Ok(extract_chunk(response)),
+        Err(e) => Err(Error::Provider(format!("Stream error: {}", e))),
+    }
+}
+
+/// Keeps a stream going across dropped connections by re-issuing the
+/// request with the content received so far as a partial assistant reply,
+/// which the model then continues
+struct StreamReconnect {
+    client: Client<async_openai::config::OpenAIConfig>,
+    request: CreateChatCompletionRequest,
+    max_retries: u32,
+    attempts: u32,
+    /// Content received so far, over every connection
+    content: String,
+    /// A chunk carried a finish reason, so there is nothing left to resume
+    finished: bool,
+}
+
+impl StreamReconnect {
+    fn into_stream(self, first: ChatCompletionResponseStream) -> BoxStream<'static, Result<StreamChunk>> {
+        Box::pin(futures::stream::unfold(Some((self, first)), |state| async move {
+            let (mut reconnect, mut current) = state?;
+            loop {
+                match current.next().await {
+                    Some(Ok(response)) => {
+                        let chunk = extract_chunk(response);
+                        reconnect.content.push_str(&chunk.delta);
+                        reconnect.finished |= chunk.finish_reason.is_some();
+                        return Some((Ok(chunk), Some((reconnect, current))));
+                    }
+                    // Only a dropped connection is worth resuming; anything
+                    // else would fail the same way again
+                    Some(Err(OpenAIError::StreamError(e)))
+                        if !reconnect.finished && reconnect.attempts < reconnect.max_retries =>
+                    {
+                        reconnect.attempts += 1;
+                        let delay = reconnect_delay(reconnect.attempts);
+                        warn!(
+                            "Stream dropped ({}), reconnecting in {:?} (attempt {} of {})",
+                            e, delay, reconnect.attempts, reconnect.max_retries
+                        );
+                        tokio::time::sleep(delay).await;
+                        current = reconnect.resume().await;
+                    }
+                    Some(Err(e)) => return Some((map_stream_item(Err(e)), None)),
+                    None => return None,
+                }
+            }
+        }))
+    }
 
-        Ok(Box::pin(mapped_stream))
+    async fn resume(&self) -> ChatCompletionResponseStream {
+        let mut request = self.request.clone();
+        if !self.content.is_empty() {
+            let partial = ChatCompletionRequestAssistantMessageArgs::default()
+                .content(self.content.clone())
+                .build()
+                .unwrap();
+            request.messages.push(partial.into());
+        }
+
+        match self.client.chat().create_stream(request).await {
+            Ok(stream) => stream,
+            Err(e) => Box::pin(futures::stream::once(async move { Err(e) })),
+        }
+    }
+}
+
+/// Delay before reconnect attempt `attempt`, counting from 1
+fn reconnect_delay(attempt: u32) -> Duration {
+    STREAM_RECONNECT_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(STREAM_RECONNECT_MAX_DELAY)
+}
+
+/// `reason` as it appears on the wire, e.g. "stop" or "content_filter"
+fn finish_reason_name(reason: &FinishReason) -> String {
+    match serde_json::to_value(reason) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => format!("{:?}", reason),
     }
 }
 
@@ -152,14 +481,14 @@ fn extract_chunk(response: CreateChatCompletionStreamResponse) -> StreamChunk {
         .choices
         .first()
         .and_then(|c| c.delta.content.as_ref())
-        .map(|s| s.clone())
+        .cloned()
         .unwrap_or_default();
 
     let finish_reason = response
         .choices
         .first()
         .and_then(|c| c.finish_reason.as_ref())
-        .map(|r| format!("{:?}", r));
+        .map(finish_reason_name);
 
     StreamChunk {
         delta,
@@ -170,6 +499,7 @@ fn extract_chunk(response: CreateChatCompletionStreamResponse) -> StreamChunk {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_openai_provider_creation() {
@@ -178,9 +508,11 @@ mod tests {
             default_model: "gpt-4".to_string(),
             max_retries: 3,
             timeout_seconds: 30,
+            api_key: None,
+            reconnect_streams: false,
         };
 
-        let provider = OpenAIProvider::new("test-key".to_string(), config.clone());
+        let provider = OpenAIProvider::new("test-key".into(), config.clone());
         assert_eq!(provider.name(), "openai");
         assert_eq!(provider.config.default_model, "gpt-4");
     }
@@ -192,21 +524,23 @@ mod tests {
             default_model: "gpt-4".to_string(),
             max_retries: 3,
             timeout_seconds: 30,
+            api_key: None,
+            reconnect_streams: false,
         };
 
-        let provider = OpenAIProvider::new("test-key".to_string(), config);
+        let provider = OpenAIProvider::new("test-key".into(), config);
 
         let messages = vec![
             Message {
-                role: "system".to_string(),
+                role: Role::System,
                 content: "You are a helpful assistant".to_string(),
             },
             Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Hello".to_string(),
             },
             Message {
-                role: "assistant".to_string(),
+                role: Role::Assistant,
                 content: "Hi there!".to_string(),
             },
         ];
@@ -215,6 +549,414 @@ mod tests {
         assert_eq!(converted.len(), 3);
     }
 
+    #[test]
+    fn test_capabilities() {
+        let provider = OpenAIProvider::new("test-key".into(), OpenAIConfig::default());
+        let capabilities = provider.capabilities();
+        assert!(capabilities.supports_streaming);
+        assert_eq!(capabilities.models, vec!["gpt-4".to_string()]);
+
+        let provider = provider.with_models(vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()]);
+        assert_eq!(provider.capabilities().models.len(), 2);
+    }
+
+    #[test]
+    fn test_with_headers_rejects_invalid_name() {
+        let provider = OpenAIProvider::new("test-key".into(), OpenAIConfig::default());
+        let mut headers = BTreeMap::new();
+        headers.insert("bad header".to_string(), "value".to_string());
+
+        assert!(matches!(provider.with_headers(&headers), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_map_api_error_model_not_found() {
+        let err = OpenAIError::ApiError(async_openai::error::ApiError {
+            message: "The model `gpt-5` does not exist".to_string(),
+            r#type: Some("invalid_request_error".to_string()),
+            param: None,
+            code: Some("model_not_found".to_string()),
+        });
+        assert!(matches!(map_api_error(err, "gpt-5"), Error::ModelNotFound(model) if model == "gpt-5"));
+
+        let err = OpenAIError::ApiError(async_openai::error::ApiError {
+            message: "Rate limit reached".to_string(),
+            r#type: None,
+            param: None,
+            code: Some("rate_limit_exceeded".to_string()),
+        });
+        assert!(matches!(map_api_error(err, "gpt-4"), Error::RateLimited(_)));
+
+        let err = OpenAIError::ApiError(async_openai::error::ApiError {
+            message: "The server had an error".to_string(),
+            r#type: Some("server_error".to_string()),
+            param: None,
+            code: None,
+        });
+        assert!(matches!(map_api_error(err, "gpt-4"), Error::Provider(_)));
+
+        let err = OpenAIError::ApiError(async_openai::error::ApiError {
+            message: "Incorrect API key provided".to_string(),
+            r#type: Some("invalid_request_error".to_string()),
+            param: None,
+            code: Some("invalid_api_key".to_string()),
+        });
+        assert!(matches!(map_api_error(err, "gpt-4"), Error::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn test_convert_model_adds_known_context_window() {
+        let model = |id: &str| Model {
+            id: id.to_string(),
+            object: "model".to_string(),
+            created: 0,
+            owned_by: "openai".to_string(),
+        };
+
+        let info = convert_model(model("gpt-4o-mini"));
+        assert_eq!(info.id, "gpt-4o-mini");
+        assert_eq!(info.owned_by.as_deref(), Some("openai"));
+        assert_eq!(info.context_window, Some(128_000));
+
+        assert_eq!(convert_model(model("gpt-4-0613")).context_window, Some(8_192));
+        assert_eq!(convert_model(model("text-embedding-3-small")).context_window, None);
+    }
+
+    fn canned_response(created: u32) -> CreateChatCompletionResponse {
+        serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": created,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hello there" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_convert_response_parses_created() {
+        let response = convert_response(canned_response(1_700_000_000)).unwrap();
+        assert_eq!(response.content, "Hello there");
+        assert_eq!(response.usage.total_tokens, 7);
+        assert_eq!(response.created, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_convert_response_without_created() {
+        let response = convert_response(canned_response(0)).unwrap();
+        assert_eq!(response.created, None);
+    }
+
+    #[test]
+    fn test_convert_response_without_choices() {
+        let mut response = canned_response(0);
+        response.choices.clear();
+
+        let err = convert_response(response).unwrap_err();
+        assert!(matches!(err, Error::Provider(_)));
+        assert_eq!(err.to_string(), "Provider error: empty response: no choices returned");
+    }
+
+    #[test]
+    fn test_convert_response_with_filtered_content() {
+        let response: CreateChatCompletionResponse = serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": null },
+                "finish_reason": "content_filter"
+            }]
+        }))
+        .unwrap();
+
+        let err = convert_response(response).unwrap_err();
+        assert_eq!(err.to_string(), "Provider error: empty response: finish reason content_filter");
+    }
+
+    /// One SSE event streaming `content`
+    fn sse_chunk(content: &str, finish_reason: Option<&str>) -> String {
+        let chunk = serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1_700_000_000,
+            "model": "gpt-4",
+            "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": finish_reason }],
+        });
+        format!("data: {}\n\n", chunk)
+    }
+
+    /// Read one HTTP request and return its body
+    async fn read_request_body(socket: &mut tokio::net::TcpStream) -> String {
+        use tokio::io::AsyncReadExt;
+
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&request);
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + content_length {
+                    return text[header_end + 4..].to_string();
+                }
+            }
+        }
+        String::from_utf8_lossy(&request).to_string()
+    }
+
+    /// Serve chat streams that drop after "Hello, ", except for requests
+    /// continuing a partial reply, which get the rest of the answer. Returns
+    /// the API base and the bodies of the continuation requests.
+    async fn flaky_stream_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+        let continuations = Arc::new(Mutex::new(Vec::new()));
+        let seen = continuations.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let body = read_request_body(&mut socket).await;
+
+                let mut response =
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n".to_string();
+                if body.contains("\"assistant\"") {
+                    seen.lock().unwrap().push(body);
+                    response.push_str(&sse_chunk("world!", None));
+                    response.push_str(&sse_chunk("", Some("stop")));
+                    response.push_str("data: [DONE]\n\n");
+                } else {
+                    response.push_str(&sse_chunk("Hello, ", None));
+                }
+                // Closing the connection ends the stream, finished or not
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (api_base, continuations)
+    }
+
+    fn streaming_provider(api_base: String, reconnect_streams: bool) -> OpenAIProvider {
+        let config = OpenAIConfig {
+            api_base,
+            max_retries: 2,
+            reconnect_streams,
+            ..OpenAIConfig::default()
+        };
+        OpenAIProvider::new("sk-test".into(), config)
+    }
+
+    fn say_hello() -> CompletionRequest {
+        CompletionRequest::builder()
+            .model("gpt-4")
+            .message(Message {
+                role: Role::User,
+                content: "Greet the world".to_string(),
+            })
+            .stream(true)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_stream_reconnects_after_drop() {
+        let (api_base, continuations) = flaky_stream_server().await;
+        let provider = streaming_provider(api_base, true);
+
+        let stream = provider.stream(say_hello()).await.unwrap();
+        let response = collect_stream(stream).await.unwrap();
+
+        assert_eq!(response.content, "Hello, world!");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        let continuations = continuations.lock().unwrap();
+        assert_eq!(continuations.len(), 1);
+        assert!(continuations[0].contains("Hello, "), "{}", continuations[0]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_drop_is_an_error_without_reconnect() {
+        let (api_base, continuations) = flaky_stream_server().await;
+        let provider = streaming_provider(api_base, false);
+
+        let stream = provider.stream(say_hello()).await.unwrap();
+        let err = collect_stream(stream).await.unwrap_err();
+
+        assert!(err.to_string().contains("Stream error"), "{}", err);
+        assert!(continuations.lock().unwrap().is_empty());
+    }
+
+    /// Answer every request with `body` as JSON; returns the API base and the
+    /// request bodies received
+    async fn json_server(body: serde_json::Value) -> (String, Arc<Mutex<Vec<String>>>) {
+        canned_server("application/json", body.to_string()).await
+    }
+
+    /// Answer every request with `body`; returns the API base and the request
+    /// bodies received
+    async fn canned_server(content_type: &'static str, body: String) -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_base = format!("http://{}/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let request = read_request_body(&mut socket).await;
+                seen.lock().unwrap().push(request);
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    content_type,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (api_base, requests)
+    }
+
+    #[tokio::test]
+    async fn test_embed_preserves_input_order() {
+        // The API may list embeddings in any order; `index` ties them to the inputs
+        let (api_base, requests) = json_server(serde_json::json!({
+            "object": "list",
+            "model": "text-embedding-3-small",
+            "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.0, 1.0, 0.5] },
+                { "object": "embedding", "index": 0, "embedding": [1.0, 0.0, 0.5] }
+            ],
+            "usage": { "prompt_tokens": 4, "total_tokens": 4 }
+        }))
+        .await;
+        let provider = OpenAIProvider::new("sk-test".into(), OpenAIConfig { api_base, ..OpenAIConfig::default() });
+
+        let vectors = provider
+            .embed(vec!["first".to_string(), "second".to_string()], DEFAULT_EMBEDDING_MODEL)
+            .await
+            .unwrap();
+
+        assert_eq!(vectors, vec![vec![1.0, 0.0, 0.5], vec![0.0, 1.0, 0.5]]);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("\"text-embedding-3-small\""), "{}", requests[0]);
+    }
+
+    #[tokio::test]
+    async fn test_embed_rejects_missing_vectors() {
+        let (api_base, _) = json_server(serde_json::json!({
+            "object": "list",
+            "model": "text-embedding-3-small",
+            "data": [{ "object": "embedding", "index": 0, "embedding": [1.0] }],
+            "usage": { "prompt_tokens": 4, "total_tokens": 4 }
+        }))
+        .await;
+        let provider = OpenAIProvider::new("sk-test".into(), OpenAIConfig { api_base, ..OpenAIConfig::default() });
+
+        let err = provider
+            .embed(vec!["first".to_string(), "second".to_string()], DEFAULT_EMBEDDING_MODEL)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Provider error: Expected 2 embeddings, got 1");
+    }
+
+    #[tokio::test]
+    async fn test_optional_options_are_sent_only_when_set() {
+        let (api_base, requests) = json_server(serde_json::to_value(canned_response(0)).unwrap()).await;
+        let provider = OpenAIProvider::new("sk-test".into(), OpenAIConfig { api_base, ..OpenAIConfig::default() });
+
+        let request = CompletionRequest {
+            stream: false,
+            ..say_hello()
+        };
+        provider.complete(request.clone()).await.unwrap();
+        let with_options = CompletionRequest {
+            stop: Some(vec!["\n\n".to_string(), "END".to_string()]),
+            seed: Some(7),
+            top_p: Some(0.25),
+            response_format: Some(ResponseFormat::JsonObject),
+            ..request
+        };
+        provider.complete(with_options).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let body: serde_json::Value = serde_json::from_str(&requests[0]).unwrap();
+        for option in ["stop", "seed", "top_p", "response_format"] {
+            assert!(body.get(option).is_none(), "{}", requests[0]);
+        }
+        let body: serde_json::Value = serde_json::from_str(&requests[1]).unwrap();
+        assert_eq!(body["stop"], serde_json::json!(["\n\n", "END"]));
+        assert_eq!(body["seed"], serde_json::json!(7));
+        assert_eq!(body["top_p"], serde_json::json!(0.25));
+        assert_eq!(body["response_format"], serde_json::json!({"type": "json_object"}));
+    }
+
+    #[test]
+    fn test_json_schema_response_format_is_named_after_its_title() {
+        let schema = serde_json::json!({"title": "verdict", "type": "object"});
+        let format = convert_response_format(ResponseFormat::JsonSchema(schema.clone()));
+        assert_eq!(
+            serde_json::to_value(format).unwrap(),
+            serde_json::json!({"type": "json_schema", "json_schema": {"name": "verdict", "schema": schema}})
+        );
+
+        let format = convert_response_format(ResponseFormat::JsonSchema(serde_json::json!({"type": "object"})));
+        assert_eq!(serde_json::to_value(format).unwrap()["json_schema"]["name"], "response");
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_at_stop_sequence() {
+        let events = format!("{}{}data: [DONE]\n\n", sse_chunk("SELECT 1;", None), sse_chunk("", Some("stop")));
+        let (api_base, requests) = canned_server("text/event-stream", events).await;
+        let provider = streaming_provider(api_base, false);
+
+        let request = CompletionRequest::builder()
+            .model("gpt-4")
+            .message(Message {
+                role: Role::User,
+                content: "Write a query".to_string(),
+            })
+            .stop(";")
+            .build();
+        let response = collect_stream(provider.stream(request).await.unwrap()).await.unwrap();
+
+        assert_eq!(response.content, "SELECT 1;");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        let body: serde_json::Value = serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+        assert_eq!(body["stop"], serde_json::json!([";"]));
+        assert_eq!(body["stream"], serde_json::json!(true));
+    }
+
+    #[test]
+    fn test_reconnect_delay_backs_off() {
+        assert_eq!(reconnect_delay(1), Duration::from_millis(250));
+        assert_eq!(reconnect_delay(3), Duration::from_secs(1));
+        assert_eq!(reconnect_delay(20), STREAM_RECONNECT_MAX_DELAY);
+    }
+
     #[test]
     fn test_extract_chunk() {
         // This would require mocking CreateChatCompletionStreamResponse
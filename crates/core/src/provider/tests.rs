@@ -27,6 +27,8 @@ impl LLMProvider for MockProvider {
                 completion_tokens: 20,
                 total_tokens: 30,
             },
+            created: None,
+            finish_reason: None,
         })
     }
 
@@ -54,7 +56,7 @@ impl LLMProvider for MockProvider {
 }
 
 #[cfg(test)]
-mod tests {
+mod mock_provider_tests {
     use super::*;
 
     #[tokio::test]
@@ -67,12 +69,12 @@ mod tests {
         let request = CompletionRequest {
             model: "gpt-4".to_string(),
             messages: vec![Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Hello".to_string(),
             }],
             temperature: Some(0.7),
             max_tokens: Some(100),
-            stream: false,
+            ..Default::default()
         };
 
         let response = provider.complete(request.clone()).await.unwrap();
@@ -91,12 +93,12 @@ mod tests {
         let request = CompletionRequest {
             model: "gpt-4".to_string(),
             messages: vec![Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "Hello".to_string(),
             }],
             temperature: None,
             max_tokens: None,
-            stream: false,
+            ..Default::default()
         };
 
         let result = provider.complete(request).await;
@@ -117,12 +119,13 @@ mod tests {
         let request = CompletionRequest {
             model: "gpt-4".to_string(),
             messages: vec![Message {
-                role: "system".to_string(),
+                role: Role::System,
                 content: "You are a helpful assistant".to_string(),
             }],
             temperature: Some(0.5),
             max_tokens: Some(200),
             stream: true,
+            ..Default::default()
         };
 
         let mut stream = provider.stream(request).await.unwrap();
@@ -152,31 +155,56 @@ mod tests {
     #[test]
     fn test_message_construction() {
         let msg = Message {
-            role: "assistant".to_string(),
+            role: Role::Assistant,
             content: "I can help with that".to_string(),
         };
 
-        assert_eq!(msg.role, "assistant");
+        assert_eq!(msg.role, Role::Assistant);
         assert_eq!(msg.content, "I can help with that");
     }
 
+    #[test]
+    fn test_role_serializes_to_wire_strings() {
+        let msg = Message {
+            role: Role::Tool,
+            content: "42".to_string(),
+        };
+        assert_eq!(serde_json::to_string(&msg).unwrap(), r#"{"role":"tool","content":"42"}"#);
+
+        let msg: Message = serde_json::from_str(r#"{"role":"system","content":"Be brief"}"#).unwrap();
+        assert_eq!(msg.role, Role::System);
+        assert_eq!(Role::try_from("assistant".to_string()).unwrap(), Role::Assistant);
+    }
+
+    #[test]
+    fn test_invalid_role_fails_to_deserialize() {
+        let err = serde_json::from_str::<Message>(r#"{"role":"assistat","content":"hi"}"#).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unknown message role 'assistat', expected one of system, user, assistant, tool"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     fn test_completion_request_builder() {
         let request = CompletionRequest {
             model: "gpt-3.5-turbo".to_string(),
             messages: vec![
                 Message {
-                    role: "system".to_string(),
+                    role: Role::System,
                     content: "You are a coding assistant".to_string(),
                 },
                 Message {
-                    role: "user".to_string(),
+                    role: Role::User,
                     content: "Write a hello world program".to_string(),
                 },
             ],
             temperature: Some(0.8),
             max_tokens: Some(1000),
             stream: true,
+            ..Default::default()
         };
 
         assert_eq!(request.model, "gpt-3.5-turbo");
@@ -186,6 +214,119 @@ mod tests {
         assert!(request.stream);
     }
 
+    #[test]
+    fn test_completion_request_builder_defaults() {
+        let request = CompletionRequest::builder().model("gpt-4").build();
+
+        assert_eq!(request.model, "gpt-4");
+        assert!(request.messages.is_empty());
+        assert_eq!(request.temperature, None);
+        assert_eq!(request.max_tokens, None);
+        assert!(!request.stream);
+    }
+
+    #[test]
+    fn test_completion_request_builder_setters() {
+        let request = CompletionRequest::builder()
+            .model("gpt-3.5-turbo")
+            .message(Message {
+                role: Role::System,
+                content: "You are a coding assistant".to_string(),
+            })
+            .message(Message {
+                role: Role::User,
+                content: "Write a hello world program".to_string(),
+            })
+            .temperature(0.8)
+            .max_tokens(1000)
+            .stream(true)
+            .build();
+
+        assert_eq!(request.model, "gpt-3.5-turbo");
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.messages[1].role, Role::User);
+        assert_eq!(request.temperature, Some(0.8));
+        assert_eq!(request.max_tokens, Some(1000));
+        assert!(request.stream);
+
+        let replaced = CompletionRequest::builder()
+            .message(Message {
+                role: Role::User,
+                content: "dropped".to_string(),
+            })
+            .messages(vec![])
+            .build();
+        assert!(replaced.messages.is_empty());
+    }
+
+    #[test]
+    fn test_seed_and_top_p_serialize_only_when_set() {
+        let json = serde_json::to_value(CompletionRequest::builder().model("gpt-4").build()).unwrap();
+        assert!(json.get("seed").is_none());
+        assert!(json.get("top_p").is_none());
+
+        let request = CompletionRequest::builder().model("gpt-4").seed(42).top_p(0.5).build();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["seed"], serde_json::json!(42));
+        assert_eq!(json["top_p"], serde_json::json!(0.5));
+    }
+
+    #[test]
+    fn test_response_format_round_trips() {
+        let schema = serde_json::json!({"type": "object", "required": ["verdict"]});
+        let request = CompletionRequest::builder()
+            .model("gpt-4")
+            .response_format(ResponseFormat::JsonSchema(schema.clone()))
+            .build();
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["response_format"], serde_json::json!({"json_schema": schema}));
+        let parsed: CompletionRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.response_format, Some(ResponseFormat::JsonSchema(schema)));
+
+        let json = serde_json::to_value(CompletionRequest::builder().build()).unwrap();
+        assert!(json.get("response_format").is_none());
+    }
+
+    #[test]
+    fn test_stop_sequences_serialize_only_when_set() {
+        let request = CompletionRequest::builder().model("gpt-4").build();
+        assert!(!serde_json::to_string(&request).unwrap().contains("stop"));
+
+        let request = CompletionRequest::builder().model("gpt-4").stop("\n").stop("END").build();
+        assert_eq!(request.stop, Some(vec!["\n".to_string(), "END".to_string()]));
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["stop"], serde_json::json!(["\n", "END"]));
+    }
+
+    #[test]
+    fn test_validate_accepts_boundary_values() {
+        for temperature in [0.0, 2.0] {
+            let request = CompletionRequest::builder().temperature(temperature).max_tokens(1).build();
+            assert!(request.validate().is_ok(), "temperature {}", temperature);
+        }
+        for top_p in [0.0, 1.0] {
+            assert!(CompletionRequest::builder().top_p(top_p).build().validate().is_ok(), "top_p {}", top_p);
+        }
+        assert!(CompletionRequest::builder().build().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_values() {
+        for temperature in [-0.1, 2.1, f32::NAN] {
+            let err = CompletionRequest::builder().temperature(temperature).build().validate().unwrap_err();
+            assert!(matches!(err, Error::InvalidRequest(_)), "temperature {}", temperature);
+        }
+
+        for top_p in [-0.1, 1.1, f32::NAN] {
+            let err = CompletionRequest::builder().top_p(top_p).build().validate().unwrap_err();
+            assert!(matches!(err, Error::InvalidRequest(_)), "top_p {}", top_p);
+        }
+
+        let err = CompletionRequest::builder().max_tokens(0).build().validate().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid request: max_tokens must be at least 1");
+    }
+
     #[test]
     fn test_usage_calculation() {
         let usage = Usage {
@@ -198,4 +339,76 @@ mod tests {
         assert_eq!(usage.completion_tokens, 100);
         assert_eq!(usage.total_tokens, 150);
     }
-}
\ No newline at end of file
+
+    fn message(role: Role, content: &str) -> Message {
+        Message {
+            role,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_split_system_without_system_messages() {
+        let messages = vec![message(Role::User, "Hi"), message(Role::Assistant, "Hello")];
+        assert_eq!(split_system(&messages), (None, messages.clone()));
+        assert_eq!(split_system(&[]), (None, vec![]));
+    }
+
+    #[test]
+    fn test_split_system_with_one_system_message() {
+        let messages = vec![message(Role::System, "Be brief"), message(Role::User, "Hi")];
+        assert_eq!(
+            split_system(&messages),
+            (Some("Be brief".to_string()), vec![message(Role::User, "Hi")])
+        );
+    }
+
+    #[test]
+    fn test_split_system_joins_leading_system_messages() {
+        let messages = vec![
+            message(Role::System, "You are a reviewer."),
+            message(Role::System, "Relevant file: main.rs"),
+            message(Role::User, "Review it"),
+            message(Role::System, "Answer in French"),
+            message(Role::Assistant, "D'accord"),
+        ];
+
+        let (system, rest) = split_system(&messages);
+        assert_eq!(system.as_deref(), Some("You are a reviewer.\n\nRelevant file: main.rs"));
+        // A system message mid-conversation stays in place
+        assert_eq!(rest, messages[2..].to_vec());
+    }
+
+    fn chunk(delta: &str, finish_reason: Option<&str>) -> Result<StreamChunk> {
+        Ok(StreamChunk {
+            delta: delta.to_string(),
+            finish_reason: finish_reason.map(str::to_string),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream_concatenates_chunks() {
+        let chunks = vec![
+            chunk("Hello", None),
+            chunk(", ", None),
+            chunk("world!", None),
+            chunk("", Some("stop")),
+        ];
+
+        let response = collect_stream(Box::pin(tokio_stream::iter(chunks))).await.unwrap();
+
+        assert_eq!(response.content, "Hello, world!");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        // 13 characters at four per token, rounded up
+        assert_eq!(response.usage.completion_tokens, 4);
+        assert_eq!(response.usage.total_tokens, 4);
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream_propagates_errors() {
+        let chunks = vec![chunk("partial", None), Err(Error::Provider("connection reset".into()))];
+
+        let err = collect_stream(Box::pin(tokio_stream::iter(chunks))).await.unwrap_err();
+        assert!(err.to_string().contains("connection reset"));
+    }
+}
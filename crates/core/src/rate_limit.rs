@@ -0,0 +1,160 @@
+use crate::config::RateLimitConfig;
+use crate::provider::CompletionRequest;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// Remaining capacity of a rate limiter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RateLimitCapacity {
+    pub requests: f64,
+    pub tokens: f64,
+}
+
+/// Token-bucket limiter enforcing a provider's requests-per-minute and
+/// tokens-per-minute budgets. Both buckets start full and refill
+/// continuously; callers wait for capacity instead of failing.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    requests: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                requests: config.requests_per_minute as f64,
+                tokens: config.tokens_per_minute as f64,
+                refilled_at: Instant::now(),
+            }),
+            config,
+        }
+    }
+
+    /// Wait until one request costing `tokens` fits in both buckets, then
+    /// take it. Costs above the per-minute budget are capped at the budget
+    /// so a single oversized request can't wait forever.
+    pub async fn acquire(&self, tokens: u32) {
+        let tokens = (tokens as f64).min(self.config.tokens_per_minute as f64);
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                self.refill(&mut state);
+
+                if state.requests >= 1.0 && state.tokens >= tokens {
+                    state.requests -= 1.0;
+                    state.tokens -= tokens;
+                    return;
+                }
+
+                let request_wait = wait_for(1.0 - state.requests, self.config.requests_per_minute);
+                let token_wait = wait_for(tokens - state.tokens, self.config.tokens_per_minute);
+                request_wait.max(token_wait)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Capacity available right now
+    pub async fn capacity(&self) -> RateLimitCapacity {
+        let mut state = self.state.lock().await;
+        self.refill(&mut state);
+        RateLimitCapacity {
+            requests: state.requests,
+            tokens: state.tokens,
+        }
+    }
+
+    fn refill(&self, state: &mut BucketState) {
+        let now = Instant::now();
+        let minutes = now.duration_since(state.refilled_at).as_secs_f64() / 60.0;
+        state.requests = (state.requests + minutes * self.config.requests_per_minute as f64)
+            .min(self.config.requests_per_minute as f64);
+        state.tokens = (state.tokens + minutes * self.config.tokens_per_minute as f64)
+            .min(self.config.tokens_per_minute as f64);
+        state.refilled_at = now;
+    }
+}
+
+/// Time for a bucket refilling at `per_minute` to gain `missing` units
+fn wait_for(missing: f64, per_minute: u32) -> Duration {
+    if missing <= 0.0 || per_minute == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(missing * 60.0 / per_minute as f64)
+}
+
+/// Rough token cost of a request: about four characters per token of
+/// input, plus the completion budget
+pub fn estimate_request_tokens(request: &CompletionRequest) -> u32 {
+    let chars: usize = request.messages.iter().map(|m| m.content.chars().count()).sum();
+    (chars / 4) as u32 + request.max_tokens.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_minute: u32, tokens_per_minute: u32) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            requests_per_minute,
+            tokens_per_minute,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_wait_for_the_bucket() {
+        // Two requests up front, then one every 30 seconds
+        let limiter = limiter(2, 100_000);
+        let start = Instant::now();
+
+        for _ in 0..4 {
+            limiter.acquire(1).await;
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(60), "finished after {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(61), "finished after {:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tokens_wait_for_the_bucket() {
+        let limiter = limiter(1000, 600);
+        let start = Instant::now();
+
+        limiter.acquire(600).await;
+        // The bucket is empty and refills 10 tokens per second
+        limiter.acquire(100).await;
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(10), "finished after {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(11), "finished after {:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_capacity_reports_remaining_budget() {
+        let limiter = limiter(10, 1000);
+        limiter.acquire(250).await;
+
+        let capacity = limiter.capacity().await;
+        assert_eq!(capacity.requests, 9.0);
+        assert_eq!(capacity.tokens, 750.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_oversized_request_is_capped() {
+        let limiter = limiter(10, 100);
+        limiter.acquire(10_000).await;
+        assert_eq!(limiter.capacity().await.tokens, 0.0);
+    }
+}
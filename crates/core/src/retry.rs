@@ -0,0 +1,123 @@
+use crate::error::{Error, OpenCodeError, RetryPolicy};
+use std::future::Future;
+use tracing::debug;
+
+/// Errors that know whether, and how, a failed operation should be retried
+pub trait Retryable {
+    fn retry_policy(&self) -> RetryPolicy;
+}
+
+impl Retryable for OpenCodeError {
+    fn retry_policy(&self) -> RetryPolicy {
+        OpenCodeError::retry_policy(self)
+    }
+}
+
+impl Retryable for Error {
+    fn retry_policy(&self) -> RetryPolicy {
+        match self {
+            Error::RateLimited(_) => RetryPolicy::transient(),
+            _ => RetryPolicy::None,
+        }
+    }
+}
+
+/// Run `op`, retrying according to the policy of the error it returns.
+/// Permanent errors (`RetryPolicy::None`) are returned immediately; the last
+/// error is returned once the policy's attempts are used up.
+pub async fn retry_with_policy<F, Fut, T, E>(mut op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Retryable,
+{
+    let mut attempt: u32 = 1;
+    loop {
+        let error = match op().await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        let delay = match error.retry_policy() {
+            RetryPolicy::None => None,
+            RetryPolicy::Exponential {
+                max_attempts,
+                base_delay,
+                max_delay,
+            } => (attempt < max_attempts).then(|| {
+                base_delay
+                    .saturating_mul(2u32.saturating_pow(attempt - 1))
+                    .min(max_delay)
+            }),
+            RetryPolicy::Fixed { attempts, delay } => (attempt < attempts).then_some(delay),
+        };
+
+        match delay {
+            Some(delay) => {
+                debug!("Attempt {} failed, retrying in {:?}", attempt, delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            None => return Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ConfigError, NetworkError, ProviderError};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    async fn count_attempts(make_error: fn() -> OpenCodeError) -> (u32, Result<(), OpenCodeError>) {
+        let attempts = AtomicU32::new(0);
+        let result = retry_with_policy(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(make_error())
+        })
+        .await;
+        (attempts.load(Ordering::SeqCst), result)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_transient_error_is_retried() {
+        let (attempts, result) = count_attempts(|| OpenCodeError::Provider(ProviderError::RateLimitExceeded)).await;
+        assert_eq!(attempts, 3);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_permanent_error_fails_fast() {
+        let (attempts, result) = count_attempts(|| {
+            OpenCodeError::Configuration(ConfigError::Validation("bad".to_string()))
+        })
+        .await;
+        assert_eq!(attempts, 1);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_exponential_backoff_schedule() {
+        let start = tokio::time::Instant::now();
+        let (attempts, _) = count_attempts(|| OpenCodeError::Network(NetworkError::ConnectionRefused)).await;
+        assert_eq!(attempts, 3);
+        // 1s after the first failure, 2s after the second
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_recovers_after_transient_failure() {
+        let attempts = AtomicU32::new(0);
+        let result = retry_with_policy(|| async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(Error::RateLimited("slow down".to_string())),
+                _ => Ok("done"),
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
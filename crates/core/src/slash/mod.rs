@@ -1,11 +1,16 @@
 use crate::personas::{load_personas, Persona};
+use crate::provider::{CompletionRequest, Message, Role};
 use anyhow::{anyhow, Context, Result};
 use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
 
 #[cfg(test)]
 mod tests;
 
+/// Default cap on the total bytes of file context a glob may pull into a prompt
+pub const DEFAULT_MAX_CONTEXT_BYTES: usize = 256 * 1024;
+
 #[derive(Debug, Default)]
 pub struct Command {
     pub name: String,
@@ -52,7 +57,8 @@ pub fn parse_with_personas(line: &str, personas: HashMap<String, Persona>) -> Re
                 if i + 1 >= args.len() {
                     return Err(anyhow!("Missing file path after --file"));
                 }
-                cmd.file_path = Some(args[i + 1].to_string());
+                // Allow quoting globs so they read naturally: --file 'src/**/*.rs'
+                cmd.file_path = Some(args[i + 1].trim_matches(|c| c == '\'' || c == '"').to_string());
                 i += 2;
             }
             arg if arg.starts_with("--") => {
@@ -72,34 +78,123 @@ pub fn parse_with_personas(line: &str, personas: HashMap<String, Persona>) -> Re
 
 /// Renders a parsed command into a final prompt for the AI.
 pub fn render(cmd: Command) -> Result<String> {
+    render_with_limit(cmd, DEFAULT_MAX_CONTEXT_BYTES)
+}
+
+/// Renders a parsed command into a request for `default_model`. The
+/// persona's model and temperature replace the defaults when it sets them.
+pub fn render_request(cmd: Command, default_model: &str) -> Result<CompletionRequest> {
+    let persona = cmd.persona.clone();
+    let mut request = CompletionRequest::builder()
+        .model(default_model)
+        .message(Message {
+            role: Role::User,
+            content: render(cmd)?,
+        })
+        .temperature(0.7)
+        .max_tokens(1000)
+        .build();
+
+    if let Some(persona) = persona {
+        persona.apply_to(&mut request);
+    }
+    Ok(request)
+}
+
+/// Task text of each slash command. `{{source}}` names the file context.
+const TASK_TEMPLATES: &[(&str, &str)] = &[
+    ("test", "Based on the context from {{source}}, please write a comprehensive suite of unit tests for the code. Cover edge cases."),
+    ("build", "Based on the context from {{source}}, analyze the code for potential build issues or improvements."),
+    ("explain", "Explain the code provided in the context file. Describe its purpose, how it works, and any potential improvements."),
+];
+
+/// Renders a parsed command, capping file context pulled in by a glob at
+/// `max_context_bytes`.
+///
+/// The task text and the persona's system prompt are templates that may
+/// use `{{command}}` (the command name) and `{{source}}` (the file or glob
+/// given with `--file`).
+pub fn render_with_limit(cmd: Command, max_context_bytes: usize) -> Result<String> {
+    let task_template = TASK_TEMPLATES
+        .iter()
+        .find(|(name, _)| *name == cmd.name)
+        .map(|(_, template)| *template)
+        .ok_or_else(|| anyhow!("Unknown slash command: /{}", cmd.name))?;
+
+    let source = match &cmd.file_path {
+        Some(path) => path.clone(),
+        None => "the file".to_string(),
+    };
+    let vars = HashMap::from([
+        ("command".to_string(), cmd.name.clone()),
+        ("source".to_string(), source),
+    ]);
+
     let mut final_prompt = String::new();
 
     // 1. Add the persona's system prompt if it exists.
     if let Some(persona) = &cmd.persona {
         final_prompt.push_str(&format!(
             "SYSTEM PROMPT: {}\n\n---\n\n",
-            persona.system_prompt
+            persona.render_system_prompt(&vars)?
         ));
     }
 
-    // 2. Add context from a file if provided.
+    // 2. Add context from a file (or every file matched by a glob) if provided.
     if let Some(path) = &cmd.file_path {
-        let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read file: {}", path))?;
-        final_prompt.push_str(&format!(
-            "CONTEXT FROM FILE ({}):\n```\n{}\n```\n\n---\n\n",
-            path, content
-        ));
+        if is_glob(path) {
+            final_prompt.push_str(&render_glob_context(path, max_context_bytes)?);
+        } else {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read file: {}", path))?;
+            final_prompt.push_str(&format!(
+                "CONTEXT FROM FILE ({}):\n```\n{}\n```\n\n---\n\n",
+                path, content
+            ));
+        }
     }
 
     // 3. Add the main task based on the command name.
-    let task = match cmd.name.as_str() {
-        "test" => "Based on the context from the file, please write a comprehensive suite of unit tests for the code. Cover edge cases.",
-        "build" => "Based on the context from the file, analyze the code for potential build issues or improvements.",
-        "explain" => "Explain the code provided in the context file. Describe its purpose, how it works, and any potential improvements.",
-        _ => return Err(anyhow!("Unknown slash command: /{}", cmd.name)),
-    };
+    let task = crate::template::render_template(task_template, &vars)?;
     final_prompt.push_str(&format!("TASK: {}\n", task));
 
     Ok(final_prompt)
-}
\ No newline at end of file
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Reads every file matched by `pattern` into a single context block with a
+/// header per file.
+fn render_glob_context(pattern: &str, max_context_bytes: usize) -> Result<String> {
+    let mut paths: Vec<PathBuf> = glob::glob(pattern)
+        .with_context(|| format!("Invalid glob pattern: {}", pattern))?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        return Err(anyhow!("No files matched pattern: {}", pattern));
+    }
+
+    let mut context = format!("CONTEXT FROM FILE ({}):\n", pattern);
+    let mut total_bytes = 0;
+    for path in paths {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        total_bytes += content.len();
+        if total_bytes > max_context_bytes {
+            return Err(anyhow!(
+                "Files matched by '{}' exceed the context limit of {} bytes",
+                pattern,
+                max_context_bytes
+            ));
+        }
+        context.push_str(&format!("--- {} ---\n```\n{}\n```\n", path.display(), content));
+    }
+    context.push_str("\n---\n\n");
+
+    Ok(context)
+}
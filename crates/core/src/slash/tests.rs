@@ -15,6 +15,7 @@ fn sample_personas() -> HashMap<String, Persona> {
         Persona {
             name: "rusty".to_string(),
             system_prompt: "You are a senior Rust developer".to_string(),
+            ..Default::default()
         },
     );
     personas.insert(
@@ -22,6 +23,7 @@ fn sample_personas() -> HashMap<String, Persona> {
         Persona {
             name: "security".to_string(),
             system_prompt: "You are a cybersecurity expert".to_string(),
+            ..Default::default()
         },
     );
     personas
@@ -130,6 +132,7 @@ fn test_render_command_with_persona() {
     let persona = Persona {
         name: "rusty".to_string(),
         system_prompt: "You are a Rust expert".to_string(),
+        ..Default::default()
     };
     
     let cmd = Command {
@@ -165,6 +168,7 @@ fn test_render_command_with_both(temp_file: TempDir) {
     let persona = Persona {
         name: "security".to_string(),
         system_prompt: "You are a security expert".to_string(),
+        ..Default::default()
     };
     let file_path = temp_file.path().join("test.rs");
     
@@ -181,6 +185,26 @@ fn test_render_command_with_both(temp_file: TempDir) {
     assert!(result.contains("TASK: Based on the context"));
 }
 
+#[rstest]
+fn test_render_substitutes_persona_placeholders(temp_file: TempDir) {
+    let persona = Persona {
+        name: "tester".to_string(),
+        system_prompt: "You write tests for /{{command}} on {{source}}".to_string(),
+        ..Default::default()
+    };
+    let file_path = temp_file.path().join("test.rs").to_string_lossy().to_string();
+
+    let cmd = Command {
+        name: "test".to_string(),
+        persona: Some(persona),
+        file_path: Some(file_path.clone()),
+    };
+
+    let result = render(cmd).expect("Should render templated persona");
+    assert!(result.contains(&format!("SYSTEM PROMPT: You write tests for /test on {}", file_path)));
+    assert!(result.contains(&format!("TASK: Based on the context from {}", file_path)));
+}
+
 #[rstest]
 fn test_render_unknown_command() {
     let cmd = Command {
@@ -219,4 +243,96 @@ fn test_command_task_descriptions(command_name: &str, expected_task: &str) {
     
     let result = render(cmd).expect("Should render command");
     assert!(result.contains(expected_task));
-}
\ No newline at end of file
+}
+
+#[fixture]
+fn source_tree() -> TempDir {
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let src = temp_dir.path().join("src");
+    fs::create_dir_all(src.join("nested")).expect("Failed to create dirs");
+    fs::write(src.join("lib.rs"), "pub mod nested;").expect("Failed to write file");
+    fs::write(src.join("nested").join("mod.rs"), "pub fn nested() {}").expect("Failed to write file");
+    fs::write(src.join("notes.txt"), "not rust").expect("Failed to write file");
+    temp_dir
+}
+
+#[rstest]
+fn test_parse_command_with_quoted_glob(sample_personas: HashMap<String, Persona>) {
+    let result = parse_with_personas("/explain --file 'src/**/*.rs'", sample_personas)
+        .expect("Should parse command with glob");
+    assert_eq!(result.file_path, Some("src/**/*.rs".to_string()));
+}
+
+#[rstest]
+fn test_render_glob_concatenates_matched_files(source_tree: TempDir) {
+    let pattern = source_tree.path().join("src/**/*.rs");
+    let cmd = Command {
+        name: "explain".to_string(),
+        persona: None,
+        file_path: Some(pattern.to_string_lossy().to_string()),
+    };
+
+    let result = render(cmd).expect("Should render glob context");
+    assert_eq!(result.matches("CONTEXT FROM FILE").count(), 1);
+    assert!(result.contains("lib.rs ---"));
+    assert!(result.contains("mod.rs ---"));
+    assert!(result.contains("pub mod nested;"));
+    assert!(result.contains("pub fn nested() {}"));
+    assert!(!result.contains("not rust"));
+    assert!(result.find("lib.rs ---").unwrap() < result.find("mod.rs ---").unwrap());
+}
+
+#[rstest]
+fn test_render_glob_without_matches(source_tree: TempDir) {
+    let pattern = source_tree.path().join("src/**/*.py");
+    let cmd = Command {
+        name: "explain".to_string(),
+        persona: None,
+        file_path: Some(pattern.to_string_lossy().to_string()),
+    };
+
+    let result = render(cmd);
+    assert!(result.unwrap_err().to_string().contains("No files matched pattern"));
+}
+
+#[rstest]
+fn test_render_glob_exceeding_limit(source_tree: TempDir) {
+    let pattern = source_tree.path().join("src/**/*.rs");
+    let cmd = Command {
+        name: "explain".to_string(),
+        persona: None,
+        file_path: Some(pattern.to_string_lossy().to_string()),
+    };
+
+    let result = render_with_limit(cmd, 20);
+    assert!(result.unwrap_err().to_string().contains("exceed the context limit of 20 bytes"));
+}
+
+#[rstest]
+fn test_render_request_applies_persona_overrides() {
+    let persona = Persona {
+        name: "creative".to_string(),
+        system_prompt: "You brainstorm".to_string(),
+        model: Some("gpt-4o".to_string()),
+        temperature: Some(1.2),
+        ..Default::default()
+    };
+    let cmd = Command {
+        name: "explain".to_string(),
+        persona: Some(persona),
+        file_path: None,
+    };
+
+    let request = render_request(cmd, "gpt-4").expect("Should render request");
+    assert_eq!(request.model, "gpt-4o");
+    assert_eq!(request.temperature, Some(1.2));
+    assert!(request.messages[0].content.starts_with("SYSTEM PROMPT: You brainstorm"));
+
+    let cmd = Command {
+        name: "explain".to_string(),
+        ..Default::default()
+    };
+    let request = render_request(cmd, "gpt-4").expect("Should render request");
+    assert_eq!(request.model, "gpt-4");
+    assert_eq!(request.temperature, Some(0.7));
+}
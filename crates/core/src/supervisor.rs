@@ -1,51 +1,536 @@
-use anyhow::{Context, Result};
+use crate::behavior::{AgentBehavior, BehaviorFactory};
+use crate::container::{ContainerManager, LogBuffer, AGENT_SHELL_COMMAND, DEFAULT_LOG_LINES};
+use crate::error::{Error, Result};
+use crate::git::GitCheckpointManager;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, instrument, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Agent {
     pub id: String,
     pub persona: String,
     pub status: AgentStatus,
     pub branch_name: String,
+    /// Free-form `key=value` metadata such as project, priority or owner
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Lifecycle state of an agent, shared by the supervisor, the swarm
+/// orchestrator and the GUI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub enum AgentStatus {
+    Starting,
     Running,
+    Idle,
+    Busy,
     Stopped,
     Error(String),
 }
 
+/// Point-in-time health summary of a supervisor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SupervisorHealth {
+    /// False as soon as any agent is in the `Error` state
+    pub is_healthy: bool,
+    pub total_agents: usize,
+    /// Agents that are `Running` or `Busy`
+    pub running_agents: usize,
+    pub failed_agents: usize,
+}
+
+/// Outcome of `AgentSupervisor::shutdown_with_timeout`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ShutdownReport {
+    /// Agents whose containers were torn down cleanly
+    pub stopped: Vec<String>,
+    /// Agents that failed or ran out of time and were force-aborted
+    pub forced: Vec<String>,
+}
+
+/// Counters accumulated over the lifetime of a supervisor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SupervisorStats {
+    /// Number of tasks completed, counted as Busy -> Idle transitions
+    pub total_tasks: usize,
+}
+
+#[derive(Debug)]
 pub struct AgentSupervisor {
     agents: Arc<Mutex<HashMap<String, Agent>>>,
+    /// Maximum number of active (non-stopped) agents; once reached the
+    /// supervisor stops accepting work and refuses to spawn or restart
+    /// agents. `None` means unbounded.
+    max_agents: Option<usize>,
+    draining: AtomicBool,
+    completed_tasks: Arc<AtomicUsize>,
+    /// Creates the behavior that handles each launched agent's tasks
+    behavior: BehaviorFactory,
+    /// When set, spawned agents run inside `container-use` environments
+    containers: Option<ContainerManager>,
+    /// Whether `cu` has been found to work, checked on the first spawn
+    runtime_checked: AtomicBool,
+    background: Background,
+    /// Recent container output per agent, kept after the agent stops
+    logs: Mutex<HashMap<String, LogBuffer>>,
+    /// Echo captured agent output to stdout as well
+    tee_logs: bool,
+    /// When set, running agents' branches are checkpointed periodically
+    auto_checkpoint: Option<AutoCheckpoint>,
+    /// When set, agents that stay `Running` or `Busy` this long are stopped
+    agent_timeout: Option<Duration>,
+}
+
+/// Everything running in the background on behalf of agents, keyed by agent
+/// id. Shared with the timeout timers so they can tear agents down.
+#[derive(Debug, Clone, Default)]
+struct Background {
+    /// Tasks of containerized agents
+    tasks: Arc<Mutex<HashMap<String, AgentTask>>>,
+    /// Message loops handing tasks to agents' behaviors
+    loops: Arc<Mutex<HashMap<String, AgentLoop>>>,
+    /// Auto-checkpoint timers
+    checkpoint_timers: Arc<Mutex<HashMap<String, JoinHandle<()>>>>,
+    /// Timers stopping agents that outlive the agent timeout
+    timeout_timers: Arc<Mutex<HashMap<String, JoinHandle<()>>>>,
+}
+
+impl Background {
+    /// Abort an agent's background task and timers and stop its container,
+    /// if any
+    async fn teardown(&self, id: &str, containers: Option<&ContainerManager>) -> Result<()> {
+        if let Some(agent_loop) = self.loops.lock().await.remove(id) {
+            agent_loop.handle.abort();
+        }
+        if let Some(timer) = self.checkpoint_timers.lock().await.remove(id) {
+            timer.abort();
+        }
+        if let Some(timer) = self.timeout_timers.lock().await.remove(id) {
+            timer.abort();
+        }
+
+        let task = self.tasks.lock().await.remove(id);
+
+        match (task, containers) {
+            (Some(task), Some(manager)) => {
+                task.handle.abort();
+                manager.stop_container(&task.container_id).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AutoCheckpoint {
+    manager: GitCheckpointManager,
+    interval: Duration,
+}
+
+/// The sending end of an agent's task queue and the loop draining it
+#[derive(Debug)]
+struct AgentLoop {
+    tasks: mpsc::UnboundedSender<String>,
+    handle: JoinHandle<()>,
+}
+
+/// A running containerized agent and the environment it was started in
+#[derive(Debug)]
+struct AgentTask {
+    container_id: String,
+    handle: JoinHandle<()>,
 }
 
 impl AgentSupervisor {
+    /// Supervisor with no optional dependencies: agents run in-process
+    /// without containers, echo their tasks back, are never checkpointed or
+    /// timed out, and any number of them may be active. Chain the `with_*`
+    /// methods to supply a container manager, behavior, limits and timers,
+    /// or use `default()` for a supervisor backed by real containers.
     pub fn new() -> Self {
         Self {
             agents: Arc::new(Mutex::new(HashMap::new())),
+            max_agents: None,
+            draining: AtomicBool::new(false),
+            completed_tasks: Arc::new(AtomicUsize::new(0)),
+            behavior: BehaviorFactory::default(),
+            containers: None,
+            runtime_checked: AtomicBool::new(false),
+            background: Background::default(),
+            logs: Mutex::new(HashMap::new()),
+            tee_logs: false,
+            auto_checkpoint: None,
+            agent_timeout: None,
+        }
+    }
+
+    /// Echo the output of containerized agents to stdout while capturing it
+    pub fn with_tee_logs(mut self, tee: bool) -> Self {
+        self.tee_logs = tee;
+        self
+    }
+
+    /// Handle the tasks of each launched agent with the behavior `factory`
+    /// creates for it, instead of echoing them back
+    pub fn with_behavior(
+        mut self,
+        factory: impl Fn(&Agent) -> Box<dyn AgentBehavior> + Send + Sync + 'static,
+    ) -> Self {
+        self.behavior = BehaviorFactory::new(factory);
+        self
+    }
+
+    /// Run agents started with `spawn` inside containers managed by `manager`
+    pub fn with_container_manager(mut self, manager: ContainerManager) -> Self {
+        self.containers = Some(manager);
+        self
+    }
+
+    /// Checkpoint each agent's branch every `interval` while it runs
+    pub fn with_auto_checkpoint(mut self, manager: GitCheckpointManager, interval: Duration) -> Self {
+        self.auto_checkpoint = Some(AutoCheckpoint { manager, interval });
+        self
+    }
+
+    /// Stop agents that have been `Running` or `Busy` for `timeout` without
+    /// a break, tearing down their containers. Time spent `Idle` doesn't count.
+    pub fn with_agent_timeout(mut self, timeout: Duration) -> Self {
+        self.agent_timeout = Some(timeout);
+        self
+    }
+
+    /// Stop accepting work and refuse to add agents once `max_agents` are
+    /// active
+    pub fn with_max_agents(mut self, max_agents: usize) -> Self {
+        self.max_agents = Some(max_agents);
+        self
+    }
+
+    /// Create a supervisor limited to `capacity` active agents, the same as
+    /// `new().with_max_agents(capacity)`
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::new().with_max_agents(capacity)
+    }
+
+    /// Mark the supervisor as draining so it stops accepting new work
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Whether the supervisor is willing to take on more work.
+    /// Returns false while draining or once the active agent count reaches `max_agents`.
+    pub async fn accepting_work(&self) -> bool {
+        if self.is_draining() {
+            return false;
+        }
+
+        let agents = self.agents.lock().await;
+        self.check_agent_limit(&agents, 1).is_ok()
+    }
+
+    pub async fn spawn(&self, id: &str, persona: &str) -> Result<()> {
+        self.spawn_with_labels(id, persona, HashMap::new()).await
+    }
+
+    /// Like `spawn`, tagging the agent with `labels`
+    #[instrument(name = "agent.spawn", skip(self))]
+    pub async fn spawn_with_labels(
+        &self,
+        id: &str,
+        persona: &str,
+        labels: HashMap<String, String>,
+    ) -> Result<()> {
+        self.check_container_runtime().await?;
+        let branch_name = self.insert_agent(id, persona, AgentStatus::Running, labels).await?;
+        self.launch(id, branch_name).await;
+        Ok(())
+    }
+
+    /// Fail early, before any agent is created, when agents would run in
+    /// containers but `cu` is unusable. Only checked until it succeeds once.
+    async fn check_container_runtime(&self) -> Result<()> {
+        let Some(manager) = &self.containers else {
+            return Ok(());
+        };
+        if manager.is_dry_run() || self.runtime_checked.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        manager.check_cu_exists().await.map_err(|e| {
+            Error::Service(format!(
+                "container-use not found; install it and add `cu` to your PATH ({})",
+                e
+            ))
+        })?;
+        self.runtime_checked.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Spawn `count` agents with the same persona, returning their ids.
+    /// A single agent keeps `base_id`; larger pools are numbered
+    /// `<base_id>-1`, `<base_id>-2`, and so on. Nothing is spawned if any of
+    /// those ids is already taken.
+    pub async fn spawn_pool(&self, base_id: &str, persona: &str, count: usize) -> Result<Vec<String>> {
+        self.spawn_pool_with_labels(base_id, persona, count, HashMap::new()).await
+    }
+
+    /// Like `spawn_pool`, tagging every agent in the pool with `labels`
+    pub async fn spawn_pool_with_labels(
+        &self,
+        base_id: &str,
+        persona: &str,
+        count: usize,
+        labels: HashMap<String, String>,
+    ) -> Result<Vec<String>> {
+        let ids: Vec<String> = match count {
+            0 => return Err(Error::Service("Cannot spawn a pool of 0 agents".into())),
+            1 => vec![base_id.to_string()],
+            _ => (1..=count).map(|i| format!("{}-{}", base_id, i)).collect(),
+        };
+
+        {
+            let agents = self.agents.lock().await;
+            if let Some(taken) = ids.iter().find(|id| agents.contains_key(id.as_str())) {
+                return Err(Error::Service(format!("Agent with id '{}' already exists", taken)));
+            }
+            self.check_agent_limit(&agents, ids.len())?;
+        }
+
+        for id in &ids {
+            self.spawn_with_labels(id, persona, labels.clone()).await?;
+        }
+        Ok(ids)
+    }
+
+    /// Stop an agent if it is still running, then start it again on the same
+    /// branch with the same persona. Errored agents pass through `Stopped`
+    /// on their way back to `Running`. Restarting a stopped agent counts
+    /// against `max_agents` like spawning one.
+    #[instrument(name = "agent.restart", skip(self))]
+    pub async fn restart(&self, id: &str) -> Result<()> {
+        let agent = self.get_agent(id).await?;
+        if agent.status == AgentStatus::Stopped {
+            self.check_agent_limit(&*self.agents.lock().await, 1)?;
+        }
+
+        self.stop(id).await?;
+        self.update_agent_status(id, AgentStatus::Running).await?;
+        self.launch(id, agent.branch_name).await;
+        Ok(())
+    }
+
+    /// Start the message loop of an agent, plus the container task and
+    /// timers the supervisor is configured with. Output is appended to the
+    /// agent's existing logs, so they survive restarts.
+    async fn launch(&self, id: &str, branch_name: String) {
+        let logs = self
+            .logs
+            .lock()
+            .await
+            .entry(id.to_string())
+            .or_insert_with(|| LogBuffer::new(DEFAULT_LOG_LINES).with_tee(self.tee_logs))
+            .clone();
+
+        let agent = self.agents.lock().await.get(id).cloned();
+        if let Some(agent) = agent {
+            let (tasks, receiver) = mpsc::unbounded_channel();
+            let handle = tokio::spawn(run_message_loop(
+                id.to_string(),
+                self.behavior.create(&agent),
+                receiver,
+                Arc::clone(&self.agents),
+                Arc::clone(&self.completed_tasks),
+                logs.clone(),
+            ));
+            self.background
+                .loops
+                .lock()
+                .await
+                .insert(id.to_string(), AgentLoop { tasks, handle });
+        }
+
+        if let Some(auto_checkpoint) = self.auto_checkpoint.clone() {
+            let timer = tokio::spawn(run_checkpoint_timer(
+                auto_checkpoint,
+                id.to_string(),
+                branch_name.clone(),
+            ));
+            self.background.checkpoint_timers.lock().await.insert(id.to_string(), timer);
+        }
+
+        if let Some(timeout) = self.agent_timeout {
+            let timer = tokio::spawn(run_timeout_timer(
+                id.to_string(),
+                timeout,
+                Arc::clone(&self.agents),
+                self.background.clone(),
+                self.containers.clone(),
+            ));
+            self.background.timeout_timers.lock().await.insert(id.to_string(), timer);
+        }
+
+        if let Some(manager) = self.containers.clone() {
+            let agents = Arc::clone(&self.agents);
+            let agent_id = id.to_string();
+            let container_id = branch_name.clone();
+
+            let handle = tokio::spawn(async move {
+                let result = manager
+                    .run_in_container_with_logs(&branch_name, AGENT_SHELL_COMMAND, &logs)
+                    .await;
+                if let Err(e) = result {
+                    tracing::warn!("Agent '{}' encountered an error: {}", agent_id, e);
+                    if let Some(agent) = agents.lock().await.get_mut(&agent_id) {
+                        agent.status = AgentStatus::Error(e.to_string());
+                    }
+                }
+            });
+
+            self.background
+                .tasks
+                .lock()
+                .await
+                .insert(id.to_string(), AgentTask { container_id, handle });
+        }
+    }
+
+    /// Queue `task` for the agent's behavior. Tasks are handled one at a
+    /// time, in the order they were sent. Fails unless the agent was launched
+    /// and is still running.
+    pub async fn send_task(&self, id: &str, task: impl Into<String>) -> Result<()> {
+        let agent = self.get_agent(id).await?;
+        let not_running = || {
+            Error::Service(format!(
+                "Agent '{}' is not running (status: {:?}); restart it to send it tasks",
+                id, agent.status
+            ))
+        };
+        if matches!(agent.status, AgentStatus::Stopped | AgentStatus::Error(_)) {
+            return Err(not_running());
+        }
+
+        let loops = self.background.loops.lock().await;
+        let agent_loop = loops.get(id).ok_or_else(not_running)?;
+        agent_loop.tasks.send(task.into()).map_err(|_| not_running())
+    }
+
+    /// Register a pooled worker agent with the default persona. It starts
+    /// out `Idle` until work is assigned to it.
+    pub async fn register_agent(&self, id: String) -> Result<()> {
+        self.register_agent_with_persona(id, "default").await
+    }
+
+    /// Register an idle pooled worker agent with the given persona
+    pub async fn register_agent_with_persona(&self, id: String, persona: &str) -> Result<()> {
+        self.insert_agent(&id, persona, AgentStatus::Idle, HashMap::new()).await?;
+        Ok(())
+    }
+
+    /// Re-add an agent recorded in a swarm snapshot, keeping its branch and
+    /// status. Agents that were running are launched again on their branch,
+    /// which reattaches to their `container-use` environment if it still exists.
+    pub async fn restore_agent(&self, agent: Agent) -> Result<()> {
+        let running = matches!(agent.status, AgentStatus::Running | AgentStatus::Busy);
+        let id = agent.id.clone();
+
+        self.adopt_agent(agent).await?;
+        if running {
+            self.resume(&id).await?;
         }
+        Ok(())
     }
 
-    pub async fn spawn(&mut self, id: &str, persona: &str) -> Result<()> {
+    /// Add an agent taken from elsewhere as is, keeping its persona, branch,
+    /// labels and status. Nothing is launched; see `resume`.
+    pub async fn adopt_agent(&self, agent: Agent) -> Result<()> {
         let mut agents = self.agents.lock().await;
-        
+        if agents.contains_key(&agent.id) {
+            return Err(Error::Service(format!("Agent with id '{}' already exists", agent.id)));
+        }
+        agents.insert(agent.id.clone(), agent);
+        Ok(())
+    }
+
+    /// Launch an adopted agent on its branch without changing its status.
+    /// Does nothing if the agent's message loop is already running.
+    pub async fn resume(&self, id: &str) -> Result<()> {
+        let agent = self.get_agent(id).await?;
+        if !self.is_launched(id).await {
+            self.launch(id, agent.branch_name).await;
+        }
+        Ok(())
+    }
+
+    /// Whether agent `id` has a message loop taking tasks
+    pub async fn is_launched(&self, id: &str) -> bool {
+        self.background.loops.lock().await.contains_key(id)
+    }
+
+    /// Add a new agent and return its branch name
+    async fn insert_agent(
+        &self,
+        id: &str,
+        persona: &str,
+        status: AgentStatus,
+        labels: HashMap<String, String>,
+    ) -> Result<String> {
+        let mut agents = self.agents.lock().await;
+
         if agents.contains_key(id) {
-            return Err(anyhow::anyhow!("Agent with id '{}' already exists", id));
+            return Err(Error::Service(format!("Agent with id '{}' already exists", id)));
         }
+        self.check_agent_limit(&agents, 1)?;
 
+        let branch_name = format!("agent-{}", id);
         let agent = Agent {
             id: id.to_string(),
             persona: persona.to_string(),
-            status: AgentStatus::Running,
-            branch_name: format!("agent-{}", id),
+            status,
+            branch_name: branch_name.clone(),
+            labels,
         };
 
         agents.insert(id.to_string(), agent);
+        Ok(branch_name)
+    }
+
+    /// Fail if adding `new` agents would take the active count past `max_agents`
+    fn check_agent_limit(&self, agents: &HashMap<String, Agent>, new: usize) -> Result<()> {
+        let Some(max_agents) = self.max_agents else {
+            return Ok(());
+        };
+
+        let active = agents.values().filter(|a| a.status != AgentStatus::Stopped).count();
+        if active + new > max_agents {
+            return Err(Error::Service(format!(
+                "Agent limit of {} reached ({} active, {} requested); stop an agent first",
+                max_agents, active, new
+            )));
+        }
+        Ok(())
+    }
+
+    /// Remove an agent from the supervisor entirely. Like `stop`, its
+    /// message loop, timers and container are torn down first; if that
+    /// fails the agent is kept.
+    pub async fn unregister_agent(&self, id: &str) -> Result<()> {
+        self.get_agent(id).await?;
+        self.teardown(id).await?;
+
+        self.agents.lock().await.remove(id).ok_or_else(|| agent_not_found(id))?;
+        self.logs.lock().await.remove(id);
         Ok(())
     }
 
@@ -54,35 +539,286 @@ impl AgentSupervisor {
         agents.values().cloned().collect()
     }
 
-    pub async fn stop(&mut self, id: &str) -> Result<()> {
+    /// Agents whose label `key` is set to `value`
+    pub async fn list_by_label(&self, key: &str, value: &str) -> Vec<Agent> {
+        let agents = self.agents.lock().await;
+        agents
+            .values()
+            .filter(|a| a.labels.get(key).is_some_and(|v| v == value))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn get_agent(&self, id: &str) -> Result<Agent> {
+        let agents = self.agents.lock().await;
+        agents.get(id).cloned().ok_or_else(|| agent_not_found(id))
+    }
+
+    /// Stop an agent, tearing down its container if it has one. If teardown
+    /// fails the agent is marked `Error` so the leftover container is visible.
+    #[instrument(name = "agent.stop", skip(self))]
+    pub async fn stop(&self, id: &str) -> Result<()> {
+        // Fail early for unknown agents before touching any container
+        self.get_agent(id).await?;
+
+        if let Err(e) = self.teardown(id).await {
+            self.update_agent_status(id, AgentStatus::Error(e.to_string())).await?;
+            return Err(e);
+        }
+
+        self.update_agent_status(id, AgentStatus::Stopped).await
+    }
+
+    /// Abort an agent's background task and timers and stop its container,
+    /// if any
+    async fn teardown(&self, id: &str) -> Result<()> {
+        self.background.teardown(id, self.containers.as_ref()).await
+    }
+
+    pub async fn update_agent_status(&self, id: &str, status: AgentStatus) -> Result<()> {
         let mut agents = self.agents.lock().await;
-        
-        let agent = agents.get_mut(id)
-            .context(format!("Agent '{}' not found", id))?;
-        
-        agent.status = AgentStatus::Stopped;
+
+        let agent = agents.get_mut(id).ok_or_else(|| agent_not_found(id))?;
+
+        if agent.status == AgentStatus::Busy && status == AgentStatus::Idle {
+            self.completed_tasks.fetch_add(1, Ordering::SeqCst);
+        }
+        agent.status = status;
         Ok(())
     }
 
+    /// Recent output lines of an agent, oldest first: its container's output
+    /// and the results of its tasks. Agents that were never launched have no
+    /// output.
+    pub async fn get_logs(&self, id: &str) -> Result<Vec<String>> {
+        self.get_agent(id).await?;
+
+        Ok(self
+            .logs
+            .lock()
+            .await
+            .get(id)
+            .map(LogBuffer::lines)
+            .unwrap_or_default())
+    }
+
     pub async fn get_status(&self, id: &str) -> Result<AgentStatus> {
+        Ok(self.get_agent(id).await?.status)
+    }
+
+    pub async fn health_check(&self) -> Result<SupervisorHealth> {
         let agents = self.agents.lock().await;
-        
-        let agent = agents.get(id)
-            .context(format!("Agent '{}' not found", id))?;
-        
-        Ok(agent.status.clone())
+
+        let running_agents = agents
+            .values()
+            .filter(|a| matches!(a.status, AgentStatus::Running | AgentStatus::Busy))
+            .count();
+        let failed_agents = agents
+            .values()
+            .filter(|a| matches!(a.status, AgentStatus::Error(_)))
+            .count();
+
+        Ok(SupervisorHealth {
+            is_healthy: failed_agents == 0,
+            total_agents: agents.len(),
+            running_agents,
+            failed_agents,
+        })
+    }
+
+    pub async fn get_stats(&self) -> SupervisorStats {
+        SupervisorStats {
+            total_tasks: self.completed_tasks.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Stop every agent and stop accepting new work. Every container is
+    /// torn down even if an earlier one fails; the first failure is returned.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.set_draining(true);
+
+        let ids: Vec<String> = self.agents.lock().await.keys().cloned().collect();
+        let mut first_error = None;
+        for id in ids {
+            if let Err(e) = self.stop(&id).await {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Stop every active agent concurrently, giving each at most `timeout`
+    /// to tear down its container. Agents that fail or run out of time have
+    /// their task aborted and are marked `Error`, since their container may
+    /// still be running.
+    pub async fn shutdown_with_timeout(&self, timeout: Duration) -> ShutdownReport {
+        self.set_draining(true);
+
+        let mut ids: Vec<String> = self
+            .agents
+            .lock()
+            .await
+            .values()
+            .filter(|a| a.status != AgentStatus::Stopped)
+            .map(|a| a.id.clone())
+            .collect();
+        ids.sort();
+
+        let results = futures::future::join_all(
+            ids.iter().map(|id| tokio::time::timeout(timeout, self.stop(id))),
+        )
+        .await;
+
+        let mut report = ShutdownReport::default();
+        for (id, result) in ids.into_iter().zip(results) {
+            match result {
+                Ok(Ok(())) => report.stopped.push(id),
+                Ok(Err(_)) => report.forced.push(id),
+                Err(_) => {
+                    // The timed-out stop was dropped mid-teardown; make sure
+                    // the task is gone and the agent doesn't look healthy
+                    if let Some(task) = self.background.tasks.lock().await.remove(&id) {
+                        task.handle.abort();
+                    }
+                    let message = format!("Container teardown timed out after {:?}", timeout);
+                    let _ = self.update_agent_status(&id, AgentStatus::Error(message)).await;
+                    report.forced.push(id);
+                }
+            }
+        }
+        report
     }
 }
 
+/// Hand the tasks sent to an agent to its behavior one at a time, in order.
+/// An active agent is `Busy` while a task runs and `Idle` once it is done.
+/// Task output goes to the agent's logs; a failed task is logged and the
+/// loop moves on to the next one.
+async fn run_message_loop(
+    agent_id: String,
+    mut behavior: Box<dyn AgentBehavior>,
+    mut tasks: mpsc::UnboundedReceiver<String>,
+    agents: Arc<Mutex<HashMap<String, Agent>>>,
+    completed_tasks: Arc<AtomicUsize>,
+    logs: LogBuffer,
+) {
+    while let Some(task) = tasks.recv().await {
+        if let Some(agent) = agents.lock().await.get_mut(&agent_id) {
+            if matches!(agent.status, AgentStatus::Running | AgentStatus::Idle) {
+                agent.status = AgentStatus::Busy;
+            }
+        }
+
+        match behavior.on_task(task).await {
+            Ok(output) => output.lines().for_each(|line| logs.push(line)),
+            Err(e) => {
+                warn!("Agent '{}' failed a task: {}", agent_id, e);
+                logs.push(&format!("Task failed: {}", e));
+            }
+        }
+
+        if let Some(agent) = agents.lock().await.get_mut(&agent_id) {
+            if agent.status == AgentStatus::Busy {
+                agent.status = AgentStatus::Idle;
+                completed_tasks.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Save a checkpoint of `branch_name` every interval until aborted. A failed
+/// checkpoint is logged and the next one is attempted as usual.
+async fn run_checkpoint_timer(auto_checkpoint: AutoCheckpoint, agent_id: String, branch_name: String) {
+    let AutoCheckpoint { manager, interval } = auto_checkpoint;
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticks.tick().await;
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let message = format!("auto-checkpoint {}", timestamp);
+        let manager = manager.clone();
+        let branch = branch_name.clone();
+
+        match tokio::task::spawn_blocking(move || manager.save_checkpoint(&branch, &message)).await {
+            Ok(Ok(tag)) => debug!("Saved checkpoint '{}' for agent '{}'", tag, agent_id),
+            Ok(Err(e)) => warn!("Auto-checkpoint of agent '{}' failed: {}", agent_id, e),
+            Err(e) => warn!("Auto-checkpoint of agent '{}' panicked: {}", agent_id, e),
+        }
+    }
+}
+
+/// How often agent timeout timers look at the agent's status
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stop the agent once it has been `Running` or `Busy` for `timeout`
+/// without a break. Its status is checked every [`TIMEOUT_CHECK_INTERVAL`],
+/// or every `timeout` if that is shorter, and the clock restarts whenever
+/// the agent is seen in any other state. Ends when the agent is stopped or
+/// removed. A failed teardown leaves the agent in the `Error` state.
+async fn run_timeout_timer(
+    agent_id: String,
+    timeout: Duration,
+    agents: Arc<Mutex<HashMap<String, Agent>>>,
+    background: Background,
+    containers: Option<ContainerManager>,
+) {
+    let interval = timeout.min(TIMEOUT_CHECK_INTERVAL);
+    // Agents are launched `Running`
+    let mut active_since = Some(Instant::now());
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let status = agents.lock().await.get(&agent_id).map(|a| a.status.clone());
+        match status {
+            Some(AgentStatus::Running | AgentStatus::Busy) => {
+                if active_since.get_or_insert_with(Instant::now).elapsed() >= timeout {
+                    break;
+                }
+            }
+            Some(AgentStatus::Stopped) | None => return,
+            Some(_) => active_since = None,
+        }
+    }
+
+    // Forget this timer first so the teardown doesn't abort it midway
+    background.timeout_timers.lock().await.remove(&agent_id);
+    warn!("Agent '{}' exceeded its {:?} timeout; stopping it", agent_id, timeout);
+    let status = match background.teardown(&agent_id, containers.as_ref()).await {
+        Ok(()) => AgentStatus::Stopped,
+        Err(e) => AgentStatus::Error(e.to_string()),
+    };
+    if let Some(agent) = agents.lock().await.get_mut(&agent_id) {
+        agent.status = status;
+    }
+}
+
+fn agent_not_found(id: &str) -> Error {
+    Error::Service(format!("Agent '{}' not found", id))
+}
+
+/// Supervisor for real use: spawned agents run in `container-use`
+/// environments through `ContainerManager::new()`, otherwise the same as
+/// `new`. Personas need no validator here; they are validated when
+/// `personas::load_personas` reads them.
 impl Default for AgentSupervisor {
     fn default() -> Self {
-        Self::new()
+        Self::new().with_container_manager(ContainerManager::new())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::container::tests::MockExecutor;
+    use crate::container::{CommandExecutor, CommandOutput};
+    use crate::git::{CheckpointOptions, MockGitOperations};
 
     #[tokio::test]
     async fn test_supervisor_new() {
@@ -93,7 +829,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_spawn_agent() {
-        let mut supervisor = AgentSupervisor::new();
+        let supervisor = AgentSupervisor::new();
         let result = supervisor.spawn("test-agent", "rusty").await;
         assert!(result.is_ok());
 
@@ -105,7 +841,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_spawn_duplicate_agent() {
-        let mut supervisor = AgentSupervisor::new();
+        let supervisor = AgentSupervisor::new();
         supervisor.spawn("test-agent", "rusty").await.unwrap();
         
         let result = supervisor.spawn("test-agent", "pythonic").await;
@@ -114,7 +850,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_stop_agent() {
-        let mut supervisor = AgentSupervisor::new();
+        let supervisor = AgentSupervisor::new();
         supervisor.spawn("test-agent", "rusty").await.unwrap();
         
         let result = supervisor.stop("test-agent").await;
@@ -126,7 +862,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_status() {
-        let mut supervisor = AgentSupervisor::new();
+        let supervisor = AgentSupervisor::new();
         supervisor.spawn("test-agent", "rusty").await.unwrap();
         
         let status = supervisor.get_status("test-agent").await.unwrap();
@@ -146,7 +882,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_spawn_multiple_agents() {
-        let mut supervisor = AgentSupervisor::new();
+        let supervisor = AgentSupervisor::new();
         
         supervisor.spawn("agent1", "rusty").await.unwrap();
         supervisor.spawn("agent2", "pythonic").await.unwrap();
@@ -163,9 +899,47 @@ mod tests {
         assert!(matches!(agent2.status, AgentStatus::Running));
     }
 
+    #[tokio::test]
+    async fn test_spawn_pool() {
+        let supervisor = AgentSupervisor::new();
+        let ids = supervisor.spawn_pool("worker", "rusty", 3).await.unwrap();
+        assert_eq!(ids, vec!["worker-1", "worker-2", "worker-3"]);
+
+        let mut branches: Vec<String> = supervisor.list().await.into_iter().map(|a| a.branch_name).collect();
+        branches.sort();
+        branches.dedup();
+        assert_eq!(branches, vec!["agent-worker-1", "agent-worker-2", "agent-worker-3"]);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_pool_stops_on_existing_id() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("worker-2", "rusty").await.unwrap();
+
+        let err = supervisor.spawn_pool("worker", "rusty", 3).await.unwrap_err();
+        assert!(err.to_string().contains("'worker-2' already exists"));
+        assert_eq!(supervisor.list().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_by_label() {
+        let supervisor = AgentSupervisor::new();
+        let labels = |project: &str| HashMap::from([("project".to_string(), project.to_string())]);
+        supervisor.spawn_pool_with_labels("web", "rusty", 2, labels("web")).await.unwrap();
+        supervisor.spawn_with_labels("api", "rusty", labels("api")).await.unwrap();
+        supervisor.spawn("plain", "rusty").await.unwrap();
+
+        let mut ids: Vec<String> = supervisor.list_by_label("project", "web").await.into_iter().map(|a| a.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["web-1", "web-2"]);
+        assert_eq!(supervisor.list_by_label("project", "api").await[0].id, "api");
+        assert!(supervisor.list_by_label("owner", "web").await.is_empty());
+        assert!(supervisor.get_agent("plain").await.unwrap().labels.is_empty());
+    }
+
     #[tokio::test]
     async fn test_stop_nonexistent_agent() {
-        let mut supervisor = AgentSupervisor::new();
+        let supervisor = AgentSupervisor::new();
         let result = supervisor.stop("nonexistent").await;
         assert!(result.is_err());
     }
@@ -185,20 +959,47 @@ mod tests {
         assert!(error_json.contains("test error"));
     }
 
+    #[tokio::test]
+    async fn test_accepting_work_by_default() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("agent1", "rusty").await.unwrap();
+        assert!(supervisor.accepting_work().await);
+    }
+
+    #[tokio::test]
+    async fn test_not_accepting_work_at_capacity() {
+        let supervisor = AgentSupervisor::with_capacity(2);
+        supervisor.spawn("agent1", "rusty").await.unwrap();
+        assert!(supervisor.accepting_work().await);
+
+        supervisor.spawn("agent2", "rusty").await.unwrap();
+        assert!(!supervisor.accepting_work().await);
+
+        // Stopped agents no longer count against capacity
+        supervisor.stop("agent1").await.unwrap();
+        assert!(supervisor.accepting_work().await);
+    }
+
+    #[tokio::test]
+    async fn test_not_accepting_work_while_draining() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.set_draining(true);
+        assert!(!supervisor.accepting_work().await);
+
+        supervisor.set_draining(false);
+        assert!(supervisor.accepting_work().await);
+    }
+
     #[tokio::test]
     async fn test_concurrent_agent_operations() {
-        use std::sync::Arc;
-        use tokio::sync::Mutex;
-        
-        let supervisor = Arc::new(Mutex::new(AgentSupervisor::new()));
+        let supervisor = Arc::new(AgentSupervisor::new());
         let mut handles = vec![];
         
         // Spawn 10 agents concurrently
         for i in 0..10 {
             let supervisor = supervisor.clone();
             let handle = tokio::spawn(async move {
-                let mut sup = supervisor.lock().await;
-                sup.spawn(&format!("agent{}", i), "rusty").await
+                supervisor.spawn(&format!("agent{}", i), "rusty").await
             });
             handles.push(handle);
         }
@@ -208,8 +1009,631 @@ mod tests {
             handle.await.unwrap().unwrap();
         }
         
-        let supervisor = supervisor.lock().await;
         let agents = supervisor.list().await;
         assert_eq!(agents.len(), 10);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_register_and_unregister_agent() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.register_agent("worker".to_string()).await.unwrap();
+
+        let agent = supervisor.get_agent("worker").await.unwrap();
+        assert_eq!(agent.status, AgentStatus::Idle);
+        assert_eq!(agent.persona, "default");
+
+        supervisor.unregister_agent("worker").await.unwrap();
+        assert!(supervisor.get_agent("worker").await.is_err());
+        assert!(supervisor.unregister_agent("worker").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_counts_failed_agents() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("agent1", "rusty").await.unwrap();
+        supervisor.register_agent("agent2".to_string()).await.unwrap();
+
+        let health = supervisor.health_check().await.unwrap();
+        assert!(health.is_healthy);
+        assert_eq!(health.total_agents, 2);
+        assert_eq!(health.running_agents, 1);
+
+        supervisor
+            .update_agent_status("agent2", AgentStatus::Error("crashed".to_string()))
+            .await
+            .unwrap();
+        let health = supervisor.health_check().await.unwrap();
+        assert!(!health.is_healthy);
+        assert_eq!(health.failed_agents, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_completed_tasks() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.register_agent("worker".to_string()).await.unwrap();
+
+        for _ in 0..2 {
+            supervisor.update_agent_status("worker", AgentStatus::Busy).await.unwrap();
+            supervisor.update_agent_status("worker", AgentStatus::Idle).await.unwrap();
+        }
+        // Idle -> Idle is not a completed task
+        supervisor.update_agent_status("worker", AgentStatus::Idle).await.unwrap();
+
+        assert_eq!(supervisor.get_stats().await.total_tasks, 2);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_agents_and_drains() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("agent1", "rusty").await.unwrap();
+
+        supervisor.shutdown().await.unwrap();
+
+        assert_eq!(supervisor.get_status("agent1").await.unwrap(), AgentStatus::Stopped);
+        assert!(!supervisor.accepting_work().await);
+    }
+
+    fn containerized(executor: Arc<MockExecutor>) -> AgentSupervisor {
+        AgentSupervisor::new().with_container_manager(ContainerManager::with_executor(executor))
+    }
+
+    #[tokio::test]
+    async fn test_stop_tears_down_container() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone());
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+
+        supervisor.stop("test-agent").await.unwrap();
+
+        assert!(executor
+            .calls()
+            .contains(&vec!["cu", "environment", "stop", "--branch", "agent-test-agent"]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()));
+        assert_eq!(supervisor.get_status("test-agent").await.unwrap(), AgentStatus::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_unregister_tears_down_agent() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone());
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+        assert!(supervisor.is_launched("test-agent").await);
+
+        supervisor.unregister_agent("test-agent").await.unwrap();
+
+        assert!(!supervisor.is_launched("test-agent").await);
+        assert!(executor
+            .calls()
+            .contains(&vec!["cu", "environment", "stop", "--branch", "agent-test-agent"]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()));
+        assert!(supervisor.get_agent("test-agent").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stop_marks_error_when_teardown_fails() {
+        let executor = Arc::new(MockExecutor::failing_on(&["stop"]));
+        let supervisor = containerized(executor);
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+
+        assert!(supervisor.stop("test-agent").await.is_err());
+        assert!(matches!(
+            supervisor.get_status("test-agent").await.unwrap(),
+            AgentStatus::Error(msg) if msg.contains("mock failure")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stop_without_container_issues_no_commands() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone());
+        supervisor.register_agent("worker".to_string()).await.unwrap();
+
+        supervisor.stop("worker").await.unwrap();
+        assert!(executor.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_agent_status_reflects_container_failure() {
+        let executor = Arc::new(MockExecutor::failing_on(&["open"]));
+        let supervisor = containerized(executor);
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+
+        let status = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let status = supervisor.get_status("test-agent").await.unwrap();
+                if status != AgentStatus::Running {
+                    return status;
+                }
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("agent status never left Running");
+
+        assert!(matches!(status, AgentStatus::Error(msg) if msg.contains("mock failure")));
+    }
+
+    #[tokio::test]
+    async fn test_get_logs_returns_container_output() {
+        let executor = Arc::new(MockExecutor {
+            stdout: "Agent started. Waiting for tasks...\n".to_string(),
+            ..MockExecutor::default()
+        });
+        let supervisor = containerized(executor);
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+
+        let logs = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let logs = supervisor.get_logs("test-agent").await.unwrap();
+                if !logs.is_empty() {
+                    return logs;
+                }
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("agent produced no logs");
+
+        assert_eq!(logs, vec!["Agent started. Waiting for tasks..."]);
+        assert!(supervisor.get_logs("nonexistent").await.is_err());
+    }
+
+    /// Behaves as if `cu` is not installed
+    struct MissingCuExecutor;
+
+    #[async_trait::async_trait]
+    impl CommandExecutor for MissingCuExecutor {
+        async fn execute(&self, program: &str, _args: &[String]) -> Result<CommandOutput> {
+            Err(Error::Service(format!("Failed to execute '{}': No such file or directory", program)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_fails_early_without_container_runtime() {
+        let supervisor = AgentSupervisor::new()
+            .with_container_manager(ContainerManager::with_executor(Arc::new(MissingCuExecutor)));
+
+        let err = supervisor.spawn("test-agent", "rusty").await.unwrap_err();
+
+        assert!(err.to_string().contains("container-use not found"));
+        assert!(supervisor.list().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_container_runtime_checked_once() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone());
+        supervisor.spawn("agent1", "rusty").await.unwrap();
+        supervisor.spawn("agent2", "rusty").await.unwrap();
+
+        let version_checks = executor
+            .calls()
+            .iter()
+            .filter(|call| call.get(1).map(String::as_str) == Some("--version"))
+            .count();
+        assert_eq!(version_checks, 1);
+    }
+
+    /// Never finishes tearing down a container
+    struct HangingExecutor;
+
+    #[async_trait::async_trait]
+    impl CommandExecutor for HangingExecutor {
+        async fn execute(&self, _program: &str, args: &[String]) -> Result<CommandOutput> {
+            if args.get(1).map(String::as_str) == Some("stop") {
+                std::future::pending::<()>().await;
+            }
+            Ok(CommandOutput { success: true, ..CommandOutput::default() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_with_timeout_stops_agents() {
+        let supervisor = containerized(Arc::new(MockExecutor::default()));
+        supervisor.spawn("agent1", "rusty").await.unwrap();
+        supervisor.spawn("agent2", "rusty").await.unwrap();
+        supervisor.register_agent("idle".to_string()).await.unwrap();
+        supervisor.stop("idle").await.unwrap();
+
+        let report = supervisor.shutdown_with_timeout(Duration::from_secs(5)).await;
+
+        // Already-stopped agents are not reported
+        assert_eq!(report.stopped, vec!["agent1", "agent2"]);
+        assert!(report.forced.is_empty());
+        assert!(!supervisor.accepting_work().await);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_with_timeout_forces_stuck_agents() {
+        let supervisor = AgentSupervisor::new()
+            .with_container_manager(ContainerManager::with_executor(Arc::new(HangingExecutor)));
+        supervisor.spawn("stuck", "rusty").await.unwrap();
+
+        let report = supervisor.shutdown_with_timeout(Duration::from_millis(50)).await;
+
+        assert!(report.stopped.is_empty());
+        assert_eq!(report.forced, vec!["stuck"]);
+        assert!(matches!(
+            supervisor.get_status("stuck").await.unwrap(),
+            AgentStatus::Error(msg) if msg.contains("timed out")
+        ));
+    }
+
+    fn open_calls(executor: &MockExecutor) -> usize {
+        executor
+            .calls()
+            .iter()
+            .filter(|call| call.get(2).map(String::as_str) == Some("open"))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_restart_running_agent() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone());
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+        tokio::task::yield_now().await;
+
+        supervisor.restart("test-agent").await.unwrap();
+        tokio::task::yield_now().await;
+
+        let agent = supervisor.get_agent("test-agent").await.unwrap();
+        assert_eq!(agent.status, AgentStatus::Running);
+        assert_eq!(agent.persona, "rusty");
+        assert_eq!(agent.branch_name, "agent-test-agent");
+
+        let stop = vec!["cu", "environment", "stop", "--branch", "agent-test-agent"];
+        assert!(executor.calls().contains(&stop.into_iter().map(String::from).collect()));
+        assert_eq!(open_calls(&executor), 2);
+    }
+
+    #[tokio::test]
+    async fn test_restore_agent_reattaches_running_containers() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone());
+        let agent = |id: &str, status| Agent {
+            id: id.to_string(),
+            persona: "rusty".to_string(),
+            status,
+            branch_name: format!("agent/{}", id),
+            labels: HashMap::new(),
+        };
+
+        supervisor.restore_agent(agent("running", AgentStatus::Running)).await.unwrap();
+        supervisor.restore_agent(agent("stopped", AgentStatus::Stopped)).await.unwrap();
+        tokio::task::yield_now().await;
+
+        // Only the running agent reopens its environment, on its recorded branch
+        assert_eq!(open_calls(&executor), 1);
+        assert_eq!(executor.calls()[0][4], "agent/running");
+        assert_eq!(supervisor.get_agent("stopped").await.unwrap().status, AgentStatus::Stopped);
+        assert!(supervisor.restore_agent(agent("running", AgentStatus::Idle)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restart_stopped_agent() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone());
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+        supervisor.stop("test-agent").await.unwrap();
+
+        supervisor.restart("test-agent").await.unwrap();
+        tokio::task::yield_now().await;
+
+        assert_eq!(supervisor.get_status("test-agent").await.unwrap(), AgentStatus::Running);
+        assert_eq!(open_calls(&executor), 1);
+    }
+
+    #[tokio::test]
+    async fn test_restart_errored_agent() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+        supervisor
+            .update_agent_status("test-agent", AgentStatus::Error("crashed".to_string()))
+            .await
+            .unwrap();
+
+        supervisor.restart("test-agent").await.unwrap();
+
+        assert_eq!(supervisor.get_status("test-agent").await.unwrap(), AgentStatus::Running);
+        assert!(supervisor.health_check().await.unwrap().is_healthy);
+        assert!(supervisor.restart("nonexistent").await.is_err());
+    }
+
+    /// A checkpoint manager whose saves are counted, and fail when `fail` is set
+    fn counting_checkpoints(fail: bool) -> (GitCheckpointManager, Arc<AtomicUsize>) {
+        let saves = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&saves);
+
+        let mut git = MockGitOperations::new();
+        git.expect_save_checkpoint()
+            .withf(|_, branch, message, options| {
+                branch == "agent-test-agent"
+                    && message.starts_with("auto-checkpoint ")
+                    && *options == CheckpointOptions::default()
+            })
+            .returning(move |_, _, _, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                if fail {
+                    Err(Error::Other("repository is locked".to_string()))
+                } else {
+                    Ok("cp/test-agent/1".to_string())
+                }
+            });
+
+        (GitCheckpointManager::with_operations(Arc::new(git), "/repo"), saves)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_auto_checkpoint_fires_on_schedule() {
+        let (manager, saves) = counting_checkpoints(false);
+        let supervisor = AgentSupervisor::new().with_auto_checkpoint(manager, Duration::from_secs(10));
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(35)).await;
+        assert_eq!(saves.load(Ordering::SeqCst), 3);
+
+        // The timer stops with the agent
+        supervisor.stop("test-agent").await.unwrap();
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(saves.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_auto_checkpoint_failure_keeps_agent_running() {
+        let (manager, saves) = counting_checkpoints(true);
+        let supervisor = AgentSupervisor::new().with_auto_checkpoint(manager, Duration::from_secs(10));
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(25)).await;
+
+        assert_eq!(saves.load(Ordering::SeqCst), 2);
+        assert_eq!(supervisor.get_status("test-agent").await.unwrap(), AgentStatus::Running);
+    }
+
+    #[tokio::test]
+    async fn test_no_auto_checkpoint_by_default() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("test-agent", "rusty").await.unwrap();
+        assert!(supervisor.background.checkpoint_timers.lock().await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_agent_timeout_stops_running_agent() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = containerized(executor.clone()).with_agent_timeout(Duration::from_secs(60));
+        supervisor.spawn("slow", "rusty").await.unwrap();
+        supervisor.spawn("quick", "rusty").await.unwrap();
+        supervisor.stop("quick").await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(59)).await;
+        assert_eq!(supervisor.get_status("slow").await.unwrap(), AgentStatus::Running);
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(supervisor.get_status("slow").await.unwrap(), AgentStatus::Stopped);
+        let stop = vec!["cu", "environment", "stop", "--branch", "agent-slow"];
+        assert!(executor.calls().contains(&stop.into_iter().map(String::from).collect()));
+        assert!(supervisor.background.timeout_timers.lock().await.is_empty());
+        assert!(supervisor.background.tasks.lock().await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_agent_timeout_skips_idle_agents() {
+        let supervisor = AgentSupervisor::new().with_agent_timeout(Duration::from_secs(60));
+        supervisor.spawn("worker", "rusty").await.unwrap();
+        supervisor.update_agent_status("worker", AgentStatus::Idle).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(120)).await;
+        assert_eq!(supervisor.get_status("worker").await.unwrap(), AgentStatus::Idle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_agent_timeout_counts_from_becoming_busy() {
+        let supervisor = AgentSupervisor::new().with_agent_timeout(Duration::from_secs(60));
+        supervisor.spawn("worker", "rusty").await.unwrap();
+        supervisor.update_agent_status("worker", AgentStatus::Idle).await.unwrap();
+
+        // Idle past the deadline, then picks up work
+        tokio::time::sleep(Duration::from_secs(90)).await;
+        supervisor.update_agent_status("worker", AgentStatus::Busy).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(58)).await;
+        assert_eq!(supervisor.get_status("worker").await.unwrap(), AgentStatus::Busy);
+
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert_eq!(supervisor.get_status("worker").await.unwrap(), AgentStatus::Stopped);
+    }
+
+    /// Records each task it receives, failing the ones that say "fail"
+    struct RecordingBehavior {
+        seen: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentBehavior for RecordingBehavior {
+        async fn on_task(&mut self, task: String) -> Result<String> {
+            self.seen.lock().unwrap().push(task.clone());
+            if task == "fail" {
+                return Err(Error::Other("task refused".into()));
+            }
+            Ok(format!("done {}", task))
+        }
+    }
+
+    async fn wait_for_tasks(supervisor: &AgentSupervisor, count: usize) {
+        while supervisor.get_stats().await.total_tasks < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_message_loop_handles_tasks_in_order() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        let supervisor = AgentSupervisor::new().with_behavior(move |_| {
+            Box::new(RecordingBehavior {
+                seen: Arc::clone(&recorded),
+            })
+        });
+        supervisor.spawn("worker", "rusty").await.unwrap();
+
+        for task in ["first", "fail", "second"] {
+            supervisor.send_task("worker", task).await.unwrap();
+        }
+        wait_for_tasks(&supervisor, 3).await;
+
+        assert_eq!(*seen.lock().unwrap(), vec!["first", "fail", "second"]);
+        assert_eq!(
+            supervisor.get_logs("worker").await.unwrap(),
+            vec!["done first", "Task failed: Error: task refused", "done second"]
+        );
+        assert_eq!(supervisor.get_status("worker").await.unwrap(), AgentStatus::Idle);
+    }
+
+    #[tokio::test]
+    async fn test_agents_echo_tasks_by_default() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("worker", "rusty").await.unwrap();
+
+        supervisor.send_task("worker", "hello\nworld").await.unwrap();
+        wait_for_tasks(&supervisor, 1).await;
+
+        assert_eq!(supervisor.get_logs("worker").await.unwrap(), vec!["hello", "world"]);
+    }
+
+    #[tokio::test]
+    async fn test_stop_ends_message_loop() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("worker", "rusty").await.unwrap();
+        let tasks = supervisor.background.loops.lock().await["worker"].tasks.clone();
+
+        supervisor.stop("worker").await.unwrap();
+        tasks.closed().await;
+        assert_eq!(supervisor.get_status("worker").await.unwrap(), AgentStatus::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_send_task_reaches_behavior() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        let supervisor = AgentSupervisor::new().with_behavior(move |agent| {
+            assert_eq!(agent.persona, "rusty");
+            Box::new(RecordingBehavior {
+                seen: Arc::clone(&recorded),
+            })
+        });
+        supervisor.spawn("worker", "rusty").await.unwrap();
+
+        supervisor.send_task("worker", "refactor main.rs").await.unwrap();
+        wait_for_tasks(&supervisor, 1).await;
+        assert_eq!(*seen.lock().unwrap(), vec!["refactor main.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_send_task_requires_running_agent() {
+        let supervisor = AgentSupervisor::new();
+        supervisor.spawn("stopped", "rusty").await.unwrap();
+        supervisor.stop("stopped").await.unwrap();
+        // Pooled workers are never launched, so nothing would handle the task
+        supervisor.register_agent("pooled".to_string()).await.unwrap();
+
+        let err = supervisor.send_task("stopped", "work").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Service error: Agent 'stopped' is not running (status: Stopped); restart it to send it tasks"
+        );
+        assert!(supervisor.send_task("pooled", "work").await.is_err());
+        let err = supervisor.send_task("ghost", "work").await.unwrap_err();
+        assert!(err.to_string().contains("'ghost' not found"));
+
+        // Restarting brings the message loop back
+        supervisor.restart("stopped").await.unwrap();
+        assert!(supervisor.send_task("stopped", "work").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_respects_max_agents() {
+        let supervisor = AgentSupervisor::new().with_max_agents(2);
+        supervisor.spawn("a", "rusty").await.unwrap();
+        supervisor.spawn("b", "rusty").await.unwrap();
+
+        let err = supervisor.spawn("c", "rusty").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Service error: Agent limit of 2 reached (2 active, 1 requested); stop an agent first"
+        );
+        assert!(supervisor.get_agent("c").await.is_err());
+
+        // Stopped agents don't count
+        supervisor.stop("a").await.unwrap();
+        supervisor.spawn("c", "rusty").await.unwrap();
+        assert_eq!(supervisor.list().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_pool_respects_max_agents() {
+        let supervisor = AgentSupervisor::new().with_max_agents(3);
+        supervisor.spawn("solo", "rusty").await.unwrap();
+
+        // The whole pool is refused rather than partially spawned
+        assert!(supervisor.spawn_pool("worker", "rusty", 3).await.is_err());
+        assert_eq!(supervisor.list().await.len(), 1);
+
+        assert_eq!(supervisor.spawn_pool("worker", "rusty", 2).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_agents_limits_accepting_work_and_restart() {
+        let supervisor = AgentSupervisor::new().with_max_agents(1);
+        supervisor.spawn("a", "rusty").await.unwrap();
+        assert!(!supervisor.accepting_work().await);
+
+        supervisor.stop("a").await.unwrap();
+        assert!(supervisor.accepting_work().await);
+        supervisor.spawn("b", "rusty").await.unwrap();
+
+        // Bringing "a" back would make two active agents
+        let err = supervisor.restart("a").await.unwrap_err();
+        assert!(err.to_string().contains("Agent limit of 1 reached"), "{}", err);
+        assert_eq!(supervisor.get_agent("a").await.unwrap().status, AgentStatus::Stopped);
+
+        // A running agent can still be restarted in place
+        supervisor.restart("b").await.unwrap();
+        assert_eq!(supervisor.get_agent("b").await.unwrap().status, AgentStatus::Running);
+    }
+
+    #[test]
+    fn test_default_runs_agents_in_containers() {
+        assert!(AgentSupervisor::default().containers.is_some());
+        assert!(AgentSupervisor::new().containers.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_builder_supplies_optional_dependencies() {
+        let executor = Arc::new(MockExecutor::default());
+        let supervisor = AgentSupervisor::default()
+            .with_container_manager(ContainerManager::with_executor(executor.clone()))
+            .with_behavior(|_| {
+                Box::new(RecordingBehavior {
+                    seen: Default::default(),
+                })
+            })
+            .with_max_agents(1)
+            .with_agent_timeout(Duration::from_secs(3600));
+
+        supervisor.spawn("worker", "rusty").await.unwrap();
+        assert!(supervisor.spawn("second", "rusty").await.is_err());
+
+        supervisor.send_task("worker", "build").await.unwrap();
+        wait_for_tasks(&supervisor, 1).await;
+        assert!(supervisor.get_logs("worker").await.unwrap().contains(&"done build".to_string()));
+
+        supervisor.stop("worker").await.unwrap();
+
+        assert!(executor.calls().iter().any(|call| call.contains(&"agent-worker".to_string())));
+    }
+}
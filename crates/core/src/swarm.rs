@@ -1,17 +1,55 @@
-use crate::config::Config;
-use crate::error::{Error, Result};
-use crate::supervisor::{Supervisor, AgentStatus};
+use crate::error::{collect_results, Error, OpenCodeError, Result};
+use crate::supervisor::{Agent, AgentStatus, AgentSupervisor};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
 
 /// Swarm orchestrator that manages multiple supervisors and coordinates agent swarms
 #[derive(Debug)]
 pub struct SwarmOrchestrator {
-    config: Config,
-    supervisors: Arc<RwLock<HashMap<String, Arc<Supervisor>>>>,
+    supervisors: Arc<RwLock<HashMap<String, Arc<AgentSupervisor>>>>,
     started_at: Instant,
+    progress: broadcast::Sender<SwarmProgress>,
+    /// Creates the supervisors `restore` finds missing
+    new_supervisor: SupervisorFactory,
+}
+
+type MakeSupervisor = dyn Fn() -> AgentSupervisor + Send + Sync;
+
+#[derive(Clone)]
+struct SupervisorFactory(Arc<MakeSupervisor>);
+
+/// Supervisors backed by real containers, so restored agents reattach to
+/// their environments
+impl Default for SupervisorFactory {
+    fn default() -> Self {
+        Self(Arc::new(AgentSupervisor::default))
+    }
+}
+
+impl fmt::Debug for SupervisorFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SupervisorFactory")
+    }
+}
+
+/// Progress events a subscriber can fall behind by before missing some
+const PROGRESS_CHANNEL_CAPACITY: usize = 256;
+
+/// Task lifecycle events, published as tasks actually start and finish
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "PascalCase")]
+pub enum SwarmProgress {
+    TaskStarted { task: String, supervisor_id: String },
+    TaskCompleted { task: String, supervisor_id: String },
+    TaskFailed { task: String, supervisor_id: String, error: String },
 }
 
 #[derive(Debug, Clone)]
@@ -33,27 +71,170 @@ pub enum SwarmStatus {
     Shutdown,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct SwarmMetrics {
     pub total_supervisors: usize,
     pub total_agents: usize,
     pub active_agents: usize,
     pub failed_agents: usize,
     pub tasks_processed: usize,
+    #[serde(rename = "uptime_seconds", serialize_with = "serialize_seconds")]
     pub uptime: Duration,
-    pub memory_usage: usize,
+    /// Resident memory of this process, where the platform reports it
+    pub memory_usage_bytes: Option<u64>,
+}
+
+fn serialize_seconds<S: serde::Serializer>(duration: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+/// Resident set size of the current process. Only Linux reports it.
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Append one metric family: its HELP and TYPE lines followed by a line per
+/// sample. Each sample is its rendered label set (empty for none) and value.
+fn write_metric<'a>(out: &mut String, name: &str, kind: &str, help: &str, samples: impl IntoIterator<Item = (&'a str, f64)>) {
+    use std::fmt::Write;
+
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+}
+
+/// Escape a label value as the exposition format requires
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// An idle agent moved from one supervisor to another by `migrate_agents`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentMigration {
+    pub from_supervisor: String,
+    pub to_supervisor: String,
+    /// Id of the agent on the source supervisor
+    pub agent_id: String,
+    /// Id of the agent on the destination; differs from `agent_id` only if
+    /// the destination already had an agent with that id
+    pub new_agent_id: String,
+}
+
+/// Serializable description of every supervisor in a swarm and its
+/// agents, used to bring a swarm back after a restart
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SwarmSnapshot {
+    pub supervisors: Vec<SupervisorSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SupervisorSnapshot {
+    pub id: String,
+    pub agents: Vec<Agent>,
+}
+
+impl SwarmSnapshot {
+    /// Read a snapshot written by `save`. A missing file is an empty swarm.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| Error::Other(format!("Invalid swarm snapshot {}: {}", path.display(), e)))
+    }
+
+    /// Write the snapshot as JSON, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Other(format!("Failed to serialize swarm snapshot: {}", e)))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoManifest {
+    #[serde(default)]
+    workspace: Workspace,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct Workspace {
+    #[serde(default)]
+    members: Vec<String>,
+}
+
+/// A "plan" consisting of a series of sub-tasks
+#[derive(Debug)]
+pub struct Plan {
+    pub tasks: Vec<String>,
+}
+
+/// A simple planner that creates one task per workspace member in a Cargo.toml
+pub fn plan_build_from_manifest(manifest_path: &Path) -> Result<Plan> {
+    let content = std::fs::read_to_string(manifest_path)?;
+    let manifest: CargoManifest = toml::from_str(&content)?;
+
+    if manifest.workspace.members.is_empty() {
+        // If not a workspace, consider the root package as the single task
+        return Ok(Plan {
+            tasks: vec!["root_package".to_string()],
+        });
+    }
+
+    Ok(Plan {
+        tasks: manifest.workspace.members,
+    })
+}
+
+/// Id of the agent that works on `task`
+pub fn task_agent_id(task: &str) -> String {
+    format!("task-{}", task.replace('/', "-"))
 }
 
 impl SwarmOrchestrator {
     /// Create a new swarm orchestrator
-    pub fn new(config: Config) -> Self {
+    pub fn new() -> Self {
         Self {
-            config,
             supervisors: Arc::new(RwLock::new(HashMap::new())),
             started_at: Instant::now(),
+            progress: broadcast::channel(PROGRESS_CHANNEL_CAPACITY).0,
+            new_supervisor: SupervisorFactory::default(),
         }
     }
 
+    /// Build the supervisors `restore` creates with `factory` instead of
+    /// `AgentSupervisor::default`
+    pub fn with_supervisor_factory(mut self, factory: impl Fn() -> AgentSupervisor + Send + Sync + 'static) -> Self {
+        self.new_supervisor = SupervisorFactory(Arc::new(factory));
+        self
+    }
+
+    /// Receive task progress events from now on
+    pub fn subscribe_progress(&self) -> broadcast::Receiver<SwarmProgress> {
+        self.progress.subscribe()
+    }
+
+    fn emit(&self, event: SwarmProgress) {
+        // Nobody listening is fine; progress is informational
+        let _ = self.progress.send(event);
+    }
+
     /// Check if the swarm orchestrator is healthy
     pub async fn is_healthy(&self) -> bool {
         let supervisors = self.supervisors.read().await;
@@ -75,7 +256,8 @@ impl SwarmOrchestrator {
     }
 
     /// Add a supervisor to the swarm
-    pub async fn add_supervisor(&self, supervisor_id: String, supervisor: Arc<Supervisor>) -> Result<()> {
+    #[instrument(name = "swarm.add_supervisor", skip(self, supervisor))]
+    pub async fn add_supervisor(&self, supervisor_id: String, supervisor: Arc<AgentSupervisor>) -> Result<()> {
         let mut supervisors = self.supervisors.write().await;
         
         if supervisors.contains_key(&supervisor_id) {
@@ -87,6 +269,7 @@ impl SwarmOrchestrator {
     }
 
     /// Remove a supervisor from the swarm
+    #[instrument(name = "swarm.remove_supervisor", skip(self))]
     pub async fn remove_supervisor(&self, supervisor_id: &str) -> Result<()> {
         let mut supervisors = self.supervisors.write().await;
         
@@ -101,7 +284,7 @@ impl SwarmOrchestrator {
     }
 
     /// Get a supervisor by ID
-    pub async fn get_supervisor(&self, supervisor_id: &str) -> Result<Arc<Supervisor>> {
+    pub async fn get_supervisor(&self, supervisor_id: &str) -> Result<Arc<AgentSupervisor>> {
         let supervisors = self.supervisors.read().await;
         supervisors.get(supervisor_id)
             .cloned()
@@ -114,6 +297,51 @@ impl SwarmOrchestrator {
         supervisors.keys().cloned().collect()
     }
 
+    /// Describe every supervisor and agent, in id order
+    pub async fn snapshot(&self) -> SwarmSnapshot {
+        let supervisors = self.supervisors.read().await;
+        let mut snapshot = SwarmSnapshot::default();
+
+        for (supervisor_id, supervisor) in supervisors.iter() {
+            let mut agents = supervisor.list().await;
+            agents.sort_by(|a, b| a.id.cmp(&b.id));
+            snapshot.supervisors.push(SupervisorSnapshot {
+                id: supervisor_id.clone(),
+                agents,
+            });
+        }
+
+        snapshot.supervisors.sort_by(|a, b| a.id.cmp(&b.id));
+        snapshot
+    }
+
+    /// Recreate the supervisors and agents of `snapshot`. Supervisors that
+    /// already exist receive the snapshot's agents; missing ones are created
+    /// with the supervisor factory, by default backed by real containers.
+    /// Every agent is restored even if others fail; all failures are
+    /// returned together.
+    #[instrument(name = "swarm.restore", skip_all)]
+    pub async fn restore(&self, snapshot: SwarmSnapshot) -> Result<(), OpenCodeError> {
+        let mut results = Vec::new();
+
+        for supervisor_snapshot in snapshot.supervisors {
+            let supervisor = Arc::clone(
+                self.supervisors
+                    .write()
+                    .await
+                    .entry(supervisor_snapshot.id)
+                    .or_insert_with(|| Arc::new((self.new_supervisor.0)())),
+            );
+
+            for agent in supervisor_snapshot.agents {
+                results.push(supervisor.restore_agent(agent).await);
+            }
+        }
+
+        collect_results(results)?;
+        Ok(())
+    }
+
     /// Get comprehensive swarm metrics
     pub async fn get_metrics(&self) -> SwarmMetrics {
         let supervisors = self.supervisors.read().await;
@@ -121,14 +349,12 @@ impl SwarmOrchestrator {
         let mut active_agents = 0;
         let mut failed_agents = 0;
         let mut tasks_processed = 0;
-        let mut memory_usage = 0;
 
         for supervisor in supervisors.values() {
             if let Ok(health) = supervisor.health_check().await {
                 total_agents += health.total_agents;
                 active_agents += health.running_agents;
                 failed_agents += health.failed_agents;
-                memory_usage += health.memory_usage;
             }
 
             let stats = supervisor.get_stats().await;
@@ -142,36 +368,100 @@ impl SwarmOrchestrator {
             failed_agents,
             tasks_processed,
             uptime: self.started_at.elapsed(),
-            memory_usage,
+            memory_usage_bytes: resident_memory_bytes(),
+        }
+    }
+
+    /// Render the swarm metrics, plus per-supervisor gauges labelled by
+    /// supervisor id, in the Prometheus text exposition format
+    pub async fn metrics_prometheus(&self) -> String {
+        let metrics = self.get_metrics().await;
+
+        let mut per_supervisor = Vec::new();
+        {
+            let supervisors = self.supervisors.read().await;
+            for (id, supervisor) in supervisors.iter() {
+                let (agents, active, failed) = match supervisor.health_check().await {
+                    Ok(health) => (health.total_agents, health.running_agents, health.failed_agents),
+                    Err(_) => (0, 0, 0),
+                };
+                let tasks = supervisor.get_stats().await.total_tasks;
+                per_supervisor.push((format!("{{supervisor=\"{}\"}}", escape_label(id)), agents, active, failed, tasks));
+            }
+        }
+        per_supervisor.sort();
+
+        let mut out = String::new();
+        write_metric(&mut out, "opencode_swarm_supervisors", "gauge", "Number of supervisors in the swarm", [("", metrics.total_supervisors as f64)]);
+        write_metric(&mut out, "opencode_swarm_agents", "gauge", "Number of agents across all supervisors", [("", metrics.total_agents as f64)]);
+        write_metric(&mut out, "opencode_swarm_active_agents", "gauge", "Number of running agents", [("", metrics.active_agents as f64)]);
+        write_metric(&mut out, "opencode_swarm_failed_agents", "gauge", "Number of agents in an error state", [("", metrics.failed_agents as f64)]);
+        write_metric(&mut out, "opencode_swarm_tasks_processed_total", "counter", "Tasks completed by all agents", [("", metrics.tasks_processed as f64)]);
+        write_metric(&mut out, "opencode_swarm_uptime_seconds", "gauge", "Seconds since the orchestrator was created", [("", metrics.uptime.as_secs_f64())]);
+        if let Some(bytes) = metrics.memory_usage_bytes {
+            write_metric(&mut out, "opencode_process_resident_memory_bytes", "gauge", "Resident memory of this process", [("", bytes as f64)]);
         }
+
+        let samples = |value: fn(&(String, usize, usize, usize, usize)) -> usize| {
+            per_supervisor.iter().map(move |entry| (entry.0.as_str(), value(entry) as f64))
+        };
+        write_metric(&mut out, "opencode_supervisor_agents", "gauge", "Number of agents per supervisor", samples(|entry| entry.1));
+        write_metric(&mut out, "opencode_supervisor_active_agents", "gauge", "Number of running agents per supervisor", samples(|entry| entry.2));
+        write_metric(&mut out, "opencode_supervisor_failed_agents", "gauge", "Number of agents in an error state per supervisor", samples(|entry| entry.3));
+        write_metric(&mut out, "opencode_supervisor_tasks_processed_total", "counter", "Tasks completed per supervisor", samples(|entry| entry.4));
+        out
     }
 
-    /// Scale the swarm by adding agents to supervisors
-    pub async fn scale_up(&self, target_agents_per_supervisor: usize) -> Result<()> {
+    /// Scale the swarm by adding agents to supervisors. Every supervisor is
+    /// scaled even if another fails; all failures are returned together.
+    /// Cancelling `cancel` stops before the next agent is added; agents added
+    /// so far are kept.
+    #[instrument(name = "swarm.scale_up", skip(self, cancel))]
+    pub async fn scale_up(
+        &self,
+        target_agents_per_supervisor: usize,
+        cancel: &CancellationToken,
+    ) -> Result<(), OpenCodeError> {
         let supervisors = self.supervisors.read().await;
-        
+        let mut results = Vec::new();
+
         for (supervisor_id, supervisor) in supervisors.iter() {
-            let current_agents = supervisor.list_agents().await.len();
-            
-            if current_agents < target_agents_per_supervisor {
-                let agents_to_add = target_agents_per_supervisor - current_agents;
-                
-                for i in 0..agents_to_add {
-                    let agent_id = format!("{}-agent-{}", supervisor_id, current_agents + i + 1);
-                    supervisor.register_agent(agent_id).await?;
-                }
+            let result =
+                Self::scale_supervisor_up(supervisor_id, supervisor, target_agents_per_supervisor, cancel).await;
+            let cancelled = matches!(result, Err(Error::Cancelled(_)));
+            results.push(result);
+            if cancelled {
+                break;
             }
         }
 
+        collect_results(results)?;
+        Ok(())
+    }
+
+    async fn scale_supervisor_up(
+        supervisor_id: &str,
+        supervisor: &AgentSupervisor,
+        target_agents: usize,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let current_agents = supervisor.list().await.len();
+
+        for i in current_agents..target_agents {
+            checkpoint(cancel, "swarm.scale_up").await?;
+            let agent_id = format!("{}-agent-{}", supervisor_id, i + 1);
+            supervisor.register_agent(agent_id).await?;
+        }
         Ok(())
     }
 
     /// Scale down the swarm by removing agents
+    #[instrument(name = "swarm.scale_down", skip(self))]
     pub async fn scale_down(&self, target_agents_per_supervisor: usize) -> Result<()> {
         let supervisors = self.supervisors.read().await;
         
         for supervisor in supervisors.values() {
-            let agents = supervisor.list_agents().await;
+            let agents = supervisor.list().await;
             
             if agents.len() > target_agents_per_supervisor {
                 let agents_to_remove = agents.len() - target_agents_per_supervisor;
@@ -194,77 +484,135 @@ impl SwarmOrchestrator {
         Ok(())
     }
 
-    /// Rebalance agents across supervisors
-    pub async fn rebalance(&self) -> Result<()> {
+    /// Rebalance agents across supervisors by migrating idle agents
+    #[instrument(name = "swarm.rebalance", skip(self, cancel))]
+    pub async fn rebalance(&self, cancel: &CancellationToken) -> Result<()> {
+        self.migrate_agents(cancel).await?;
+        Ok(())
+    }
+
+    /// Move idle agents from supervisors above their fair share to those
+    /// below it, keeping each agent's id, persona, branch, labels and status. Busy agents are never
+    /// moved, so counts may stay uneven when the excess isn't idle. Returns
+    /// every move made, in order.
+    /// Cancelling `cancel` stops before the next move; every agent moved so
+    /// far lives on exactly one supervisor.
+    #[instrument(name = "swarm.migrate_agents", skip(self, cancel))]
+    pub async fn migrate_agents(&self, cancel: &CancellationToken) -> Result<Vec<AgentMigration>> {
         let supervisors = self.supervisors.read().await;
-        
+        let mut migrations = Vec::new();
+
         if supervisors.len() < 2 {
-            return Ok(()) // Nothing to rebalance
+            return Ok(migrations); // Nothing to rebalance
         }
 
-        // Calculate total agents and target per supervisor
-        let mut total_agents = 0;
-        for supervisor in supervisors.values() {
-            total_agents += supervisor.list_agents().await.len();
+        // Sort for a deterministic choice of who gets the remainder
+        let mut ids: Vec<&String> = supervisors.keys().collect();
+        ids.sort();
+
+        let mut counts = Vec::with_capacity(ids.len());
+        for id in &ids {
+            counts.push(supervisors[*id].list().await.len());
         }
+        let total: usize = counts.iter().sum();
+        let target_per_supervisor = total / ids.len();
+        let remainder = total % ids.len();
+        let target = |i: usize| if i < remainder { target_per_supervisor + 1 } else { target_per_supervisor };
 
-        let target_per_supervisor = total_agents / supervisors.len();
-        let remainder = total_agents % supervisors.len();
+        for source in 0..ids.len() {
+            if counts[source] <= target(source) {
+                continue;
+            }
 
-        // For simplicity, this is a basic rebalancing strategy
-        // In a real implementation, you'd want more sophisticated load balancing
-        
-        for (i, (_supervisor_id, supervisor)) in supervisors.iter().enumerate() {
-            let current_agents = supervisor.list_agents().await.len();
-            let target = if i < remainder { target_per_supervisor + 1 } else { target_per_supervisor };
-            
-            if current_agents > target {
-                let excess = current_agents - target;
-                // Remove excess agents (in real implementation, migrate to other supervisors)
-                let agents = supervisor.list_agents().await;
-                for agent in agents.iter().take(excess) {
-                    if agent.status == AgentStatus::Idle {
-                        supervisor.unregister_agent(&agent.id).await?;
-                    }
+            let mut idle: Vec<_> = supervisors[ids[source]]
+                .list()
+                .await
+                .into_iter()
+                .filter(|agent| agent.status == AgentStatus::Idle)
+                .collect();
+            idle.sort_by(|a, b| a.id.cmp(&b.id));
+
+            for agent in idle {
+                if counts[source] <= target(source) {
+                    break;
+                }
+                let Some(destination) = (0..ids.len()).find(|&i| counts[i] < target(i)) else {
+                    break;
+                };
+                checkpoint(cancel, "swarm.migrate_agents").await?;
+
+                let to = &supervisors[ids[destination]];
+                let mut new_agent_id = agent.id.clone();
+                if to.get_agent(&new_agent_id).await.is_ok() {
+                    new_agent_id = format!("{}-{}", ids[destination], agent.id);
+                }
+
+                // Adopt first so a failure can't lose the agent, and only
+                // launch it again once the source has torn it down
+                let from = &supervisors[ids[source]];
+                let launched = from.is_launched(&agent.id).await;
+                to.adopt_agent(Agent {
+                    id: new_agent_id.clone(),
+                    ..agent.clone()
+                })
+                .await?;
+                from.unregister_agent(&agent.id).await?;
+                if launched {
+                    to.resume(&new_agent_id).await?;
                 }
+
+                counts[source] -= 1;
+                counts[destination] += 1;
+                migrations.push(AgentMigration {
+                    from_supervisor: ids[source].clone(),
+                    to_supervisor: ids[destination].clone(),
+                    agent_id: agent.id,
+                    new_agent_id,
+                });
             }
         }
 
-        Ok(())
+        Ok(migrations)
     }
 
-    /// Perform health checks on all supervisors and recover failed ones
-    pub async fn health_check_and_recover(&self) -> Result<Vec<String>> {
+    /// Perform health checks on all supervisors and recover failed ones.
+    /// Returns the ids of supervisors that needed recovery; a failure to
+    /// recover one supervisor doesn't stop the others from being recovered.
+    #[instrument(name = "swarm.health_check_and_recover", skip(self))]
+    pub async fn health_check_and_recover(&self) -> Result<Vec<String>, OpenCodeError> {
         let supervisors = self.supervisors.read().await;
-        let mut recovered_supervisors = Vec::new();
+        let mut results = Vec::new();
 
         for (supervisor_id, supervisor) in supervisors.iter() {
-            match supervisor.health_check().await {
-                Ok(health) => {
-                    if !health.is_healthy && health.failed_agents > 0 {
-                        // Attempt to recover failed agents
-                        let agents = supervisor.list_agents().await;
-                        for agent in agents.iter() {
-                            if agent.status == AgentStatus::Failed {
-                                // In a real implementation, this would restart the agent
-                                supervisor.update_agent_status(&agent.id, AgentStatus::Starting).await?;
-                            }
-                        }
-                        recovered_supervisors.push(supervisor_id.clone());
-                    }
-                }
-                Err(_) => {
-                    // Supervisor is completely unresponsive
-                    // In a real implementation, you might restart the supervisor
-                    recovered_supervisors.push(supervisor_id.clone());
+            let result = match supervisor.health_check().await {
+                Ok(health) if !health.is_healthy && health.failed_agents > 0 => {
+                    Self::recover_failed_agents(supervisor)
+                        .await
+                        .map(|()| Some(supervisor_id.clone()))
                 }
-            }
+                Ok(_) => Ok(None),
+                // Supervisor is completely unresponsive
+                // In a real implementation, you might restart the supervisor
+                Err(_) => Ok(Some(supervisor_id.clone())),
+            };
+            results.push(result);
         }
 
-        Ok(recovered_supervisors)
+        Ok(collect_results(results)?.into_iter().flatten().collect())
+    }
+
+    async fn recover_failed_agents(supervisor: &AgentSupervisor) -> Result<()> {
+        for agent in supervisor.list().await {
+            if matches!(agent.status, AgentStatus::Error(_)) {
+                // In a real implementation, this would restart the agent
+                supervisor.update_agent_status(&agent.id, AgentStatus::Starting).await?;
+            }
+        }
+        Ok(())
     }
 
     /// Shutdown the entire swarm
+    #[instrument(name = "swarm.shutdown", skip(self))]
     pub async fn shutdown(&self) -> Result<()> {
         let supervisors = self.supervisors.read().await;
         
@@ -282,7 +630,7 @@ impl SwarmOrchestrator {
         let mut active_agents = 0;
 
         for supervisor in supervisors.values() {
-            let agents = supervisor.list_agents().await;
+            let agents = supervisor.list().await;
             total_agents += agents.len();
             active_agents += agents.iter()
                 .filter(|a| a.status == AgentStatus::Running || a.status == AgentStatus::Busy)
@@ -307,21 +655,197 @@ impl SwarmOrchestrator {
         }
     }
 
+    /// List the supervisors currently willing to take on more work, in id order.
+    /// Saturated or draining supervisors are skipped so work doesn't pile up on them.
+    pub async fn accepting_supervisors(&self) -> Vec<String> {
+        let supervisors = self.supervisors.read().await;
+        let mut accepting = Vec::new();
+
+        for (supervisor_id, supervisor) in supervisors.iter() {
+            if supervisor.accepting_work().await {
+                accepting.push(supervisor_id.clone());
+            }
+        }
+
+        accepting.sort();
+        accepting
+    }
+
+    /// The accepting supervisor with the fewest busy agents. Ties go to the
+    /// smallest supervisor id so the choice is deterministic.
+    pub async fn least_loaded_supervisor(&self) -> Option<String> {
+        let supervisors = self.supervisors.read().await;
+        let mut least_loaded: Option<(usize, &String)> = None;
+
+        for (supervisor_id, supervisor) in supervisors.iter() {
+            if !supervisor.accepting_work().await {
+                continue;
+            }
+
+            let busy_agents = supervisor.list().await
+                .iter()
+                .filter(|a| a.status == AgentStatus::Busy)
+                .count();
+            let candidate = (busy_agents, supervisor_id);
+            if least_loaded.is_none_or(|current| candidate < current) {
+                least_loaded = Some(candidate);
+            }
+        }
+
+        least_loaded.map(|(_, supervisor_id)| supervisor_id.clone())
+    }
+
+    /// Hand `task` to a busy agent named after it and return the id of the
+    /// agent's supervisor. The agent left behind by an earlier run of the same
+    /// task is reused; otherwise a new one is registered on the least-loaded
+    /// supervisor. Fails if the task is already running.
+    #[instrument(name = "swarm.assign_task", skip(self))]
+    pub async fn assign_task(&self, task: &str) -> Result<String> {
+        let agent_id = task_agent_id(task);
+        let supervisor_id = match self.task_owner(&agent_id).await {
+            Some((supervisor_id, supervisor)) => {
+                if supervisor.get_status(&agent_id).await? == AgentStatus::Busy {
+                    return Err(Error::Service(format!("Task '{}' is already running", task)));
+                }
+                supervisor.update_agent_status(&agent_id, AgentStatus::Busy).await?;
+                supervisor_id
+            }
+            None => {
+                let supervisor_id = self
+                    .least_loaded_supervisor()
+                    .await
+                    .ok_or_else(|| Error::Service("No supervisor is accepting work".to_string()))?;
+                let supervisor = self.get_supervisor(&supervisor_id).await?;
+                supervisor.register_agent(agent_id.clone()).await?;
+                supervisor.update_agent_status(&agent_id, AgentStatus::Busy).await?;
+                supervisor_id
+            }
+        };
+
+        self.emit(SwarmProgress::TaskStarted {
+            task: task.to_string(),
+            supervisor_id: supervisor_id.clone(),
+        });
+        Ok(supervisor_id)
+    }
+
+    /// Record the outcome of an assigned task: its agent goes back to `Idle`
+    /// on success or to `Error` on failure, and a progress event is sent.
+    /// Returns `outcome` unchanged unless the task's agent can't be found.
+    pub async fn complete_task(&self, task: &str, outcome: Result<()>) -> Result<()> {
+        let agent_id = task_agent_id(task);
+        let (supervisor_id, supervisor) = self
+            .task_owner(&agent_id)
+            .await
+            .ok_or_else(|| Error::Service(format!("Task '{}' is not assigned", task)))?;
+
+        match &outcome {
+            Ok(()) => {
+                supervisor.update_agent_status(&agent_id, AgentStatus::Idle).await?;
+                self.emit(SwarmProgress::TaskCompleted {
+                    task: task.to_string(),
+                    supervisor_id,
+                });
+            }
+            Err(e) => {
+                supervisor.update_agent_status(&agent_id, AgentStatus::Error(e.to_string())).await?;
+                self.emit(SwarmProgress::TaskFailed {
+                    task: task.to_string(),
+                    supervisor_id,
+                    error: e.to_string(),
+                });
+            }
+        }
+        outcome
+    }
+
+    /// The supervisor, and its id, that has the task agent `agent_id`
+    async fn task_owner(&self, agent_id: &str) -> Option<(String, Arc<AgentSupervisor>)> {
+        let supervisors = self.supervisors.read().await;
+        for (supervisor_id, supervisor) in supervisors.iter() {
+            if supervisor.get_agent(agent_id).await.is_ok() {
+                return Some((supervisor_id.clone(), Arc::clone(supervisor)));
+            }
+        }
+        None
+    }
+
+    /// Assign every task of `plan`, then run `work` for all of them
+    /// concurrently, completing each task as its work finishes. Every task
+    /// is run even if others fail; all failures are returned together.
+    /// Cancelling `cancel` stops assigning the remaining tasks and abandons
+    /// the work of assigned ones, which then complete as failed.
+    #[instrument(name = "swarm.execute_plan", skip_all, fields(tasks = plan.tasks.len()))]
+    pub async fn execute_plan<F, Fut>(
+        &self,
+        plan: &Plan,
+        work: F,
+        cancel: &CancellationToken,
+    ) -> Result<(), OpenCodeError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut results = Vec::new();
+        let mut assigned = Vec::new();
+
+        for task in &plan.tasks {
+            if let Err(e) = checkpoint(cancel, "swarm.execute_plan").await {
+                results.push(Err(e));
+                break;
+            }
+            match self.assign_task(task).await {
+                Ok(_) => assigned.push(task.clone()),
+                Err(e) => results.push(Err(e)),
+            }
+        }
+
+        let work = &work;
+        let runs = assigned.into_iter().map(|task| async move {
+            let outcome = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => Err(Error::Cancelled(format!("task '{}'", task))),
+                outcome = work(task.clone()) => outcome,
+            };
+            self.complete_task(&task, outcome).await
+        });
+        results.extend(futures::future::join_all(runs).await);
+
+        collect_results(results)?;
+        Ok(())
+    }
+
     /// Monitor swarm and auto-scale based on load
+    #[instrument(name = "swarm.auto_scale", skip(self))]
     pub async fn auto_scale(&self, min_agents_per_supervisor: usize, max_agents_per_supervisor: usize) -> Result<()> {
         let supervisors = self.supervisors.read().await;
-        
-        for supervisor in supervisors.values() {
-            let agents = supervisor.list_agents().await;
+        let mut saturated = 0;
+        let mut scaled_up = false;
+        // Accepting supervisors with room to grow, by current agent count
+        let mut candidates = Vec::new();
+
+        for (supervisor_id, supervisor) in supervisors.iter() {
+            // A supervisor that signals backpressure must not get more agents
+            if !supervisor.accepting_work().await {
+                saturated += 1;
+                continue;
+            }
+
+            let agents = supervisor.list().await;
             let busy_agents = agents.iter()
                 .filter(|a| a.status == AgentStatus::Busy)
                 .count();
             let total_agents = agents.len();
 
+            if total_agents < max_agents_per_supervisor {
+                candidates.push((total_agents, supervisor_id.clone()));
+            }
+
             // Scale up if more than 80% of agents are busy
             if total_agents > 0 && (busy_agents as f64 / total_agents as f64) > 0.8 && total_agents < max_agents_per_supervisor {
                 let agent_id = format!("auto-scale-agent-{}", total_agents + 1);
                 supervisor.register_agent(agent_id).await?;
+                scaled_up = true;
             }
             // Scale down if less than 20% of agents are busy
             else if total_agents > min_agents_per_supervisor && (busy_agents as f64 / total_agents as f64) < 0.2 {
@@ -335,18 +859,44 @@ impl SwarmOrchestrator {
             }
         }
 
+        // Saturated supervisors shed their load onto the least-loaded accepting one
+        if saturated > 0 && !scaled_up {
+            candidates.sort();
+            if let Some((total_agents, supervisor_id)) = candidates.first() {
+                let agent_id = format!("{}-auto-scale-agent-{}", supervisor_id, total_agents + 1);
+                supervisors[supervisor_id].register_agent(agent_id).await?;
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Let other tasks run, so a caller on the same runtime thread gets to
+/// cancel, then fail if `cancel` has been triggered
+async fn checkpoint(cancel: &CancellationToken, operation: &str) -> Result<()> {
+    tokio::task::yield_now().await;
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled(operation.to_string()));
+    }
+    Ok(())
+}
+
+impl Default for SwarmOrchestrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::container::tests::MockExecutor;
+    use crate::container::ContainerManager;
 
     #[tokio::test]
     async fn test_swarm_orchestrator_creation() {
-        let config = Config::default();
-        let orchestrator = SwarmOrchestrator::new(config);
+        let orchestrator = SwarmOrchestrator::new();
         
         assert!(!orchestrator.is_healthy().await);
         
@@ -357,10 +907,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_remove_supervisor() {
-        let config = Config::default();
-        let orchestrator = SwarmOrchestrator::new(config.clone());
+        let orchestrator = SwarmOrchestrator::new();
         
-        let supervisor = Arc::new(Supervisor::new(config));
+        let supervisor = Arc::new(AgentSupervisor::new());
         orchestrator.add_supervisor("test-supervisor".to_string(), supervisor).await.unwrap();
         
         let supervisors = orchestrator.list_supervisors().await;
@@ -374,10 +923,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_swarm_metrics() {
-        let config = Config::default();
-        let orchestrator = SwarmOrchestrator::new(config.clone());
+        let orchestrator = SwarmOrchestrator::new();
         
-        let supervisor = Arc::new(Supervisor::new(config));
+        let supervisor = Arc::new(AgentSupervisor::new());
         supervisor.register_agent("test-agent".to_string()).await.unwrap();
         supervisor.update_agent_status("test-agent", AgentStatus::Running).await.unwrap();
         
@@ -389,26 +937,94 @@ mod tests {
         assert_eq!(metrics.active_agents, 1);
     }
 
+    #[tokio::test]
+    async fn test_metrics_prometheus() {
+        let orchestrator = SwarmOrchestrator::new();
+
+        let supervisor = Arc::new(AgentSupervisor::new());
+        supervisor.register_agent("running".to_string()).await.unwrap();
+        supervisor.register_agent("idle".to_string()).await.unwrap();
+        supervisor.update_agent_status("running", AgentStatus::Running).await.unwrap();
+        orchestrator.add_supervisor("sup \"a\"".to_string(), supervisor).await.unwrap();
+        orchestrator.add_supervisor("sup-b".to_string(), Arc::new(AgentSupervisor::new())).await.unwrap();
+
+        let text = orchestrator.metrics_prometheus().await;
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert!(text.ends_with('\n'));
+        assert!(lines.contains(&"opencode_swarm_active_agents 1"));
+        assert!(lines.contains(&"opencode_swarm_agents 2"));
+        assert!(lines.contains(&"# TYPE opencode_swarm_active_agents gauge"));
+        assert!(lines.contains(&"# TYPE opencode_swarm_tasks_processed_total counter"));
+        assert!(lines.contains(&r#"opencode_supervisor_active_agents{supervisor="sup \"a\""} 1"#));
+        assert!(lines.contains(&r#"opencode_supervisor_agents{supervisor="sup-b"} 0"#));
+
+        // Every sample belongs to the family declared by the TYPE line before it
+        let sample = regex::Regex::new(r#"^([a-zA-Z_:][a-zA-Z0-9_:]*)(\{[a-zA-Z_][a-zA-Z0-9_]*="(?:[^"\\]|\\.)*"\})? -?[0-9]+(\.[0-9]+)?$"#).unwrap();
+        let mut family = None;
+        for line in lines {
+            if let Some(declared) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = declared.split_once(' ').unwrap();
+                assert!(kind == "gauge" || kind == "counter", "{}", line);
+                family = Some(name.to_string());
+            } else if !line.starts_with("# HELP ") {
+                let captures = sample.captures(line).unwrap_or_else(|| panic!("invalid sample: {}", line));
+                assert_eq!(family.as_deref(), Some(&captures[1]), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_swarm_metrics_serialization() {
+        let metrics = SwarmMetrics {
+            total_supervisors: 2,
+            total_agents: 5,
+            active_agents: 3,
+            failed_agents: 1,
+            tasks_processed: 7,
+            uptime: Duration::from_millis(90_500),
+            memory_usage_bytes: Some(4096),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&metrics).unwrap(),
+            serde_json::json!({
+                "total_supervisors": 2,
+                "total_agents": 5,
+                "active_agents": 3,
+                "failed_agents": 1,
+                "tasks_processed": 7,
+                "uptime_seconds": 90,
+                "memory_usage_bytes": 4096,
+            })
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_swarm_metrics_report_memory_usage() {
+        let metrics = SwarmOrchestrator::new().get_metrics().await;
+        assert!(metrics.memory_usage_bytes.unwrap() > 0);
+    }
+
     #[tokio::test]
     async fn test_scale_up() {
-        let config = Config::default();
-        let orchestrator = SwarmOrchestrator::new(config.clone());
+        let orchestrator = SwarmOrchestrator::new();
         
-        let supervisor = Arc::new(Supervisor::new(config));
+        let supervisor = Arc::new(AgentSupervisor::new());
         orchestrator.add_supervisor("test-supervisor".to_string(), supervisor.clone()).await.unwrap();
         
-        orchestrator.scale_up(3).await.unwrap();
+        orchestrator.scale_up(3, &CancellationToken::new()).await.unwrap();
         
-        let agents = supervisor.list_agents().await;
+        let agents = supervisor.list().await;
         assert_eq!(agents.len(), 3);
     }
 
     #[tokio::test]
     async fn test_scale_down() {
-        let config = Config::default();
-        let orchestrator = SwarmOrchestrator::new(config.clone());
+        let orchestrator = SwarmOrchestrator::new();
         
-        let supervisor = Arc::new(Supervisor::new(config));
+        let supervisor = Arc::new(AgentSupervisor::new());
         
         // Add some agents first
         for i in 0..5 {
@@ -421,18 +1037,93 @@ mod tests {
         
         orchestrator.scale_down(2).await.unwrap();
         
-        let agents = supervisor.list_agents().await;
+        let agents = supervisor.list().await;
         assert_eq!(agents.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_migrate_agents_balances_supervisors() {
+        let orchestrator = SwarmOrchestrator::new();
+
+        let crowded = Arc::new(AgentSupervisor::new());
+        for i in 0..5 {
+            crowded.register_agent_with_persona(format!("agent-{}", i), "rusty").await.unwrap();
+        }
+        crowded.update_agent_status("agent-0", AgentStatus::Busy).await.unwrap();
+        let empty = Arc::new(AgentSupervisor::new());
+        // Already taken on the destination, so this one must be renamed
+        empty.register_agent("agent-1".to_string()).await.unwrap();
+
+        orchestrator.add_supervisor("a".to_string(), crowded.clone()).await.unwrap();
+        orchestrator.add_supervisor("b".to_string(), empty.clone()).await.unwrap();
+
+        let migrations = orchestrator.migrate_agents(&CancellationToken::new()).await.unwrap();
+
+        // 6 agents over 2 supervisors: 3 each
+        assert_eq!(crowded.list().await.len(), 3);
+        assert_eq!(empty.list().await.len(), 3);
+        assert_eq!(
+            migrations,
+            vec![
+                AgentMigration {
+                    from_supervisor: "a".to_string(),
+                    to_supervisor: "b".to_string(),
+                    agent_id: "agent-1".to_string(),
+                    new_agent_id: "b-agent-1".to_string(),
+                },
+                AgentMigration {
+                    from_supervisor: "a".to_string(),
+                    to_supervisor: "b".to_string(),
+                    agent_id: "agent-2".to_string(),
+                    new_agent_id: "agent-2".to_string(),
+                },
+            ]
+        );
+
+        // Busy agents stay put; migrated agents keep their persona
+        assert!(crowded.get_agent("agent-0").await.is_ok());
+        assert_eq!(empty.get_agent("agent-2").await.unwrap().persona, "rusty");
+        assert!(crowded.get_agent("agent-2").await.is_err());
+
+        // Already balanced, so a second pass moves nothing
+        assert!(orchestrator.migrate_agents(&CancellationToken::new()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_agents_moves_the_whole_agent() {
+        let orchestrator = SwarmOrchestrator::new();
+
+        let crowded = Arc::new(AgentSupervisor::new());
+        let labels = HashMap::from([("team".to_string(), "infra".to_string())]);
+        crowded.spawn_with_labels("a-worker", "rusty", labels.clone()).await.unwrap();
+        crowded.update_agent_status("a-worker", AgentStatus::Idle).await.unwrap();
+        crowded.register_agent("b-busy".to_string()).await.unwrap();
+        crowded.update_agent_status("b-busy", AgentStatus::Busy).await.unwrap();
+        let empty = Arc::new(AgentSupervisor::new());
+
+        orchestrator.add_supervisor("a".to_string(), crowded.clone()).await.unwrap();
+        orchestrator.add_supervisor("b".to_string(), empty.clone()).await.unwrap();
+        orchestrator.migrate_agents(&CancellationToken::new()).await.unwrap();
+
+        let moved = empty.get_agent("a-worker").await.unwrap();
+        assert_eq!(moved.labels, labels);
+        assert_eq!(moved.branch_name, "agent-a-worker");
+        assert_eq!(moved.status, AgentStatus::Idle);
+        assert!(crowded.get_agent("a-worker").await.is_err());
+        assert!(!crowded.is_launched("a-worker").await);
+
+        // It keeps taking tasks on its new supervisor
+        assert!(empty.is_launched("a-worker").await);
+        empty.send_task("a-worker", "work").await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_health_check_and_recover() {
-        let config = Config::default();
-        let orchestrator = SwarmOrchestrator::new(config.clone());
+        let orchestrator = SwarmOrchestrator::new();
         
-        let supervisor = Arc::new(Supervisor::new(config));
+        let supervisor = Arc::new(AgentSupervisor::new());
         supervisor.register_agent("test-agent".to_string()).await.unwrap();
-        supervisor.update_agent_status("test-agent", AgentStatus::Failed).await.unwrap();
+        supervisor.update_agent_status("test-agent", AgentStatus::Error("crashed".to_string())).await.unwrap();
         
         orchestrator.add_supervisor("test-supervisor".to_string(), supervisor.clone()).await.unwrap();
         
@@ -447,10 +1138,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_swarm_shutdown() {
-        let config = Config::default();
-        let orchestrator = SwarmOrchestrator::new(config.clone());
+        let orchestrator = SwarmOrchestrator::new();
         
-        let supervisor = Arc::new(Supervisor::new(config));
+        let supervisor = Arc::new(AgentSupervisor::new());
         supervisor.register_agent("test-agent".to_string()).await.unwrap();
         supervisor.update_agent_status("test-agent", AgentStatus::Running).await.unwrap();
         
@@ -461,4 +1151,406 @@ mod tests {
         let agent = supervisor.get_agent("test-agent").await.unwrap();
         assert_eq!(agent.status, AgentStatus::Stopped);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_accepting_supervisors_skips_saturated() {
+        let orchestrator = SwarmOrchestrator::new();
+
+        let saturated = Arc::new(AgentSupervisor::new());
+        saturated.set_draining(true);
+        let available = Arc::new(AgentSupervisor::new());
+
+        orchestrator.add_supervisor("saturated".to_string(), saturated).await.unwrap();
+        orchestrator.add_supervisor("available".to_string(), available).await.unwrap();
+
+        assert_eq!(orchestrator.accepting_supervisors().await, vec!["available".to_string()]);
+    }
+
+    /// A supervisor with `busy` busy agents and one idle agent
+    async fn supervisor_with_busy(busy: usize) -> Arc<AgentSupervisor> {
+        let supervisor = Arc::new(AgentSupervisor::new());
+        for i in 0..busy {
+            let agent_id = format!("busy-{}", i);
+            supervisor.register_agent(agent_id.clone()).await.unwrap();
+            supervisor.update_agent_status(&agent_id, AgentStatus::Busy).await.unwrap();
+        }
+        supervisor.register_agent("idle".to_string()).await.unwrap();
+        supervisor
+    }
+
+    #[tokio::test]
+    async fn test_least_loaded_supervisor() {
+        let orchestrator = SwarmOrchestrator::new();
+        assert_eq!(orchestrator.least_loaded_supervisor().await, None);
+
+        orchestrator.add_supervisor("a".to_string(), supervisor_with_busy(3).await).await.unwrap();
+        orchestrator.add_supervisor("b".to_string(), supervisor_with_busy(1).await).await.unwrap();
+        orchestrator.add_supervisor("c".to_string(), supervisor_with_busy(2).await).await.unwrap();
+        assert_eq!(orchestrator.least_loaded_supervisor().await, Some("b".to_string()));
+
+        // Equally loaded supervisors are picked by id
+        orchestrator.add_supervisor("d".to_string(), supervisor_with_busy(1).await).await.unwrap();
+        orchestrator.add_supervisor("aa".to_string(), supervisor_with_busy(1).await).await.unwrap();
+        assert_eq!(orchestrator.least_loaded_supervisor().await, Some("aa".to_string()));
+
+        // Draining supervisors are never chosen
+        orchestrator.get_supervisor("aa").await.unwrap().set_draining(true);
+        assert_eq!(orchestrator.least_loaded_supervisor().await, Some("b".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_assign_task_spreads_load() {
+        let orchestrator = SwarmOrchestrator::new();
+        let a = supervisor_with_busy(1).await;
+        let b = supervisor_with_busy(0).await;
+        orchestrator.add_supervisor("a".to_string(), a.clone()).await.unwrap();
+        orchestrator.add_supervisor("b".to_string(), b.clone()).await.unwrap();
+
+        assert_eq!(orchestrator.assign_task("crates/core").await.unwrap(), "b");
+        let agent = b.get_agent("task-crates-core").await.unwrap();
+        assert_eq!(agent.status, AgentStatus::Busy);
+
+        // Both have one busy agent now, so the tie goes to "a"
+        assert_eq!(orchestrator.assign_task("crates/cli").await.unwrap(), "a");
+        assert!(a.get_agent("task-crates-cli").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_can_run_again() {
+        let orchestrator = SwarmOrchestrator::new();
+        let supervisor = Arc::new(AgentSupervisor::new());
+        orchestrator.add_supervisor("main".to_string(), supervisor.clone()).await.unwrap();
+
+        let plan = Plan {
+            tasks: vec!["build".to_string(), "lint".to_string()],
+        };
+        let fail_lint = |task: String| async move {
+            match task.as_str() {
+                "lint" => Err(Error::Other("warnings".to_string())),
+                _ => Ok(()),
+            }
+        };
+        assert!(orchestrator.execute_plan(&plan, fail_lint, &CancellationToken::new()).await.is_err());
+        orchestrator
+            .execute_plan(&plan, |_| async { Ok(()) }, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        // The second run reuses the agents of the first, failed ones included
+        let mut agents = supervisor.list().await;
+        agents.sort_by(|a, b| a.id.cmp(&b.id));
+        let statuses: Vec<_> = agents.iter().map(|a| (a.id.as_str(), a.status.clone())).collect();
+        assert_eq!(statuses, vec![("task-build", AgentStatus::Idle), ("task-lint", AgentStatus::Idle)]);
+    }
+
+    #[tokio::test]
+    async fn test_assign_task_rejects_a_running_task() {
+        let orchestrator = SwarmOrchestrator::new();
+        orchestrator.add_supervisor("main".to_string(), Arc::new(AgentSupervisor::new())).await.unwrap();
+
+        orchestrator.assign_task("build").await.unwrap();
+        let err = orchestrator.assign_task("build").await.unwrap_err();
+        assert_eq!(err.to_string(), "Service error: Task 'build' is already running");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_execute_plan_reports_progress_as_tasks_finish() {
+        let orchestrator = SwarmOrchestrator::new();
+        let supervisor = Arc::new(AgentSupervisor::new());
+        orchestrator.add_supervisor("main".to_string(), supervisor.clone()).await.unwrap();
+        let mut progress = orchestrator.subscribe_progress();
+
+        let plan = Plan {
+            tasks: vec!["slow".to_string(), "fast".to_string(), "broken".to_string()],
+        };
+        let work = |task: String| async move {
+            match task.as_str() {
+                "slow" => tokio::time::sleep(Duration::from_secs(10)).await,
+                "fast" => tokio::time::sleep(Duration::from_secs(1)).await,
+                _ => return Err(Error::Other("compile error".to_string())),
+            }
+            Ok(())
+        };
+        let result = orchestrator.execute_plan(&plan, work, &CancellationToken::new()).await;
+        assert!(matches!(result, Err(OpenCodeError::Multiple(ref errors)) if errors.len() == 1));
+
+        let started = |task: &str| SwarmProgress::TaskStarted {
+            task: task.to_string(),
+            supervisor_id: "main".to_string(),
+        };
+        let completed = |task: &str| SwarmProgress::TaskCompleted {
+            task: task.to_string(),
+            supervisor_id: "main".to_string(),
+        };
+        let mut events = Vec::new();
+        while let Ok(event) = progress.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                started("slow"),
+                started("fast"),
+                started("broken"),
+                SwarmProgress::TaskFailed {
+                    task: "broken".to_string(),
+                    supervisor_id: "main".to_string(),
+                    error: "Error: compile error".to_string(),
+                },
+                completed("fast"),
+                completed("slow"),
+            ]
+        );
+
+        // Finished agents are idle again and count as completed work
+        assert_eq!(supervisor.get_status("task-slow").await.unwrap(), AgentStatus::Idle);
+        assert_eq!(supervisor.get_stats().await.total_tasks, 2);
+        assert!(matches!(supervisor.get_status("task-broken").await.unwrap(), AgentStatus::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_plan_starts_no_remaining_tasks() {
+        let orchestrator = SwarmOrchestrator::new();
+        let supervisor = Arc::new(AgentSupervisor::new());
+        orchestrator.add_supervisor("main".to_string(), supervisor.clone()).await.unwrap();
+        let mut progress = orchestrator.subscribe_progress();
+
+        // Cancel as soon as the first task has been started
+        let cancel = CancellationToken::new();
+        let canceller = {
+            let cancel = cancel.clone();
+            let mut progress = orchestrator.subscribe_progress();
+            tokio::spawn(async move {
+                if let Ok(SwarmProgress::TaskStarted { .. }) = progress.recv().await {
+                    cancel.cancel();
+                }
+            })
+        };
+
+        let plan = Plan {
+            tasks: vec!["first".to_string(), "second".to_string(), "third".to_string()],
+        };
+        let ran = std::sync::atomic::AtomicUsize::new(0);
+        let ran = &ran;
+        let result = orchestrator
+            .execute_plan(
+                &plan,
+                |_task| async move {
+                    ran.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    Ok(())
+                },
+                &cancel,
+            )
+            .await;
+        canceller.await.unwrap();
+
+        match result {
+            Err(OpenCodeError::Multiple(errors)) => assert_eq!(errors.len(), 2),
+            other => panic!("Expected Multiple error, got {:?}", other),
+        }
+        assert_eq!(ran.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        let mut events = Vec::new();
+        while let Ok(event) = progress.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                SwarmProgress::TaskStarted {
+                    task: "first".to_string(),
+                    supervisor_id: "main".to_string(),
+                },
+                SwarmProgress::TaskFailed {
+                    task: "first".to_string(),
+                    supervisor_id: "main".to_string(),
+                    error: "Cancelled: task 'first'".to_string(),
+                },
+            ]
+        );
+        assert!(supervisor.get_agent("task-second").await.is_err());
+        assert!(supervisor.get_agent("task-third").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_scale_up_adds_no_agents() {
+        let orchestrator = SwarmOrchestrator::new();
+        let supervisor = Arc::new(AgentSupervisor::new());
+        orchestrator.add_supervisor("main".to_string(), supervisor.clone()).await.unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        match orchestrator.scale_up(3, &cancel).await {
+            Err(OpenCodeError::Multiple(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("Expected Multiple error, got {:?}", other),
+        }
+        assert!(supervisor.list().await.is_empty());
+    }
+
+    fn agent(id: &str, status: AgentStatus) -> Agent {
+        Agent {
+            id: id.to_string(),
+            persona: "rusty".to_string(),
+            status,
+            branch_name: format!("agent-{}", id),
+            labels: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_serde_round_trip() {
+        let snapshot = SwarmSnapshot {
+            supervisors: vec![
+                SupervisorSnapshot {
+                    id: "a".to_string(),
+                    agents: vec![
+                        agent("alice", AgentStatus::Running),
+                        agent("bob", AgentStatus::Error("crashed".to_string())),
+                    ],
+                },
+                SupervisorSnapshot {
+                    id: "b".to_string(),
+                    agents: Vec::new(),
+                },
+            ],
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<SwarmSnapshot>(&json).unwrap(), snapshot);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("swarm.json");
+        assert_eq!(SwarmSnapshot::load(&path).unwrap(), SwarmSnapshot::default());
+        snapshot.save(&path).unwrap();
+        assert_eq!(SwarmSnapshot::load(&path).unwrap(), snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_and_restore() {
+        let orchestrator = SwarmOrchestrator::new();
+        let supervisor = Arc::new(AgentSupervisor::new());
+        supervisor.spawn("alice", "rusty").await.unwrap();
+        supervisor.register_agent("worker".to_string()).await.unwrap();
+        orchestrator.add_supervisor("b".to_string(), supervisor).await.unwrap();
+        orchestrator.add_supervisor("a".to_string(), Arc::new(AgentSupervisor::new())).await.unwrap();
+
+        let snapshot = orchestrator.snapshot().await;
+        let ids: Vec<_> = snapshot.supervisors.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(snapshot.supervisors[1].agents.len(), 2);
+
+        // A fresh orchestrator, as after a restart
+        let restored = SwarmOrchestrator::new().with_supervisor_factory(AgentSupervisor::new);
+        restored.restore(snapshot.clone()).await.unwrap();
+        assert_eq!(restored.snapshot().await, snapshot);
+        let alice = restored.get_supervisor("b").await.unwrap().get_agent("alice").await.unwrap();
+        assert_eq!(alice.status, AgentStatus::Running);
+        assert_eq!(alice.branch_name, "agent-alice");
+
+        // Restoring twice would duplicate agents
+        assert!(restored.restore(snapshot).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore_reattaches_running_containers_by_branch() {
+        let executor = Arc::new(MockExecutor::default());
+        let manager = ContainerManager::with_executor(executor.clone());
+        let snapshot = SwarmSnapshot {
+            supervisors: vec![SupervisorSnapshot {
+                id: "main".to_string(),
+                agents: vec![Agent {
+                    id: "alice".to_string(),
+                    persona: "rusty".to_string(),
+                    status: AgentStatus::Running,
+                    branch_name: "agent-alice".to_string(),
+                    labels: HashMap::new(),
+                }],
+            }],
+        };
+
+        let restored = SwarmOrchestrator::new()
+            .with_supervisor_factory(move || AgentSupervisor::new().with_container_manager(manager.clone()));
+        restored.restore(snapshot).await.unwrap();
+        tokio::task::yield_now().await;
+
+        let open = executor.calls().into_iter().find(|args| args.get(2).map(String::as_str) == Some("open")).unwrap();
+        assert_eq!(open[4], "agent-alice");
+    }
+
+    #[tokio::test]
+    async fn test_complete_unassigned_task() {
+        let orchestrator = SwarmOrchestrator::new();
+        assert!(orchestrator.complete_task("nothing", Ok(())).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_assign_task_without_supervisors() {
+        let orchestrator = SwarmOrchestrator::new();
+        assert!(orchestrator.assign_task("crates/core").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_auto_scale_scales_up_instead_of_piling_on_saturated() {
+        let orchestrator = SwarmOrchestrator::new();
+
+        let saturated = Arc::new(AgentSupervisor::new());
+        for i in 0..2 {
+            let agent_id = format!("busy-{}", i);
+            saturated.register_agent(agent_id.clone()).await.unwrap();
+            saturated.update_agent_status(&agent_id, AgentStatus::Busy).await.unwrap();
+        }
+        saturated.set_draining(true);
+
+        let available = Arc::new(AgentSupervisor::new());
+        available.register_agent("idle-0".to_string()).await.unwrap();
+        available.update_agent_status("idle-0", AgentStatus::Idle).await.unwrap();
+
+        orchestrator.add_supervisor("saturated".to_string(), saturated.clone()).await.unwrap();
+        orchestrator.add_supervisor("available".to_string(), available.clone()).await.unwrap();
+
+        orchestrator.auto_scale(1, 4).await.unwrap();
+
+        // The saturated supervisor is left alone and capacity is added elsewhere
+        assert_eq!(saturated.list().await.len(), 2);
+        assert_eq!(available.list().await.len(), 2);
+    }
+
+    #[test]
+    fn test_plan_build_from_workspace_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        std::fs::write(&manifest, "[workspace]\nmembers = [\"crates/core\", \"crates/cli\"]\n").unwrap();
+
+        let plan = plan_build_from_manifest(&manifest).unwrap();
+        assert_eq!(plan.tasks, vec!["crates/core".to_string(), "crates/cli".to_string()]);
+    }
+
+    #[test]
+    fn test_plan_build_from_package_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        std::fs::write(&manifest, "[package]\nname = \"demo\"\n").unwrap();
+
+        let plan = plan_build_from_manifest(&manifest).unwrap();
+        assert_eq!(plan.tasks, vec!["root_package".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_scale_up_reports_failures_without_masking_others() {
+        let orchestrator = SwarmOrchestrator::new();
+
+        // "a-agent-2" is the id scale_up will try to add next, so it fails
+        let conflicting = Arc::new(AgentSupervisor::new());
+        conflicting.register_agent("a-agent-2".to_string()).await.unwrap();
+        let healthy = Arc::new(AgentSupervisor::new());
+
+        orchestrator.add_supervisor("a".to_string(), conflicting).await.unwrap();
+        orchestrator.add_supervisor("b".to_string(), healthy.clone()).await.unwrap();
+
+        match orchestrator.scale_up(3, &CancellationToken::new()).await {
+            Err(OpenCodeError::Multiple(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("Expected Multiple error, got {:?}", other),
+        }
+        assert_eq!(healthy.list().await.len(), 3);
+    }
+}
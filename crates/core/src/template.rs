@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+/// Replace every `{{name}}` placeholder in `template` with its value from
+/// `vars`. Whitespace inside the braces is ignored. A backslash before `{{`
+/// or `}}` writes the braces literally. Placeholders without a value, and
+/// unclosed ones, are errors.
+pub fn render_template(template: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['\\', '{']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            if let Some(braces) = ["{{", "}}"].into_iter().find(|b| escaped.starts_with(b)) {
+                output.push_str(braces);
+                rest = &escaped[2..];
+            } else {
+                output.push('\\');
+                rest = escaped;
+            }
+            continue;
+        }
+
+        let Some(open) = rest.strip_prefix("{{") else {
+            output.push('{');
+            rest = &rest[1..];
+            continue;
+        };
+        let end = open
+            .find("}}")
+            .ok_or_else(|| anyhow!("Unclosed placeholder in template: {{{{{}", open))?;
+        let name = open[..end].trim();
+        let value = vars
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown template variable '{}'", name))?;
+        output.push_str(value);
+        rest = &open[end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_substitutes_variables() {
+        let vars = vars(&[("name", "Ferris"), ("lang", "Rust")]);
+        let rendered = render_template("Hi {{name}}, welcome to {{ lang }}! Bye {{name}}.", &vars).unwrap();
+        assert_eq!(rendered, "Hi Ferris, welcome to Rust! Bye Ferris.");
+    }
+
+    #[test]
+    fn test_unknown_variable_is_an_error() {
+        let err = render_template("Hello {{who}}", &vars(&[("name", "Ferris")])).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown template variable 'who'");
+
+        let err = render_template("Hello {{name", &vars(&[("name", "Ferris")])).unwrap_err();
+        assert!(err.to_string().starts_with("Unclosed placeholder"));
+    }
+
+    #[test]
+    fn test_escaped_braces_are_literal() {
+        let vars = vars(&[("name", "Ferris")]);
+        let rendered = render_template(r"\{{name\}} is {{name}}; {single} and C:\path", &vars).unwrap();
+        assert_eq!(rendered, r"{{name}} is Ferris; {single} and C:\path");
+    }
+}
@@ -0,0 +1,137 @@
+use crate::provider::{Message, Role};
+
+/// Tokens a chat API spends framing each message (role, separators) on top
+/// of its content
+pub const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Counts the tokens a piece of text costs a model
+pub trait Tokenizer: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Model-agnostic estimate of one token per four characters, rounded up.
+/// Close enough for English prose and code on GPT-style tokenizers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// The tokenizer used for `model`. Every model uses the heuristic until a
+/// real tokenizer is available.
+pub fn tokenizer_for(_model: &str) -> &'static dyn Tokenizer {
+    &HeuristicTokenizer
+}
+
+/// Tokens a single message costs, including framing overhead
+pub fn message_tokens(tokenizer: &dyn Tokenizer, message: &Message) -> usize {
+    tokenizer.count_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Tokens a whole conversation costs
+pub fn count_message_tokens(tokenizer: &dyn Tokenizer, messages: &[Message]) -> usize {
+    messages.iter().map(|m| message_tokens(tokenizer, m)).sum()
+}
+
+/// Drop the oldest messages until the conversation fits in `max_tokens` for
+/// `model`. System messages and the most recent user message are always kept,
+/// so the result can still exceed the budget if those alone don't fit.
+pub fn trim_messages(messages: &[Message], max_tokens: usize, model: &str) -> Vec<Message> {
+    trim_messages_with(tokenizer_for(model), messages, max_tokens)
+}
+
+/// Like `trim_messages`, counting tokens with `tokenizer`
+pub fn trim_messages_with(
+    tokenizer: &dyn Tokenizer,
+    messages: &[Message],
+    max_tokens: usize,
+) -> Vec<Message> {
+    let last_user = messages.iter().rposition(|m| m.role == Role::User);
+    let mut total = count_message_tokens(tokenizer, messages);
+    let mut keep = vec![true; messages.len()];
+
+    for (i, message) in messages.iter().enumerate() {
+        if total <= max_tokens {
+            break;
+        }
+        if message.role == Role::System || Some(i) == last_user {
+            continue;
+        }
+        keep[i] = false;
+        total -= message_tokens(tokenizer, message);
+    }
+
+    messages
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(message, _)| message.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: Role, content: &str) -> Message {
+        Message {
+            role,
+            content: content.to_string(),
+        }
+    }
+
+    /// Charges one token per word, for predictable budgets
+    struct WordTokenizer;
+
+    impl Tokenizer for WordTokenizer {
+        fn count_tokens(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+    }
+
+    #[test]
+    fn test_heuristic_rounds_up() {
+        assert_eq!(HeuristicTokenizer.count_tokens(""), 0);
+        assert_eq!(HeuristicTokenizer.count_tokens("abc"), 1);
+        assert_eq!(HeuristicTokenizer.count_tokens("abcdefgh"), 2);
+        assert_eq!(HeuristicTokenizer.count_tokens("abcdefghi"), 3);
+    }
+
+    #[test]
+    fn test_messages_within_budget_are_untouched() {
+        let messages = vec![message(Role::User, "hello"), message(Role::Assistant, "hi")];
+        assert_eq!(trim_messages(&messages, 1000, "gpt-4").len(), 2);
+    }
+
+    #[test]
+    fn test_drops_oldest_non_system_messages() {
+        let messages = vec![
+            message(Role::System, "be brief"),
+            message(Role::User, "one two"),
+            message(Role::Assistant, "three four"),
+            message(Role::User, "five six"),
+        ];
+        // Each message costs 2 words + 4 overhead = 6 tokens
+        let trimmed = trim_messages_with(&WordTokenizer, &messages, 18);
+
+        let contents: Vec<_> = trimmed.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["be brief", "three four", "five six"]);
+    }
+
+    #[test]
+    fn test_keeps_system_and_last_user_message_over_budget() {
+        let messages = vec![
+            message(Role::System, "be brief"),
+            message(Role::User, "old question"),
+            message(Role::Assistant, "old answer"),
+            message(Role::User, "a very long latest question"),
+        ];
+        let trimmed = trim_messages_with(&WordTokenizer, &messages, 1);
+
+        let contents: Vec<_> = trimmed.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["be brief", "a very long latest question"]);
+    }
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "echo-provider")]
+
+use futures::future::join_all;
+use opencode_core::config::{Config, RateLimitConfig};
+use opencode_core::provider::{collect_stream, CompletionRequest, EchoProvider, Message, Role};
+use opencode_core::service::ServiceContainer;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn echo_container(provider: EchoProvider) -> ServiceContainer {
+    let config = Config {
+        default_provider: Some("echo".to_string()),
+        ..Config::default()
+    };
+    let mut container = ServiceContainer::new(config).unwrap();
+    container.register_provider("echo", Arc::new(provider));
+    container
+}
+
+fn prompt(content: &str) -> CompletionRequest {
+    CompletionRequest::builder()
+        .model("bench")
+        .message(Message {
+            role: Role::User,
+            content: content.to_string(),
+        })
+        .build()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_concurrent_requests_overlap_their_latency() {
+    let container = echo_container(EchoProvider::new().with_latency(Duration::from_millis(100)));
+    let started = tokio::time::Instant::now();
+
+    let requests = (0..50).map(|i| container.complete(prompt(&format!("request {}", i))));
+    let responses = join_all(requests).await;
+
+    assert!(responses.iter().all(|response| response.is_ok()));
+    assert_eq!(responses[7].as_ref().unwrap().content, "request 7");
+    assert_eq!(started.elapsed(), Duration::from_millis(100));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_throttles_echo_requests() {
+    let mut container = echo_container(EchoProvider::new());
+    container.set_rate_limit(
+        "echo",
+        RateLimitConfig {
+            requests_per_minute: 2,
+            tokens_per_minute: 1_000_000,
+        },
+    );
+    let started = tokio::time::Instant::now();
+
+    for i in 0..3 {
+        container.complete(prompt(&format!("request {}", i))).await.unwrap();
+    }
+
+    // Two requests fit the budget; the third waits about 30s for a refill
+    assert!(started.elapsed() >= Duration::from_secs(20), "{:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_streamed_echo_matches_completion() {
+    let container = echo_container(EchoProvider::new().with_chunk_size(3));
+
+    let streamed = collect_stream(container.stream(prompt("stream me please")).await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(streamed.content, "stream me please");
+}
@@ -3,16 +3,27 @@
     windows_subsystem = "windows"
 )]
 
-use opencode_core::supervisor::{Agent, AgentSupervisor};
-use opencode_core::swarm;
+use futures::StreamExt;
+use opencode_core::config::Config;
+use opencode_core::error::Error;
+use opencode_core::supervisor::{Agent, AgentStatus, AgentSupervisor};
+use opencode_core::personas;
+use opencode_core::swarm::{self, SwarmMetrics, SwarmOrchestrator, SwarmProgress, SwarmSnapshot};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::{AppHandle, Emitter};
-use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
 
 // Create a struct for the application's shared state
 pub struct AppState {
-    supervisor: Arc<Mutex<AgentSupervisor>>,
+    supervisor: Arc<AgentSupervisor>,
+    // Runs swarm builds; `supervisor` is registered with it as "main"
+    swarm: Arc<SwarmOrchestrator>,
+    // Cancels the swarm build in progress, if any
+    build_cancel: Mutex<Option<CancellationToken>>,
 }
 
 // Define the payload for our progress event
@@ -23,10 +34,67 @@ struct SwarmProgressPayload {
     task: String,
 }
 
+/// Where the swarm is persisted so agents survive a restart
+fn snapshot_path() -> Option<PathBuf> {
+    personas::get_config_path_no_create()
+        .ok()
+        .map(|dir| dir.join("swarm.json"))
+}
+
+/// Save the current swarm state; called after every change to it
+async fn persist_swarm(swarm: &SwarmOrchestrator) {
+    if let Some(path) = snapshot_path() {
+        if let Err(e) = swarm.snapshot().await.save(&path) {
+            eprintln!("Failed to save swarm snapshot: {}", e);
+        }
+    }
+}
+
+// Payloads of the ASK_CHUNK, ASK_DONE and ASK_ERROR events; `request_id`
+// tells concurrent asks apart
+#[derive(Clone, serde::Serialize)]
+struct AskChunkPayload {
+    request_id: u64,
+    delta: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct AskDonePayload {
+    request_id: u64,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct AskErrorPayload {
+    request_id: u64,
+    message: String,
+}
+
+static NEXT_ASK_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Stream the model's answer to `prompt` to the frontend as it is generated
+#[tauri::command]
+async fn ask_stream(prompt: String, app_handle: AppHandle) -> Result<(), String> {
+    let request_id = NEXT_ASK_ID.fetch_add(1, Ordering::Relaxed);
+    let fail = |message: String| {
+        let _ = app_handle.emit("ASK_ERROR", AskErrorPayload { request_id, message: message.clone() });
+        message
+    };
+
+    let mut stream = opencode_core::ask_stream(&prompt).await.map_err(|e| fail(e.to_string()))?;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| fail(e.to_string()))?;
+        if !chunk.delta.is_empty() {
+            app_handle.emit("ASK_CHUNK", AskChunkPayload { request_id, delta: chunk.delta }).unwrap();
+        }
+    }
+
+    app_handle.emit("ASK_DONE", AskDonePayload { request_id }).unwrap();
+    Ok(())
+}
+
 #[tauri::command]
 async fn list_agents(state: tauri::State<'_, AppState>) -> Result<Vec<Agent>, String> {
-    let supervisor = state.supervisor.lock().await;
-    Ok(supervisor.list().await)
+    Ok(state.supervisor.list().await)
 }
 
 #[tauri::command]
@@ -35,11 +103,36 @@ async fn spawn_agent(
     persona: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), String> {
-    let mut supervisor = state.supervisor.lock().await;
-    supervisor
+    state
+        .supervisor
         .spawn(&id, &persona)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    persist_swarm(&state.swarm).await;
+    Ok(())
+}
+
+/// Stop one agent. Errors start with `not_found:` or `not_running:` so the
+/// frontend can tell the cases apart; any other error is a failed teardown.
+#[tauri::command]
+async fn stop_agent(id: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let agent = state
+        .supervisor
+        .get_agent(&id)
+        .await
+        .map_err(|_| format!("not_found: Agent '{}' not found", id))?;
+    if agent.status == AgentStatus::Stopped {
+        return Err(format!("not_running: Agent '{}' is not running", id));
+    }
+
+    let result = state.supervisor.stop(&id).await;
+    persist_swarm(&state.swarm).await;
+    result.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn swarm_metrics(state: tauri::State<'_, AppState>) -> Result<SwarmMetrics, String> {
+    Ok(state.swarm.get_metrics().await)
 }
 
 #[tauri::command]
@@ -47,8 +140,6 @@ async fn execute_swarm_build(
     app_handle: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), String> {
-    let supervisor = state.supervisor.lock().await;
-
     // For this example, we assume Cargo.toml is in the current directory.
     let manifest_path = PathBuf::from("Cargo.toml");
     let plan = swarm::plan_build_from_manifest(&manifest_path).map_err(|e| e.to_string())?;
@@ -56,6 +147,12 @@ async fn execute_swarm_build(
     let total_tasks = plan.tasks.len();
     println!("Executing swarm build with {} tasks.", total_tasks);
 
+    // Subscribe before any task starts so no event is missed
+    let mut progress = state.swarm.subscribe_progress();
+
+    let cancel = CancellationToken::new();
+    *state.build_cancel.lock().unwrap() = Some(cancel.clone());
+
     // Emit initial event
     app_handle.emit("SWARM_PROGRESS", SwarmProgressPayload {
         total: total_tasks,
@@ -63,30 +160,34 @@ async fn execute_swarm_build(
         task: "Starting swarm build...".into(),
     }).unwrap();
 
-    // Drop the supervisor lock before spawning tasks
-    drop(supervisor);
-
-    // Spawn an agent for each task
-    for (i, task) in plan.tasks.iter().enumerate() {
-        let agent_id = format!("builder-{}", task.replace('/', "-"));
-        let persona = "rusty"; // Use a default builder persona
-        
-        // Acquire lock for each spawn operation
-        let mut supervisor = state.supervisor.lock().await;
-        supervisor.spawn(&agent_id, persona).await.map_err(|e| e.to_string())?;
-        drop(supervisor);
-
-        // Simulate work being done
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-        // Emit a progress event after each task
-        app_handle.emit("SWARM_PROGRESS", SwarmProgressPayload {
-            total: total_tasks,
-            completed: i + 1,
-            task: format!("Completed build for '{}'", task),
-        }).unwrap();
+    // Each task's agent builds its workspace member; progress is forwarded
+    // as the builds actually start and finish
+    let build = state.swarm.execute_plan(&plan, build_member, &cancel);
+    tokio::pin!(build);
+    let mut completed = 0;
+    let result = loop {
+        tokio::select! {
+            event = progress.recv() => match event {
+                Ok(event) => emit_progress(&app_handle, event, total_tasks, &mut completed),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break (&mut build).await,
+            },
+            result = &mut build => {
+                // Forward whatever arrived after the last poll
+                while let Ok(event) = progress.try_recv() {
+                    emit_progress(&app_handle, event, total_tasks, &mut completed);
+                }
+                break result;
+            }
+        }
+    };
+    *state.build_cancel.lock().unwrap() = None;
+    persist_swarm(&state.swarm).await;
+    if cancel.is_cancelled() {
+        return Err("cancelled: Swarm build cancelled".to_string());
     }
-    
+    result.map_err(|e| e.to_string())?;
+
     // Final completion event
     app_handle.emit("SWARM_PROGRESS", SwarmProgressPayload {
         total: total_tasks,
@@ -97,10 +198,95 @@ async fn execute_swarm_build(
     Ok(())
 }
 
+/// Cancel the swarm build in progress. Tasks not yet started are skipped and
+/// running builds are killed; `execute_swarm_build` then fails with an error
+/// starting with `cancelled:`.
+#[tauri::command]
+fn cancel_swarm_build(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    match state.build_cancel.lock().unwrap().as_ref() {
+        Some(cancel) => {
+            cancel.cancel();
+            Ok(())
+        }
+        None => Err("not_running: No swarm build is running".to_string()),
+    }
+}
+
+/// Run `cargo build` for one task of a build plan
+async fn build_member(task: String) -> opencode_core::error::Result<()> {
+    let mut command = tokio::process::Command::new("cargo");
+    // A cancelled build drops this future; don't leave cargo running
+    command.kill_on_drop(true);
+    command.arg("build");
+    if task != "root_package" {
+        command.arg("--manifest-path").arg(PathBuf::from(&task).join("Cargo.toml"));
+    }
+
+    let status = command.status().await?;
+    if !status.success() {
+        return Err(Error::Other(format!("cargo build failed for '{}'", task)));
+    }
+    Ok(())
+}
+
+/// Forward a swarm progress event to the frontend
+fn emit_progress(app_handle: &AppHandle, event: SwarmProgress, total: usize, completed: &mut usize) {
+    let task = match event {
+        SwarmProgress::TaskStarted { task, .. } => format!("Started build for '{}'", task),
+        SwarmProgress::TaskCompleted { task, .. } => {
+            *completed += 1;
+            format!("Completed build for '{}'", task)
+        }
+        SwarmProgress::TaskFailed { task, error, .. } => {
+            *completed += 1;
+            format!("Build for '{}' failed: {}", task, error)
+        }
+    };
+
+    app_handle.emit("SWARM_PROGRESS", SwarmProgressPayload {
+        total,
+        completed: *completed,
+        task,
+    }).unwrap();
+}
+
 fn main() {
-    // Create the initial state
+    // ask_stream needs the global service container
+    let initialized = Config::load(None::<&str>).and_then(opencode_core::init);
+    if let Err(e) = initialized {
+        eprintln!("Failed to initialize OpenCode: {}", e);
+    }
+
+    // Create the initial state; the default supervisor runs agents in
+    // containers, so restored agents reattach to theirs
+    let mut supervisor = AgentSupervisor::default();
+    if let Some(seconds) = opencode_core::get_service_container()
+        .ok()
+        .and_then(|container| container.config().agent_timeout_seconds)
+    {
+        supervisor = supervisor.with_agent_timeout(Duration::from_secs(seconds.into()));
+    }
+    let supervisor = Arc::new(supervisor);
+    let swarm = Arc::new(SwarmOrchestrator::new());
+    tauri::async_runtime::block_on(swarm.add_supervisor("main".to_string(), supervisor.clone()))
+        .expect("a new swarm has no supervisors");
+
+    // Bring back the agents of the previous run, reattaching to their containers
+    if let Some(path) = snapshot_path() {
+        let restored = SwarmSnapshot::load(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|snapshot| {
+                tauri::async_runtime::block_on(swarm.restore(snapshot)).map_err(|e| e.to_string())
+            });
+        if let Err(e) = restored {
+            eprintln!("Failed to restore swarm from {}: {}", path.display(), e);
+        }
+    }
+
     let state = AppState {
-        supervisor: Arc::new(Mutex::new(AgentSupervisor::new())),
+        supervisor,
+        swarm,
+        build_cancel: Mutex::new(None),
     };
 
     tauri::Builder::default()
@@ -109,7 +295,11 @@ fn main() {
             // Register our commands
             list_agents,
             spawn_agent,
+            stop_agent,
+            swarm_metrics,
+            ask_stream,
             execute_swarm_build,
+            cancel_swarm_build,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -40,12 +40,23 @@ export function SwarmMonitor() {
     try {
       await invoke('execute_swarm_build');
     } catch (e) {
-      console.error("Failed to execute swarm build:", e);
-      alert(`Error: ${e}`);
+      // A build cancelled from the Cancel button isn't an error worth reporting
+      if (!String(e).startsWith('cancelled:')) {
+        console.error("Failed to execute swarm build:", e);
+        alert(`Error: ${e}`);
+      }
       setIsRunning(false);
     }
   };
 
+  const handleCancelSwarm = async () => {
+    try {
+      await invoke('cancel_swarm_build');
+    } catch (e) {
+      console.error("Failed to cancel swarm build:", e);
+    }
+  };
+
   const percentage = progress ? (progress.completed / progress.total) * 100 : 0;
 
   return (
@@ -54,9 +65,16 @@ export function SwarmMonitor() {
         <CardTitle>Swarm Monitor</CardTitle>
       </CardHeader>
       <CardContent className="flex flex-col gap-4">
-        <Button onClick={handleStartSwarm} disabled={isRunning}>
-          {isRunning ? "Running..." : "Start Swarm Build"}
-        </Button>
+        <div className="flex gap-2">
+          <Button onClick={handleStartSwarm} disabled={isRunning}>
+            {isRunning ? "Running..." : "Start Swarm Build"}
+          </Button>
+          {isRunning && (
+            <Button variant="outline" onClick={handleCancelSwarm}>
+              Cancel
+            </Button>
+          )}
+        </div>
         {progress && (
           <div className="flex flex-col gap-2">
             <Progress value={percentage} />
@@ -51,6 +51,7 @@ describe('App Component', () => {
         persona: 'rusty',
         status: 'Running',
         branch_name: 'agent-test-agent',
+        labels: {},
       },
     ];
     
@@ -72,6 +73,7 @@ describe('App Component', () => {
         persona: 'rusty',
         status: 'Running',
         branch_name: 'agent-new-agent',
+        labels: {},
       },
     ]);
     
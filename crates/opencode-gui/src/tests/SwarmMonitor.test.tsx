@@ -53,10 +53,44 @@ describe('SwarmMonitor Component', () => {
     
     await waitFor(() => {
       expect(screen.getByText('Running...')).toBeInTheDocument();
-      expect(screen.getByRole('button')).toBeDisabled();
+      expect(screen.getByRole('button', { name: 'Running...' })).toBeDisabled();
     });
   });
 
+  it('calls cancel_swarm_build when cancel is clicked', async () => {
+    mockInvoke.mockImplementation((command: string) =>
+      command === 'execute_swarm_build'
+        ? new Promise(() => {}) // Still running
+        : Promise.resolve(undefined)
+    );
+
+    render(<SwarmMonitor />);
+
+    fireEvent.click(screen.getByText('Start Swarm Build'));
+    fireEvent.click(await screen.findByText('Cancel'));
+
+    await waitFor(() => {
+      expect(mockInvoke).toHaveBeenCalledWith('cancel_swarm_build');
+    });
+  });
+
+  it('does not report a cancelled build as an error', async () => {
+    const alertSpy = vi.spyOn(window, 'alert').mockImplementation(() => {});
+    mockInvoke.mockRejectedValue('cancelled: Swarm build cancelled');
+
+    render(<SwarmMonitor />);
+
+    fireEvent.click(screen.getByText('Start Swarm Build'));
+
+    await waitFor(() => {
+      expect(screen.getByText('Start Swarm Build')).toBeInTheDocument();
+      expect(screen.queryByText('Cancel')).not.toBeInTheDocument();
+    });
+    expect(alertSpy).not.toHaveBeenCalled();
+
+    alertSpy.mockRestore();
+  });
+
   it('displays progress when progress events are received', async () => {
     let eventCallback: any;
     mockListen.mockImplementation((event: string, callback: any) => {
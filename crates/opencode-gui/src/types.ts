@@ -5,4 +5,5 @@ export interface Agent {
   persona: string;
   status: 'Running' | 'Stopped' | { Error: string };
   branch_name: string;
+  labels: Record<string, string>;
 }
\ No newline at end of file